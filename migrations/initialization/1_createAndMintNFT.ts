@@ -5,7 +5,7 @@ import { createMint, mintTo, getAccount } from "@solana/spl-token";
 import * as fs from "fs";
 import {
   findAssociatedTokenAddress,
-  createATAWithoutCheckIx,
+  createATAIdempotentIx,
 } from "../../ts/v2/utils";
 import {
   MINT_LIST_PATH,
@@ -34,7 +34,7 @@ describe("create nft mint and mint to wallet", () => {
       proveTokenMintList.push(nftMint);
 
       ixArr.push(
-        await createATAWithoutCheckIx(
+        await createATAIdempotentIx(
           wallet.publicKey,
           proveTokenMintList[index]
         )
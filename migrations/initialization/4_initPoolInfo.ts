@@ -6,7 +6,7 @@ import { assert } from "chai";
 import { IDL as nftStakingIDL } from "../../target/types/nft_staking";
 import {
   findAssociatedTokenAddress,
-  createATAWithoutCheckIx,
+  createATAIdempotentIx,
 } from "../../ts/v2/utils";
 import * as nftFinanceSDK from "../../ts/v2";
 import { PoolInfo, RarityInfo } from "../../ts/v2/poolInfos";
@@ -98,7 +98,7 @@ describe("initialize pool info", () => {
       // proveTokenMintList = [new PublicKey(0)];
 
       ixArr.push(
-        await createATAWithoutCheckIx(
+        await createATAIdempotentIx(
           wallet.publicKey,
           proveTokenMintList[index]
         )
@@ -1,11 +1,30 @@
 use anchor_lang::prelude::*;
-use anchor_spl::associated_token::get_associated_token_address;
-use anchor_spl::token::{self, CloseAccount, Mint, TokenAccount, Transfer};
-use anchor_lang::solana_program::{hash::hash};
+use anchor_spl::associated_token::{get_associated_token_address, AssociatedToken};
+use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, Transfer};
+use anchor_lang::solana_program::{hash::{hash, hashv}, program::set_return_data, sysvar};
+use anchor_lang::system_program;
+use anchor_lang::Discriminator;
+use std::convert::TryInto;
 use nft_rarity::RarityInfo;
 
 declare_id!("NFTS4eKECWLtMmzoo2FJH7Zkoj2jxU8PJicCViyuVGh");
 
+/// Weight of one common staked NFT, boosts are expressed on the same basis point scale.
+pub const POINTS_WEIGHT_BASE: u64 = 10_000;
+/// Weight of one staked NFT by RarityInfo.rarity, tiers not listed weigh POINTS_WEIGHT_BASE.
+pub const RARITY_POINTS_WEIGHTS: [(&str, u64); 6] = [
+    ("common", 10_000),
+    ("uncommon", 12_500),
+    ("rare", 15_000),
+    ("epic", 20_000),
+    ("legendary", 30_000),
+    ("mythic", 50_000),
+];
+/// 100% in basis points.
+pub const BPS_BASE: u64 = 10_000;
+/// Scale of PoolInfo.acc_points_per_weight.
+pub const POINTS_PRECISION: u128 = 1_000_000_000_000;
+
 #[program]
 pub mod nft_staking {
     use super::*;
@@ -13,6 +32,8 @@ pub mod nft_staking {
     const PROVE_TOKEN_VAULT_PDA_SEED: &[u8] = b"prove_token_vault";
     const NFT_VAULT_PDA_SEED: &[u8] = b"nft_vault";
     const RARITY_INFO_SEED: &str = "rarity_info";
+    const MAX_SET_SIZE: usize = 8;
+    const MAX_SET_BONUS_BPS: u64 = BPS_BASE;
 
     pub fn initialize(
         ctx: Context<Initialize>,
@@ -59,25 +80,278 @@ pub mod nft_staking {
         // This field is deprecated.
         ctx.accounts.pool_info.mint_list_length = u64::from_le_bytes(ctx.accounts.rarity_info.mint_list.len().to_le_bytes());
         ctx.accounts.pool_info.total_locked = 0u64;
+        ctx.accounts.pool_info.gate_mint = Pubkey::default();
+        ctx.accounts.pool_info.gate_min_balance = 0u64;
+        ctx.accounts.pool_info.version = PoolInfo::CURRENT_VERSION;
+        ctx.accounts.pool_info.pending_admin = Pubkey::default();
+        ctx.accounts.pool_info.pauser = Pubkey::default();
+        ctx.accounts.pool_info.reward_manager = Pubkey::default();
+        ctx.accounts.pool_info.config_manager = Pubkey::default();
+        ctx.accounts.pool_info.paused = false;
+        ctx.accounts.pool_info.points_per_second = 0u64;
+        ctx.accounts.pool_info.acc_points_per_weight = 0u128;
+        ctx.accounts.pool_info.last_points_update = Clock::get()?.unix_timestamp;
+        ctx.accounts.pool_info.referral_share_bps = 0u64;
+        ctx.accounts.pool_info.referral_cap = 0u64;
+        ctx.accounts.pool_info.curve_enabled = false;
+        ctx.accounts.pool_info.curve_min_points_per_second = 0u64;
+        ctx.accounts.pool_info.curve_max_points_per_second = 0u64;
+        ctx.accounts.pool_info.listed_count = ctx.accounts.rarity_info.mint_list.len() as u64;
+        ctx.accounts.pool_info.orphaned_prove_tokens = 0u64;
+        ctx.accounts.pool_info.sft_enabled = false;
+        ctx.accounts.pool_info.prove_token_supply = ctx.accounts.rarity_info.mint_list.len() as u64;
+        ctx.accounts.pool_info.vesting_duration = 0i64;
+        ctx.accounts.pool_info.open_snapshot = Pubkey::default();
 
         token::transfer(
             ctx.accounts.transfer_prove_token_to_vault(), 
             u64::from_le_bytes(ctx.accounts.rarity_info.mint_list.len().to_le_bytes())
         )?;
 
+        emit!(PoolInitialized {
+            pool_info: ctx.accounts.pool_info.key(),
+            admin: ctx.accounts.pool_info.admin,
+            rarity_info: ctx.accounts.pool_info.rarity_info,
+            prove_token_mint: ctx.accounts.pool_info.prove_token_mint,
+            prove_token_amount: ctx.accounts.pool_info.mint_list_length,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn propose_admin(
+        ctx: Context<ProposeAdmin>, 
+    ) -> Result<()> {
+        // new_admin has to accept before the handover takes effect
+        ctx.accounts.pool_info.pending_admin = ctx.accounts.new_admin.key();
+
+        emit!(AdminProposed {
+            pool_info: ctx.accounts.pool_info.key(),
+            admin: ctx.accounts.admin.key(),
+            pending_admin: ctx.accounts.pool_info.pending_admin,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
         Ok(())
     }
 
-    pub fn update_admin(
-        ctx: Context<UpdateAdmin>, 
+    pub fn accept_admin(
+        ctx: Context<AcceptAdmin>, 
     ) -> Result<()> {
+        let old_admin = ctx.accounts.pool_info.admin;
         ctx.accounts.pool_info.admin = ctx.accounts.new_admin.key();
+        ctx.accounts.pool_info.pending_admin = Pubkey::default();
+
+        emit!(AdminUpdated {
+            pool_info: ctx.accounts.pool_info.key(),
+            old_admin,
+            new_admin: ctx.accounts.pool_info.admin,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_admin_proposal(
+        ctx: Context<CancelAdminProposal>, 
+    ) -> Result<()> {
+        let pending_admin = ctx.accounts.pool_info.pending_admin;
+        ctx.accounts.pool_info.pending_admin = Pubkey::default();
+
+        emit!(AdminProposalCancelled {
+            pool_info: ctx.accounts.pool_info.key(),
+            admin: ctx.accounts.admin.key(),
+            pending_admin,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_role(
+        ctx: Context<SetRole>,
+        role: PoolRole,
+        authority: Pubkey,
+    ) -> Result<()> {
+        // Pubkey::default() leaves the role to admin only
+        match role {
+            PoolRole::Pauser => ctx.accounts.pool_info.pauser = authority,
+            PoolRole::RewardManager => ctx.accounts.pool_info.reward_manager = authority,
+            PoolRole::ConfigManager => ctx.accounts.pool_info.config_manager = authority,
+        };
+
+        emit!(RoleUpdated {
+            pool_info: ctx.accounts.pool_info.key(),
+            role,
+            authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_paused(
+        ctx: Context<SetPaused>,
+        paused: bool,
+    ) -> Result<()> {
+        ctx.accounts.pool_info.paused = paused;
+
+        emit!(PausedUpdated {
+            pool_info: ctx.accounts.pool_info.key(),
+            authority: ctx.accounts.authority.key(),
+            paused,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_gate(
+        ctx: Context<UpdateGate>,
+        gate_mint: Pubkey,
+        gate_min_balance: u64,
+    ) -> Result<()> {
+        // Pubkey::default() as gate_mint opens the pool to everyone
+        ctx.accounts.pool_info.gate_mint = gate_mint;
+        ctx.accounts.pool_info.gate_min_balance = gate_min_balance;
+
+        emit!(GateUpdated {
+            pool_info: ctx.accounts.pool_info.key(),
+            gate_mint,
+            gate_min_balance,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_points_rate(
+        ctx: Context<UpdatePointsRate>,
+        points_per_second: u64,
+    ) -> Result<()> {
+        // Settle accrual at the old rate first
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.pool_info.checkpoint_points(now);
+        ctx.accounts.pool_info.points_per_second = points_per_second;
+
+        emit!(PointsRateUpdated {
+            pool_info: ctx.accounts.pool_info.key(),
+            points_per_second,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_rate_curve(
+        ctx: Context<UpdateRateCurve>,
+        curve_enabled: bool,
+        curve_min_points_per_second: u64,
+        curve_max_points_per_second: u64,
+    ) -> Result<()> {
+        if curve_min_points_per_second > curve_max_points_per_second {
+            return Err(ErrorCode::InvalidRateCurve.into());
+        };
+        // Settle accrual at the old rate first
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.pool_info.checkpoint_points(now);
+        ctx.accounts.pool_info.curve_enabled = curve_enabled;
+        ctx.accounts.pool_info.curve_min_points_per_second = curve_min_points_per_second;
+        ctx.accounts.pool_info.curve_max_points_per_second = curve_max_points_per_second;
+        ctx.accounts.pool_info.listed_count = ctx.accounts.rarity_info.mint_list.len() as u64;
+
+        emit!(RateCurveUpdated {
+            pool_info: ctx.accounts.pool_info.key(),
+            curve_enabled,
+            curve_min_points_per_second,
+            curve_max_points_per_second,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn spend_points(
+        ctx: Context<SpendPoints>,
+        amount: u64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.pool_info.checkpoint_points(now);
+        ctx.accounts.user_points.checkpoint(&ctx.accounts.pool_info);
+
+        if ctx.accounts.user_points.points < amount {
+            return Err(ErrorCode::InsufficientPoints.into());
+        };
+        ctx.accounts.user_points.points -= amount;
+        ctx.accounts.user_points.total_spent += amount;
+
+        emit!(PointsSpent {
+            pool_info: ctx.accounts.pool_info.key(),
+            user: ctx.accounts.user_points.user,
+            amount,
+            points: ctx.accounts.user_points.points,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_referral(
+        ctx: Context<UpdateReferral>,
+        referral_share_bps: u64,
+        referral_cap: u64,
+    ) -> Result<()> {
+        if referral_share_bps > BPS_BASE {
+            return Err(ErrorCode::InvalidReferralShare.into());
+        };
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.pool_info.checkpoint_points(now);
+        ctx.accounts.pool_info.referral_share_bps = referral_share_bps;
+        ctx.accounts.pool_info.referral_cap = referral_cap;
+
+        emit!(ReferralUpdated {
+            pool_info: ctx.accounts.pool_info.key(),
+            referral_share_bps,
+            referral_cap,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn claim_referral(
+        ctx: Context<ClaimReferral>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.pool_info.checkpoint_points(now);
+        ctx.accounts.user_points.checkpoint(&ctx.accounts.pool_info);
+        init_user_points(
+            &mut ctx.accounts.referrer_points,
+            ctx.accounts.user_points.referrer,
+            ctx.accounts.pool_info.key(),
+        );
+        ctx.accounts.referrer_points.checkpoint(&ctx.accounts.pool_info);
+
+        let amount = ctx.accounts.referrer_points.credit_referral(
+            &mut ctx.accounts.user_points,
+            ctx.accounts.pool_info.referral_cap,
+        );
+
+        emit!(ReferralClaimed {
+            pool_info: ctx.accounts.pool_info.key(),
+            user: ctx.accounts.user_points.user,
+            referrer: ctx.accounts.user_points.referrer,
+            amount,
+            timestamp: now,
+        });
 
         Ok(())
     }
 
     pub fn stake(
         ctx: Context<Stake>, 
+        referrer: Option<Pubkey>,
     ) -> Result<()> {
         let (_prove_token_authority, _prove_token_authority_bump) =
             Pubkey::find_program_address(
@@ -94,11 +368,22 @@ pub mod nft_staking {
             &[_prove_token_authority_bump]
             ];
         
+        if ctx.accounts.pool_info.paused {
+            return Err(ErrorCode::PoolPaused.into());
+        };
+        // Single NFTs only, SFT editions go through stake_sft
+        if ctx.accounts.nft_mint.decimals != 0 || ctx.accounts.nft_mint.supply != 1 {
+            return Err(ErrorCode::NotNftMint.into());
+        };
+
         // Check NFT mint is listed in MintListAccount
         if !ctx.accounts.rarity_info.mint_list.contains(&ctx.accounts.nft_mint.key()) {
             return Err(ErrorCode::MintNotFound.into());
         };
 
+        // Check user holds enough gate token, the token account is passed as remaining account
+        check_gate(&ctx.accounts.pool_info, &ctx.accounts.user.key(), ctx.remaining_accounts.first())?;
+
         msg!("transfer nft to vault");
         // Transfer NFT to Vault
         token::transfer(
@@ -111,6 +396,14 @@ pub mod nft_staking {
         ctx.accounts.nft_vault_account.user = *ctx.accounts.user.to_account_info().key;
         ctx.accounts.nft_vault_account.pool_info = *ctx.accounts.pool_info.to_account_info().key;
         ctx.accounts.nft_vault_account.nft_mint = *ctx.accounts.nft_mint.to_account_info().key;
+        ctx.accounts.nft_vault_account.set_bonus = Pubkey::default();
+        ctx.accounts.nft_vault_account.version = NftVaultAccount::CURRENT_VERSION;
+        ctx.accounts.nft_vault_account.stake_time = Clock::get()?.unix_timestamp;
+        ctx.accounts.nft_vault_account.last_raffle = Pubkey::default();
+        ctx.accounts.nft_vault_account.loan = Pubkey::default();
+        ctx.accounts.nft_vault_account.last_snapshot = Pubkey::default();
+        ctx.accounts.nft_vault_account.boosted = false;
+        ctx.accounts.nft_vault_account.guilded = false;
         
         msg!("transfer prove token to user");
         // Transfer prove token to User
@@ -120,8 +413,47 @@ pub mod nft_staking {
             1,
         )?;
 
+        // Update UserPoints, accrual so far is settled before weight changes
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.pool_info.checkpoint_points(now);
+        init_user_points(
+            &mut ctx.accounts.user_points,
+            ctx.accounts.user.key(),
+            ctx.accounts.pool_info.key(),
+        );
+        ctx.accounts.user_points.checkpoint(&ctx.accounts.pool_info);
+
+        // Referrer is recorded once, accrual before it is not shared
+        if let Some(referrer) = referrer {
+            if ctx.accounts.user_points.referrer == Pubkey::default() && referrer != ctx.accounts.user.key() {
+                ctx.accounts.user_points.referrer = referrer;
+
+                emit!(ReferrerRecorded {
+                    pool_info: ctx.accounts.pool_info.key(),
+                    user: ctx.accounts.user.key(),
+                    referrer,
+                    timestamp: now,
+                });
+            };
+        };
+
+        ctx.accounts.user_points.staked_count += 1;
+        let points_weight = rarity_points_weight(&ctx.accounts.rarity_info.rarity);
+        ctx.accounts.user_points.weight += points_weight;
+        ctx.accounts.nft_vault_account.points_weight = points_weight;
+
         // Update PoolInfo
         ctx.accounts.pool_info.total_locked += 1;
+        ctx.accounts.pool_info.listed_count = ctx.accounts.rarity_info.mint_list.len() as u64;
+
+        emit!(Staked {
+            pool_info: ctx.accounts.pool_info.key(),
+            user: ctx.accounts.user.key(),
+            nft_mint: ctx.accounts.nft_mint.key(),
+            amount: 1,
+            total_locked: ctx.accounts.pool_info.total_locked,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
 
         Ok(())
     }
@@ -129,6 +461,25 @@ pub mod nft_staking {
     pub fn unstake(
         ctx: Context<Unstake>
     ) -> Result<()> {
+        // Vaults staked under an older layout are migrated here, no admin pass needed
+        let nft_vault = migrate_nft_vault(
+            &ctx.accounts.nft_vault_account,
+            &ctx.accounts.user,
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        if nft_vault.user != ctx.accounts.user.key() {
+            return Err(ErrorCode::NftVaultMismatch.into());
+        };
+        if nft_vault.loan != Pubkey::default() {
+            return Err(ErrorCode::LoanOutstanding.into());
+        };
+        if nft_vault.boosted {
+            return Err(ErrorCode::BoosterAttached.into());
+        };
+        if nft_vault.guilded {
+            return Err(ErrorCode::PositionInGuild.into());
+        };
+
         // Generate user specific nft vault
         let (_nft_vault_account, _nft_vault_account_bump) =
             Pubkey::find_program_address(
@@ -155,6 +506,16 @@ pub mod nft_staking {
                 ctx.program_id
             );
 
+        // Dissolve set bonus, SetBonus and the other NftVaultAccounts of the set are passed as remaining accounts
+        if nft_vault.set_bonus != Pubkey::default() {
+            dissolve_set_bonus(
+                &ctx.accounts.nft_vault_account.key(),
+                &nft_vault,
+                ctx.remaining_accounts,
+                &ctx.accounts.user,
+            )?;
+        };
+
         // Transfer NFT back to user
         token::transfer(
             ctx.accounts.transfer_nft_to_user()
@@ -175,234 +536,5595 @@ pub mod nft_staking {
             1
         )?;
         
+        // Update UserPoints of the staker, positions staked before points have no weight
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.pool_info.checkpoint_points(now);
+        init_user_points(
+            &mut ctx.accounts.user_points,
+            nft_vault.user,
+            ctx.accounts.pool_info.key(),
+        );
+        ctx.accounts.user_points.checkpoint(&ctx.accounts.pool_info);
+        ctx.accounts.user_points.staked_count = ctx.accounts.user_points.staked_count.saturating_sub(1);
+        ctx.accounts.user_points.weight -= nft_vault.points_weight;
+
         // Update PoolInfo
         ctx.accounts.pool_info.total_locked -= 1;
+        ctx.accounts.pool_info.listed_count = ctx.accounts.rarity_info.mint_list.len() as u64;
+
+        // Close NftVaultAccount, rent goes back to user
+        close_program_account(&ctx.accounts.nft_vault_account, &ctx.accounts.user)?;
+
+        emit!(Unstaked {
+            pool_info: ctx.accounts.pool_info.key(),
+            user: ctx.accounts.user.key(),
+            nft_mint: ctx.accounts.nft_mint.key(),
+            amount: 1,
+            total_locked: ctx.accounts.pool_info.total_locked,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        if ctx.accounts.recipient.key() != ctx.accounts.user.key() {
+            emit!(UnstakeRedirected {
+                pool_info: ctx.accounts.pool_info.key(),
+                user: ctx.accounts.user.key(),
+                recipient: ctx.accounts.recipient.key(),
+                nft_mint: ctx.accounts.nft_mint.key(),
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+        };
 
         Ok(())
     }
-}
 
+    pub fn force_unstake(
+        ctx: Context<ForceUnstake>,
+    ) -> Result<()> {
+        // Loan has to be repaid or seized, booster detached and guild position withdrawn first
+        if ctx.accounts.nft_vault_account.loan != Pubkey::default() {
+            return Err(ErrorCode::LoanOutstanding.into());
+        };
+        if ctx.accounts.nft_vault_account.boosted {
+            return Err(ErrorCode::BoosterAttached.into());
+        };
+        if ctx.accounts.nft_vault_account.guilded {
+            return Err(ErrorCode::PositionInGuild.into());
+        };
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut, signer)]
-    pub admin: AccountInfo<'info>,
-    pub prove_token_mint: Account<'info, Mint>, // prove token mint
-    #[account(mut)]
-    pub admin_prove_token_account: Box<Account<'info, TokenAccount>>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut, seeds = [pool_info.key().as_ref(), b"prove_token_vault".as_ref()], bump)]
-    pub prove_token_authority: AccountInfo<'info>, 
-    #[account(mut, constraint = prove_token_mint.to_account_info().key() == prove_token_vault.mint)]
-    pub prove_token_vault: Box<Account<'info, TokenAccount>>, 
-    #[account(
-        init, 
-        seeds = [rarity_info.key().as_ref(), b"pool_info".as_ref()],
-        bump, 
-        space = 184,    // 8 + 32 * 5 + 8 + 8
-        payer = admin
-    )]
-    pub pool_info: Box<Account<'info, PoolInfo>>,
-    pub rarity_info: Box<Account<'info, RarityInfo>>, 
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    pub rarity_program: AccountInfo<'info>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    pub system_program: AccountInfo<'info>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    pub token_program: AccountInfo<'info>,
-}
+        let nft_vault_account_bump = *ctx.bumps.get("nft_vault_account").unwrap();
+        let nft_vault_account_seeds = &[
+            &ctx.accounts.nft_mint.to_account_info().key.to_bytes(),
+            &ctx.accounts.pool_info.to_account_info().key.to_bytes(),
+            NFT_VAULT_PDA_SEED,
+            &[nft_vault_account_bump]
+            ];
 
-#[derive(Accounts)]
-pub struct UpdateAdmin<'info> {
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(signer)]
-    pub admin: AccountInfo<'info>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    pub new_admin: AccountInfo<'info>,
-    #[account(
-        mut,  
-        constraint = pool_info.admin == admin.key()
-    )]
-    pub pool_info: Box<Account<'info, PoolInfo>>, 
-}
+        // Dissolve set bonus, SetBonus and the other NftVaultAccounts of the set are passed as remaining accounts
+        if ctx.accounts.nft_vault_account.set_bonus != Pubkey::default() {
+            dissolve_set_bonus(
+                &ctx.accounts.nft_vault_account.key(),
+                &ctx.accounts.nft_vault_account,
+                ctx.remaining_accounts,
+                &ctx.accounts.owner,
+            )?;
+            ctx.accounts.nft_vault_account.set_bonus = Pubkey::default();
+        };
 
-#[derive(Accounts)]
-pub struct Stake<'info> {
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut, signer)]
-    pub user: AccountInfo<'info>,
-    #[account(
-        mut, 
-        seeds = [rarity_info.key().as_ref(), b"pool_info".as_ref()],
-        bump,
-        constraint = prove_token_mint.to_account_info().key() == pool_info.prove_token_mint
-    )]
-    pub pool_info: Box<Account<'info, PoolInfo>>, 
-    pub prove_token_mint: Box<Account<'info, Mint>>,
-    pub nft_mint: Box<Account<'info, Mint>>, 
-    #[account(constraint = pool_info.rarity_info == rarity_info.key())]
-    pub rarity_info: Box<Account<'info, RarityInfo>>, 
-    #[account(mut, 
-        constraint = nft_mint.to_account_info().key() == user_nft_account.mint, 
-        constraint = user.to_account_info().key() == user_nft_account.owner)]
-    pub user_nft_account: Box<Account<'info, TokenAccount>>, 
-    #[account(mut, 
-        constraint = nft_mint.to_account_info().key() == nft_vault_ata.mint, 
-        constraint = nft_vault_account.to_account_info().key() == nft_vault_ata.owner)]
-    pub nft_vault_ata: Box<Account<'info, TokenAccount>>,
-    #[account(mut, constraint = prove_token_mint.to_account_info().key() == user_prove_token_account.mint)]
-    pub user_prove_token_account: Box<Account<'info, TokenAccount>>, 
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut, seeds = [pool_info.key().as_ref(), b"prove_token_vault".as_ref()], bump, 
-        constraint = pool_info.prove_token_authority == prove_token_authority.to_account_info().key())]
-    pub prove_token_authority: AccountInfo<'info>, 
-    #[account(mut, 
-        constraint = prove_token_mint.to_account_info().key() == prove_token_vault.mint, 
-        constraint = prove_token_vault.owner == prove_token_authority.to_account_info().key())]
-    pub prove_token_vault: Box<Account<'info, TokenAccount>>, 
-    #[account(
-        init,
-        seeds = [
-            nft_mint.key().as_ref(), 
-            pool_info.key().as_ref(),
-            b"nft_vault".as_ref()
-            ], 
-        bump,
-        payer = user,
-        space =  104 // 8 + 32 + 32 + 32
-    )]
-    pub nft_vault_account: Box<Account<'info, NftVaultAccount>>, 
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    pub system_program: AccountInfo<'info>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    pub token_program: AccountInfo<'info>,
-}
+        // Transfer NFT back to owner
+        token::transfer(
+            ctx.accounts.transfer_nft_to_owner()
+            .with_signer(&[&nft_vault_account_seeds[..]]),
+            1
+        )?;
 
-#[derive(Accounts)]
-pub struct Unstake<'info> {
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut, signer)]
-    pub user: AccountInfo<'info>,
-    #[account(
-        mut, 
-        seeds = [rarity_info.key().as_ref(), b"pool_info".as_ref()],
-        bump,
-        constraint = prove_token_mint.to_account_info().key() == pool_info.prove_token_mint
-    )]
-    pub pool_info: Box<Account<'info, PoolInfo>>, 
-    pub prove_token_mint: Account<'info, Mint>,
-    pub nft_mint: Account<'info, Mint>, 
-    #[account(constraint = pool_info.rarity_info == rarity_info.key())]
-    pub rarity_info: Box<Account<'info, RarityInfo>>, 
-    #[account(mut, 
-        constraint = nft_mint.to_account_info().key() == user_nft_account.mint, 
-        constraint = user.to_account_info().key() == user_nft_account.owner)]
-    pub user_nft_account: Box<Account<'info, TokenAccount>>,
-    #[account(mut, 
-        constraint = nft_mint.to_account_info().key() == nft_vault_ata.mint, 
-        constraint = nft_vault_account.to_account_info().key() == nft_vault_ata.owner,
-    )]
-    pub nft_vault_ata: Box<Account<'info, TokenAccount>>, 
-    #[account(mut, constraint = prove_token_mint.to_account_info().key() == user_prove_token_account.mint)]
-    pub user_prove_token_account: Box<Account<'info, TokenAccount>>, 
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut, seeds = [pool_info.key().as_ref(), b"prove_token_vault".as_ref()], bump,
-    constraint = pool_info.prove_token_authority == prove_token_authority.to_account_info().key())]
-    pub prove_token_authority: AccountInfo<'info>, 
-    #[account(mut, 
-        constraint = prove_token_mint.to_account_info().key() == prove_token_vault.mint, 
-        constraint = prove_token_vault.owner == prove_token_authority.to_account_info().key())]
-    pub prove_token_vault: Box<Account<'info, TokenAccount>>, 
-    #[account(
-        mut,
-        seeds = [
-            nft_mint.key().as_ref(), 
-            pool_info.key().as_ref(),
-            b"nft_vault".as_ref()
-            ], 
-        bump,
-        close = user
-    )]
-    pub nft_vault_account: Box<Account<'info, NftVaultAccount>>, 
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    pub token_program: AccountInfo<'info>,
-}
+        // Close nft_vault_ata, rent goes back to the owner who paid it
+        token::close_account(
+            ctx.accounts
+                .close_nft_vault_ata()
+                .with_signer(&[&nft_vault_account_seeds[..]]),
+        )?;
 
-// program account
-#[account]
-pub struct PoolInfo {
-    pub admin: Pubkey, 
-    pub prove_token_authority: Pubkey, 
-    pub prove_token_vault: Pubkey,
-    pub prove_token_mint: Pubkey, 
-    pub rarity_info: Pubkey, 
+        // Update UserPoints of the owner, positions staked before points have no weight
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.pool_info.checkpoint_points(now);
+        init_user_points(
+            &mut ctx.accounts.user_points,
+            ctx.accounts.owner.key(),
+            ctx.accounts.pool_info.key(),
+        );
+        ctx.accounts.user_points.checkpoint(&ctx.accounts.pool_info);
+        ctx.accounts.user_points.staked_count = ctx.accounts.user_points.staked_count.saturating_sub(1);
+        ctx.accounts.user_points.weight -= ctx.accounts.nft_vault_account.points_weight;
+
+        // Prove token stays with its holder, unstake is bound to NftVaultAccount.user so it only
+        // stands in for the holder's own positions and can't redeem another staker's vault
+        ctx.accounts.pool_info.total_locked -= 1;
+        ctx.accounts.pool_info.orphaned_prove_tokens += 1;
+
+        emit!(ForceUnstaked {
+            pool_info: ctx.accounts.pool_info.key(),
+            admin: ctx.accounts.admin.key(),
+            user: ctx.accounts.owner.key(),
+            nft_mint: ctx.accounts.nft_mint.key(),
+            amount: 1,
+            total_locked: ctx.accounts.pool_info.total_locked,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn force_unstake_sft(
+        ctx: Context<ForceUnstakeSft>,
+    ) -> Result<()> {
+        let amount = ctx.accounts.sft_vault_account.amount;
+        let sft_vault_account_bump = *ctx.bumps.get("sft_vault_account").unwrap();
+        let sft_vault_account_seeds = &[
+            &ctx.accounts.nft_mint.to_account_info().key.to_bytes(),
+            &ctx.accounts.pool_info.to_account_info().key.to_bytes(),
+            &ctx.accounts.owner.key.to_bytes(),
+            b"sft_vault".as_ref(),
+            &[sft_vault_account_bump]
+            ];
+
+        // Transfer the whole position back to owner
+        token::transfer(
+            ctx.accounts.transfer_sft_to_owner()
+            .with_signer(&[&sft_vault_account_seeds[..]]),
+            amount
+        )?;
+
+        // Close sft_vault_ata, rent goes back to the owner who paid it
+        token::close_account(
+            ctx.accounts
+                .close_sft_vault_ata()
+                .with_signer(&[&sft_vault_account_seeds[..]]),
+        )?;
+
+        // Update UserPoints of the owner
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.pool_info.checkpoint_points(now);
+        ctx.accounts.user_points.checkpoint(&ctx.accounts.pool_info);
+        ctx.accounts.user_points.staked_count = ctx.accounts.user_points.staked_count.saturating_sub(1);
+        ctx.accounts.user_points.weight -= ctx.accounts.sft_vault_account.points_weight;
+
+        // Prove tokens stay with their holders as for force_unstake
+        ctx.accounts.pool_info.total_locked -= amount;
+        ctx.accounts.pool_info.orphaned_prove_tokens += amount;
+
+        emit!(ForceUnstaked {
+            pool_info: ctx.accounts.pool_info.key(),
+            admin: ctx.accounts.admin.key(),
+            user: ctx.accounts.owner.key(),
+            nft_mint: ctx.accounts.nft_mint.key(),
+            amount,
+            total_locked: ctx.accounts.pool_info.total_locked,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn force_unstake_compressed<'info>(
+        ctx: Context<'_, '_, '_, 'info, ForceUnstakeCompressed<'info>>,
+        leaf: CompressedLeaf,
+    ) -> Result<()> {
+        if leaf.nonce != ctx.accounts.cnft_vault_account.nonce {
+            return Err(ErrorCode::CnftVaultMismatch.into());
+        };
+
+        let cnft_vault_account_bump = *ctx.bumps.get("cnft_vault_account").unwrap();
+        let cnft_vault_account_seeds = &[
+            ctx.accounts.merkle_tree.key.as_ref(),
+            &leaf.nonce.to_le_bytes(),
+            ctx.accounts.pool_info.to_account_info().key.as_ref(),
+            b"cnft_vault".as_ref(),
+            &[cnft_vault_account_bump]
+            ];
+
+        // Transfer leaf back to owner, the position PDA signs as leaf owner and delegate
+        bubblegum::transfer(
+            bubblegum::TransferAccounts {
+                tree_authority: ctx.accounts.tree_authority.clone(),
+                leaf_owner: ctx.accounts.cnft_vault_account.to_account_info(),
+                leaf_delegate: ctx.accounts.cnft_vault_account.to_account_info(),
+                new_leaf_owner: ctx.accounts.owner.clone(),
+                merkle_tree: ctx.accounts.merkle_tree.clone(),
+                log_wrapper: ctx.accounts.log_wrapper.clone(),
+                compression_program: ctx.accounts.compression_program.clone(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                bubblegum_program: ctx.accounts.bubblegum_program.clone(),
+            },
+            ctx.remaining_accounts,
+            &leaf,
+            &[&cnft_vault_account_seeds[..]],
+        )?;
+
+        // Update UserPoints of the owner
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.pool_info.checkpoint_points(now);
+        ctx.accounts.user_points.checkpoint(&ctx.accounts.pool_info);
+        ctx.accounts.user_points.staked_count = ctx.accounts.user_points.staked_count.saturating_sub(1);
+        ctx.accounts.user_points.weight -= ctx.accounts.cnft_vault_account.points_weight;
+
+        // Prove token stays with its holder as for force_unstake
+        ctx.accounts.pool_info.total_locked -= 1;
+        ctx.accounts.pool_info.orphaned_prove_tokens += 1;
+
+        emit!(ForceUnstaked {
+            pool_info: ctx.accounts.pool_info.key(),
+            admin: ctx.accounts.admin.key(),
+            user: ctx.accounts.owner.key(),
+            nft_mint: ctx.accounts.cnft_vault_account.asset_id,
+            amount: 1,
+            total_locked: ctx.accounts.pool_info.total_locked,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn migrate_position(
+        ctx: Context<MigratePosition>,
+    ) -> Result<()> {
+        if ctx.accounts.new_pool_info.paused {
+            return Err(ErrorCode::PoolPaused.into());
+        };
+        // Loans, set bonuses, boosters and guilds are bound to the old pool
+        if ctx.accounts.old_nft_vault_account.loan != Pubkey::default() {
+            return Err(ErrorCode::LoanOutstanding.into());
+        };
+        if ctx.accounts.old_nft_vault_account.boosted {
+            return Err(ErrorCode::BoosterAttached.into());
+        };
+        if ctx.accounts.old_nft_vault_account.guilded {
+            return Err(ErrorCode::PositionInGuild.into());
+        };
+        if ctx.accounts.old_nft_vault_account.set_bonus != Pubkey::default() {
+            return Err(ErrorCode::NftAlreadyInSet.into());
+        };
+        if !ctx.accounts.new_rarity_info.mint_list.contains(&ctx.accounts.nft_mint.key()) {
+            return Err(ErrorCode::MintNotFound.into());
+        };
+        check_gate(&ctx.accounts.new_pool_info, &ctx.accounts.user.key(), ctx.remaining_accounts.first())?;
+
+        let old_nft_vault_account_bump = *ctx.bumps.get("old_nft_vault_account").unwrap();
+        let old_nft_vault_account_seeds = &[
+            &ctx.accounts.nft_mint.to_account_info().key.to_bytes(),
+            &ctx.accounts.old_pool_info.to_account_info().key.to_bytes(),
+            NFT_VAULT_PDA_SEED,
+            &[old_nft_vault_account_bump]
+            ];
+        let new_prove_token_authority_bump = *ctx.bumps.get("new_prove_token_authority").unwrap();
+        let new_prove_token_authority_seeds = &[
+            &ctx.accounts.new_pool_info.to_account_info().key.to_bytes(),
+            PROVE_TOKEN_VAULT_PDA_SEED,
+            &[new_prove_token_authority_bump]
+            ];
+
+        // Move NFT between vaults and close the old one
+        token::transfer(
+            ctx.accounts.transfer_nft_to_new_vault()
+            .with_signer(&[&old_nft_vault_account_seeds[..]]),
+            1
+        )?;
+        token::close_account(
+            ctx.accounts
+                .close_old_nft_vault_ata()
+                .with_signer(&[&old_nft_vault_account_seeds[..]]),
+        )?;
+
+        // Swap old prove token for new one
+        token::transfer(
+            ctx.accounts.transfer_old_prove_token_to_vault(),
+            1
+        )?;
+        token::transfer(
+            ctx.accounts.transfer_new_prove_token_to_user()
+            .with_signer(&[&new_prove_token_authority_seeds[..]]),
+            1
+        )?;
+
+        // Stake time and raffle entry carry over
+        ctx.accounts.new_nft_vault_account.user = ctx.accounts.user.key();
+        ctx.accounts.new_nft_vault_account.pool_info = ctx.accounts.new_pool_info.key();
+        ctx.accounts.new_nft_vault_account.nft_mint = ctx.accounts.nft_mint.key();
+        ctx.accounts.new_nft_vault_account.set_bonus = Pubkey::default();
+        ctx.accounts.new_nft_vault_account.boosted = false;
+        ctx.accounts.new_nft_vault_account.guilded = false;
+        ctx.accounts.new_nft_vault_account.version = NftVaultAccount::CURRENT_VERSION;
+        ctx.accounts.new_nft_vault_account.stake_time = ctx.accounts.old_nft_vault_account.stake_time;
+        ctx.accounts.new_nft_vault_account.last_raffle = ctx.accounts.old_nft_vault_account.last_raffle;
+        ctx.accounts.new_nft_vault_account.loan = Pubkey::default();
+        ctx.accounts.new_nft_vault_account.last_snapshot = Pubkey::default();
+
+        // Settle points in both pools before weights move
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.old_pool_info.checkpoint_points(now);
+        init_user_points(
+            &mut ctx.accounts.old_user_points,
+            ctx.accounts.old_nft_vault_account.user,
+            ctx.accounts.old_pool_info.key(),
+        );
+        ctx.accounts.old_user_points.checkpoint(&ctx.accounts.old_pool_info);
+        ctx.accounts.old_user_points.staked_count = ctx.accounts.old_user_points.staked_count.saturating_sub(1);
+        ctx.accounts.old_user_points.weight -= ctx.accounts.old_nft_vault_account.points_weight;
+
+        ctx.accounts.new_pool_info.checkpoint_points(now);
+        init_user_points(
+            &mut ctx.accounts.new_user_points,
+            ctx.accounts.user.key(),
+            ctx.accounts.new_pool_info.key(),
+        );
+        ctx.accounts.new_user_points.checkpoint(&ctx.accounts.new_pool_info);
+        ctx.accounts.new_user_points.staked_count += 1;
+        let points_weight = rarity_points_weight(&ctx.accounts.new_rarity_info.rarity);
+        ctx.accounts.new_user_points.weight += points_weight;
+        ctx.accounts.new_nft_vault_account.points_weight = points_weight;
+
+        // Update PoolInfo of both pools
+        ctx.accounts.old_pool_info.total_locked -= 1;
+        ctx.accounts.old_pool_info.listed_count = ctx.accounts.old_rarity_info.mint_list.len() as u64;
+        ctx.accounts.new_pool_info.total_locked += 1;
+        ctx.accounts.new_pool_info.listed_count = ctx.accounts.new_rarity_info.mint_list.len() as u64;
+
+        emit!(PositionMigrated {
+            old_pool_info: ctx.accounts.old_pool_info.key(),
+            new_pool_info: ctx.accounts.new_pool_info.key(),
+            user: ctx.accounts.user.key(),
+            nft_mint: ctx.accounts.nft_mint.key(),
+            stake_time: ctx.accounts.new_nft_vault_account.stake_time,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn stake_compressed<'info>(
+        ctx: Context<'_, '_, '_, 'info, StakeCompressed<'info>>,
+        leaf: CompressedLeaf,
+        referrer: Option<Pubkey>,
+    ) -> Result<()> {
+        let (_prove_token_authority, _prove_token_authority_bump) =
+            Pubkey::find_program_address(
+                &[
+                        &ctx.accounts.pool_info.to_account_info().key.to_bytes(), 
+                        PROVE_TOKEN_VAULT_PDA_SEED
+                    ], 
+                ctx.program_id
+            );
+        
+        let _prove_token_authority_seeds = &[
+            &ctx.accounts.pool_info.to_account_info().key.to_bytes(), 
+            PROVE_TOKEN_VAULT_PDA_SEED, 
+            &[_prove_token_authority_bump]
+            ];
+
+        if ctx.accounts.pool_info.paused {
+            return Err(ErrorCode::PoolPaused.into());
+        };
+
+        // Asset id takes the place of the mint in RarityInfo mint list
+        let asset_id = bubblegum::asset_id(&ctx.accounts.merkle_tree.key(), leaf.nonce);
+        if !ctx.accounts.rarity_info.mint_list.contains(&asset_id) {
+            return Err(ErrorCode::MintNotFound.into());
+        };
+
+        // Gate token account comes first in remaining accounts when gated, proof nodes follow
+        let mut proof = ctx.remaining_accounts;
+        if ctx.accounts.pool_info.gate_mint != Pubkey::default() {
+            check_gate(&ctx.accounts.pool_info, &ctx.accounts.user.key(), ctx.remaining_accounts.first())?;
+            proof = &ctx.remaining_accounts[1..];
+        };
+
+        // Bubblegum verifies the leaf against the tree root through account compression
+        bubblegum::transfer(
+            bubblegum::TransferAccounts {
+                tree_authority: ctx.accounts.tree_authority.clone(),
+                leaf_owner: ctx.accounts.user.to_account_info(),
+                leaf_delegate: ctx.accounts.leaf_delegate.clone(),
+                new_leaf_owner: ctx.accounts.cnft_vault_account.to_account_info(),
+                merkle_tree: ctx.accounts.merkle_tree.clone(),
+                log_wrapper: ctx.accounts.log_wrapper.clone(),
+                compression_program: ctx.accounts.compression_program.clone(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                bubblegum_program: ctx.accounts.bubblegum_program.clone(),
+            },
+            proof,
+            &leaf,
+            &[],
+        )?;
+
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.cnft_vault_account.user = ctx.accounts.user.key();
+        ctx.accounts.cnft_vault_account.pool_info = ctx.accounts.pool_info.key();
+        ctx.accounts.cnft_vault_account.asset_id = asset_id;
+        ctx.accounts.cnft_vault_account.merkle_tree = ctx.accounts.merkle_tree.key();
+        ctx.accounts.cnft_vault_account.nonce = leaf.nonce;
+        ctx.accounts.cnft_vault_account.stake_time = now;
+        ctx.accounts.cnft_vault_account.version = CnftVaultAccount::CURRENT_VERSION;
+
+        // Transfer prove token to User
+        token::transfer(
+            ctx.accounts.transfer_prove_token_to_user()
+            .with_signer(&[&_prove_token_authority_seeds[..]]),
+            1,
+        )?;
+
+        // Update UserPoints, accrual so far is settled before weight changes
+        ctx.accounts.pool_info.checkpoint_points(now);
+        init_user_points(
+            &mut ctx.accounts.user_points,
+            ctx.accounts.user.key(),
+            ctx.accounts.pool_info.key(),
+        );
+        ctx.accounts.user_points.checkpoint(&ctx.accounts.pool_info);
+
+        // Referrer is recorded once, accrual before it is not shared
+        if let Some(referrer) = referrer {
+            if ctx.accounts.user_points.referrer == Pubkey::default() && referrer != ctx.accounts.user.key() {
+                ctx.accounts.user_points.referrer = referrer;
+
+                emit!(ReferrerRecorded {
+                    pool_info: ctx.accounts.pool_info.key(),
+                    user: ctx.accounts.user.key(),
+                    referrer,
+                    timestamp: now,
+                });
+            };
+        };
+
+        ctx.accounts.user_points.staked_count += 1;
+        let points_weight = rarity_points_weight(&ctx.accounts.rarity_info.rarity);
+        ctx.accounts.user_points.weight += points_weight;
+        ctx.accounts.cnft_vault_account.points_weight = points_weight;
+
+        // Update PoolInfo
+        ctx.accounts.pool_info.total_locked += 1;
+        ctx.accounts.pool_info.listed_count = ctx.accounts.rarity_info.mint_list.len() as u64;
+
+        emit!(Staked {
+            pool_info: ctx.accounts.pool_info.key(),
+            user: ctx.accounts.user.key(),
+            nft_mint: asset_id,
+            amount: 1,
+            total_locked: ctx.accounts.pool_info.total_locked,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn unstake_compressed<'info>(
+        ctx: Context<'_, '_, '_, 'info, UnstakeCompressed<'info>>,
+        leaf: CompressedLeaf,
+    ) -> Result<()> {
+        if leaf.nonce != ctx.accounts.cnft_vault_account.nonce {
+            return Err(ErrorCode::CnftVaultMismatch.into());
+        };
+
+        let cnft_vault_account_bump = *ctx.bumps.get("cnft_vault_account").unwrap();
+        let cnft_vault_account_seeds = &[
+            ctx.accounts.merkle_tree.key.as_ref(),
+            &leaf.nonce.to_le_bytes(),
+            ctx.accounts.pool_info.to_account_info().key.as_ref(),
+            b"cnft_vault".as_ref(),
+            &[cnft_vault_account_bump]
+            ];
+
+        // Transfer leaf back to user, the position PDA signs as leaf owner and delegate
+        bubblegum::transfer(
+            bubblegum::TransferAccounts {
+                tree_authority: ctx.accounts.tree_authority.clone(),
+                leaf_owner: ctx.accounts.cnft_vault_account.to_account_info(),
+                leaf_delegate: ctx.accounts.cnft_vault_account.to_account_info(),
+                new_leaf_owner: ctx.accounts.user.to_account_info(),
+                merkle_tree: ctx.accounts.merkle_tree.clone(),
+                log_wrapper: ctx.accounts.log_wrapper.clone(),
+                compression_program: ctx.accounts.compression_program.clone(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                bubblegum_program: ctx.accounts.bubblegum_program.clone(),
+            },
+            ctx.remaining_accounts,
+            &leaf,
+            &[&cnft_vault_account_seeds[..]],
+        )?;
+
+        // Transfer prove token back to vault
+        token::transfer(
+            ctx.accounts.transfer_prove_token_to_vault(), 
+            1
+        )?;
+
+        // Update UserPoints of the staker
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.pool_info.checkpoint_points(now);
+        ctx.accounts.user_points.checkpoint(&ctx.accounts.pool_info);
+        ctx.accounts.user_points.staked_count = ctx.accounts.user_points.staked_count.saturating_sub(1);
+        ctx.accounts.user_points.weight -= ctx.accounts.cnft_vault_account.points_weight;
+
+        // Update PoolInfo
+        ctx.accounts.pool_info.total_locked -= 1;
+        ctx.accounts.pool_info.listed_count = ctx.accounts.rarity_info.mint_list.len() as u64;
+
+        emit!(Unstaked {
+            pool_info: ctx.accounts.pool_info.key(),
+            user: ctx.accounts.user.key(),
+            nft_mint: ctx.accounts.cnft_vault_account.asset_id,
+            amount: 1,
+            total_locked: ctx.accounts.pool_info.total_locked,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_sft(
+        ctx: Context<UpdateSft>,
+        sft_enabled: bool,
+        fund_amount: u64,
+    ) -> Result<()> {
+        // SFT stakes draw more than one prove token per listed mint
+        if fund_amount > 0 {
+            token::transfer(
+                ctx.accounts.transfer_prove_token_to_vault(),
+                fund_amount
+            )?;
+            ctx.accounts.pool_info.prove_token_supply += fund_amount;
+        };
+        ctx.accounts.pool_info.sft_enabled = sft_enabled;
+
+        emit!(SftUpdated {
+            pool_info: ctx.accounts.pool_info.key(),
+            sft_enabled,
+            fund_amount,
+            prove_token_supply: ctx.accounts.pool_info.prove_token_supply,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn stake_sft(
+        ctx: Context<StakeSft>,
+        amount: u64,
+        referrer: Option<Pubkey>,
+    ) -> Result<()> {
+        let (_prove_token_authority, _prove_token_authority_bump) =
+            Pubkey::find_program_address(
+                &[
+                        &ctx.accounts.pool_info.to_account_info().key.to_bytes(), 
+                        PROVE_TOKEN_VAULT_PDA_SEED
+                    ], 
+                ctx.program_id
+            );
+        
+        let _prove_token_authority_seeds = &[
+            &ctx.accounts.pool_info.to_account_info().key.to_bytes(), 
+            PROVE_TOKEN_VAULT_PDA_SEED, 
+            &[_prove_token_authority_bump]
+            ];
+
+        if ctx.accounts.pool_info.paused {
+            return Err(ErrorCode::PoolPaused.into());
+        };
+        if !ctx.accounts.pool_info.sft_enabled {
+            return Err(ErrorCode::SftNotEnabled.into());
+        };
+        // SFTs are whole editions with more than one in supply, single NFTs go through stake
+        if ctx.accounts.nft_mint.decimals != 0 || ctx.accounts.nft_mint.supply <= 1 {
+            return Err(ErrorCode::NotSftMint.into());
+        };
+        if amount == 0 {
+            return Err(ErrorCode::InvalidSftAmount.into());
+        };
+
+        // Check NFT mint is listed in MintListAccount
+        if !ctx.accounts.rarity_info.mint_list.contains(&ctx.accounts.nft_mint.key()) {
+            return Err(ErrorCode::MintNotFound.into());
+        };
+
+        // Check user holds enough gate token, the token account is passed as remaining account
+        check_gate(&ctx.accounts.pool_info, &ctx.accounts.user.key(), ctx.remaining_accounts.first())?;
+
+        // Transfer SFT to Vault
+        token::transfer(
+            ctx.accounts.transfer_sft_to_vault(),
+            amount,
+        )?;
+
+        // Position is kept per user, stake time is the first stake
+        let now = Clock::get()?.unix_timestamp;
+        let new_position = ctx.accounts.sft_vault_account.user == Pubkey::default();
+        if new_position {
+            ctx.accounts.sft_vault_account.user = ctx.accounts.user.key();
+            ctx.accounts.sft_vault_account.pool_info = ctx.accounts.pool_info.key();
+            ctx.accounts.sft_vault_account.nft_mint = ctx.accounts.nft_mint.key();
+            ctx.accounts.sft_vault_account.stake_time = now;
+            ctx.accounts.sft_vault_account.version = SftVaultAccount::CURRENT_VERSION;
+        };
+        ctx.accounts.sft_vault_account.amount += amount;
+
+        // Transfer prove token to User
+        token::transfer(
+            ctx.accounts.transfer_prove_token_to_user()
+            .with_signer(&[&_prove_token_authority_seeds[..]]),
+            amount,
+        )?;
+
+        // Update UserPoints, accrual so far is settled before weight changes
+        ctx.accounts.pool_info.checkpoint_points(now);
+        init_user_points(
+            &mut ctx.accounts.user_points,
+            ctx.accounts.user.key(),
+            ctx.accounts.pool_info.key(),
+        );
+        ctx.accounts.user_points.checkpoint(&ctx.accounts.pool_info);
+
+        // Referrer is recorded once, accrual before it is not shared
+        if let Some(referrer) = referrer {
+            if ctx.accounts.user_points.referrer == Pubkey::default() && referrer != ctx.accounts.user.key() {
+                ctx.accounts.user_points.referrer = referrer;
+
+                emit!(ReferrerRecorded {
+                    pool_info: ctx.accounts.pool_info.key(),
+                    user: ctx.accounts.user.key(),
+                    referrer,
+                    timestamp: now,
+                });
+            };
+        };
+
+        // staked_count counts positions, one per SFT mint whatever the amount
+        if new_position {
+            ctx.accounts.user_points.staked_count += 1;
+        };
+        let points_weight = sft_points_weight(amount, &ctx.accounts.rarity_info.rarity)?;
+        ctx.accounts.user_points.weight = ctx.accounts.user_points.weight
+            .checked_add(points_weight)
+            .ok_or(ErrorCode::MathOverflow)?;
+        ctx.accounts.sft_vault_account.points_weight = ctx.accounts.sft_vault_account.points_weight
+            .checked_add(points_weight)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        // Update PoolInfo
+        ctx.accounts.pool_info.total_locked += amount;
+        ctx.accounts.pool_info.listed_count = ctx.accounts.rarity_info.mint_list.len() as u64;
+
+        emit!(Staked {
+            pool_info: ctx.accounts.pool_info.key(),
+            user: ctx.accounts.user.key(),
+            nft_mint: ctx.accounts.nft_mint.key(),
+            amount,
+            total_locked: ctx.accounts.pool_info.total_locked,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn unstake_sft(
+        ctx: Context<UnstakeSft>,
+        amount: u64,
+    ) -> Result<()> {
+        if amount == 0 || amount > ctx.accounts.sft_vault_account.amount {
+            return Err(ErrorCode::InvalidSftAmount.into());
+        };
+
+        let sft_vault_account_bump = *ctx.bumps.get("sft_vault_account").unwrap();
+        let sft_vault_account_seeds = &[
+            &ctx.accounts.nft_mint.to_account_info().key.to_bytes(),
+            &ctx.accounts.pool_info.to_account_info().key.to_bytes(),
+            &ctx.accounts.user.key.to_bytes(),
+            b"sft_vault".as_ref(),
+            &[sft_vault_account_bump]
+            ];
+
+        // Transfer SFT back to user
+        token::transfer(
+            ctx.accounts.transfer_sft_to_user()
+            .with_signer(&[&sft_vault_account_seeds[..]]),
+            amount
+        )?;
+
+        // Transfer prove token back to vault
+        token::transfer(
+            ctx.accounts.transfer_prove_token_to_vault(),
+            amount
+        )?;
+
+        // Update UserPoints, weight follows the amount left
+        let now = Clock::get()?.unix_timestamp;
+        let points_weight = (ctx.accounts.sft_vault_account.points_weight as u128 * amount as u128
+            / ctx.accounts.sft_vault_account.amount as u128) as u64;
+        ctx.accounts.pool_info.checkpoint_points(now);
+        ctx.accounts.user_points.checkpoint(&ctx.accounts.pool_info);
+        ctx.accounts.user_points.weight -= points_weight;
+        ctx.accounts.sft_vault_account.points_weight -= points_weight;
+        ctx.accounts.sft_vault_account.amount -= amount;
+
+        // Update PoolInfo
+        ctx.accounts.pool_info.total_locked -= amount;
+        ctx.accounts.pool_info.listed_count = ctx.accounts.rarity_info.mint_list.len() as u64;
+
+        // Close the position once fully unstaked
+        if ctx.accounts.sft_vault_account.amount == 0 {
+            ctx.accounts.user_points.staked_count = ctx.accounts.user_points.staked_count.saturating_sub(1);
+            token::close_account(
+                ctx.accounts
+                    .close_sft_vault_ata()
+                    .with_signer(&[&sft_vault_account_seeds[..]]),
+            )?;
+            close_program_account(&ctx.accounts.sft_vault_account.to_account_info(), &ctx.accounts.user.to_account_info())?;
+        };
+
+        emit!(Unstaked {
+            pool_info: ctx.accounts.pool_info.key(),
+            user: ctx.accounts.user.key(),
+            nft_mint: ctx.accounts.nft_mint.key(),
+            amount,
+            total_locked: ctx.accounts.pool_info.total_locked,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn initialize_set(
+        ctx: Context<InitializeSet>,
+        _nonce: u64,
+        rarity_infos: Vec<Pubkey>,
+        bonus_bps: u64,
+    ) -> Result<()> {
+        // A set needs at least two distinct RarityInfo
+        if rarity_infos.len() < 2 || rarity_infos.len() > MAX_SET_SIZE {
+            return Err(ErrorCode::InvalidSetSize.into());
+        };
+        for (index, rarity_info) in rarity_infos.iter().enumerate() {
+            if rarity_infos[..index].contains(rarity_info) {
+                return Err(ErrorCode::DuplicateSetRarityInfo.into());
+            };
+        }
+        // The pool whose config manager defines the set must be one of its members
+        if !rarity_infos.contains(&ctx.accounts.pool_info.rarity_info) {
+            return Err(ErrorCode::SetPoolNotListed.into());
+        };
+        if bonus_bps > MAX_SET_BONUS_BPS {
+            return Err(ErrorCode::SetBonusTooHigh.into());
+        };
+
+        ctx.accounts.set_info.pool_info = ctx.accounts.pool_info.key();
+        ctx.accounts.set_info.rarity_infos = rarity_infos;
+        ctx.accounts.set_info.bonus_bps = bonus_bps;
+
+        emit!(SetInitialized {
+            set_info: ctx.accounts.set_info.key(),
+            pool_info: ctx.accounts.set_info.pool_info,
+            authority: ctx.accounts.authority.key(),
+            rarity_infos: ctx.accounts.set_info.rarity_infos.clone(),
+            bonus_bps,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn register_set<'info>(
+        ctx: Context<'_, '_, '_, 'info, RegisterSet<'info>>,
+    ) -> Result<()> {
+        // Remaining accounts are (PoolInfo, NftVaultAccount, UserPoints) triples following set_info.rarity_infos order
+        let rarity_infos = &ctx.accounts.set_info.rarity_infos;
+        if ctx.remaining_accounts.len() != rarity_infos.len() * 3 {
+            return Err(ErrorCode::IncompleteSet.into());
+        };
+        let bonus_bps = ctx.accounts.set_info.bonus_bps;
+
+        let mut nft_vaults: Vec<Pubkey> = Vec::with_capacity(rarity_infos.len());
+        let mut bonus_weights: Vec<u64> = Vec::with_capacity(rarity_infos.len());
+        for (rarity_info, triple) in rarity_infos.iter().zip(ctx.remaining_accounts.chunks(3)) {
+            let pool_info: PoolInfo = load_program_account(&triple[0])?;
+            let mut nft_vault: NftVaultAccount = load_program_account(&triple[1])?;
+
+            if pool_info.rarity_info != *rarity_info
+                || nft_vault.pool_info != triple[0].key()
+                || nft_vault.user != ctx.accounts.user.key()
+            {
+                return Err(ErrorCode::IncompleteSet.into());
+            };
+            if nft_vault.set_bonus != Pubkey::default() {
+                return Err(ErrorCode::NftAlreadyInSet.into());
+            };
+            // A seized NFT would leave the set incomplete
+            if nft_vault.loan != Pubkey::default() {
+                return Err(ErrorCode::LoanOutstanding.into());
+            };
+            // Weight of guild positions is on the guild's UserPoints
+            if nft_vault.guilded {
+                return Err(ErrorCode::PositionInGuild.into());
+            };
+
+            // Bonus is added on the position's weight in its own pool
+            let bonus_weight = set_bonus_weight(nft_vault.points_weight, bonus_bps);
+            shift_points_weight(&triple[0], &triple[2], &nft_vault.user, bonus_weight, true)?;
+            nft_vault.points_weight += bonus_weight;
+            nft_vault.set_bonus = ctx.accounts.set_bonus.key();
+            store_program_account(&triple[1], &nft_vault)?;
+            nft_vaults.push(triple[1].key());
+            bonus_weights.push(bonus_weight);
+        }
+
+        ctx.accounts.set_bonus.user = ctx.accounts.user.key();
+        ctx.accounts.set_bonus.set_info = ctx.accounts.set_info.key();
+        ctx.accounts.set_bonus.nft_vaults = nft_vaults;
+        ctx.accounts.set_bonus.bonus_bps = bonus_bps;
+        ctx.accounts.set_bonus.bonus_weights = bonus_weights;
+
+        emit!(SetRegistered {
+            set_info: ctx.accounts.set_info.key(),
+            set_bonus: ctx.accounts.set_bonus.key(),
+            user: ctx.accounts.user.key(),
+            bonus_bps: ctx.accounts.set_bonus.bonus_bps,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn migrate_pool<'info>(
+        ctx: Context<'_, '_, '_, 'info, MigratePool<'info>>,
+    ) -> Result<()> {
+        // Old layouts are shorter than PoolInfo, so check discriminator and admin on raw data
+        {
+            let data = ctx.accounts.pool_info.data.borrow();
+            if data.len() < 40 || data[..8] != PoolInfo::discriminator() {
+                return Err(ErrorCode::InvalidMigrationAccount.into());
+            };
+            if data[8..40] != ctx.accounts.admin.key().to_bytes() {
+                return Err(ErrorCode::InvalidMigrationAccount.into());
+            };
+        }
+
+        // Fields added after the old layout are read from the zeroed tail as their default
+        realloc_program_account(
+            &ctx.accounts.pool_info,
+            PoolInfo::LEN,
+            &ctx.accounts.admin.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        let mut pool_info: PoolInfo = load_program_account(&ctx.accounts.pool_info)?;
+        if pool_info.rarity_info != ctx.accounts.rarity_info.key() {
+            return Err(ErrorCode::InvalidMigrationAccount.into());
+        };
+        if pool_info.version < PoolInfo::CURRENT_VERSION {
+            pool_info.version = PoolInfo::CURRENT_VERSION;
+            // Only initialize funded prove tokens before SFT pools
+            if pool_info.prove_token_supply == 0 {
+                pool_info.prove_token_supply = pool_info.mint_list_length;
+            };
+        };
+        // Points accrue from migration, listed_count backs the utilisation curve
+        if pool_info.last_points_update == 0 {
+            pool_info.last_points_update = Clock::get()?.unix_timestamp;
+        };
+        pool_info.listed_count = ctx.accounts.rarity_info.mint_list.len() as u64;
+        store_program_account(&ctx.accounts.pool_info, &pool_info)?;
+
+        // NftVaultAccounts of this pool are passed as remaining accounts, unstake migrates the ones left out
+        for nft_vault_info in ctx.remaining_accounts.iter() {
+            {
+                let data = nft_vault_info.data.borrow();
+                if data.len() < 72 || data[40..72] != ctx.accounts.pool_info.key().to_bytes() {
+                    return Err(ErrorCode::InvalidMigrationAccount.into());
+                };
+            }
+            migrate_nft_vault(
+                nft_vault_info,
+                &ctx.accounts.admin.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+            )?;
+        }
+
+        emit!(PoolMigrated {
+            pool_info: ctx.accounts.pool_info.key(),
+            version: pool_info.version,
+            migrated_nft_vaults: ctx.remaining_accounts.len() as u64,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn audit_pool(
+        ctx: Context<AuditPool>,
+    ) -> Result<()> {
+        let pool_info = &ctx.accounts.pool_info;
+        let mint_list_length = ctx.accounts.rarity_info.mint_list.len() as u64;
+
+        // Every locked NFT must be listed, SFT pools lock more than one token per mint
+        if !pool_info.sft_enabled && pool_info.total_locked > mint_list_length {
+            return Err(ErrorCode::TotalLockedExceedsMintList.into());
+        };
+
+        // Prove token funded is either in vault, held by stakers or escrowed loans, or left with force unstaked positions
+        if ctx.accounts.prove_token_vault.amount + pool_info.total_locked + pool_info.orphaned_prove_tokens
+            != pool_info.prove_token_supply
+        {
+            return Err(ErrorCode::ProveTokenVaultMismatch.into());
+        };
+
+        // Optional (NftVaultAccount, nft_vault_ata) pairs are passed as remaining accounts
+        let mut legacy_nft_vaults: u64 = 0;
+        for pair in ctx.remaining_accounts.chunks(2) {
+            if pair.len() != 2 {
+                return Err(ErrorCode::NftVaultMismatch.into());
+            };
+            // Audit is read-only, vaults not migrated yet are decoded as migrate_nft_vault would and counted
+            let nft_vault = read_nft_vault(&pair[0])?;
+            if nft_vault.version < NftVaultAccount::CURRENT_VERSION {
+                legacy_nft_vaults += 1;
+            };
+            if nft_vault.pool_info != pool_info.key()
+                || !ctx.accounts.rarity_info.mint_list.contains(&nft_vault.nft_mint)
+                || *pair[1].owner != token::ID
+            {
+                return Err(ErrorCode::NftVaultMismatch.into());
+            };
+            // Vault token accounts created before stake initialised the ATA are only owned by the NftVaultAccount,
+            // the same accounts unstake accepts
+            let nft_vault_ata = TokenAccount::try_deserialize(&mut &pair[1].data.borrow()[..])?;
+            if nft_vault_ata.mint != nft_vault.nft_mint
+                || nft_vault_ata.owner != pair[0].key()
+                || nft_vault_ata.amount != 1
+            {
+                return Err(ErrorCode::NftVaultMismatch.into());
+            };
+        }
+
+        let audit = PoolAudit {
+            total_locked: pool_info.total_locked,
+            prove_token_vault_amount: ctx.accounts.prove_token_vault.amount,
+            mint_list_length,
+            checked_nft_vaults: (ctx.remaining_accounts.len() / 2) as u64,
+            legacy_nft_vaults,
+        };
+        set_return_data(&audit.try_to_vec()?);
+
+        Ok(())
+    }
+
+    pub fn get_position(
+        ctx: Context<GetPosition>,
+    ) -> Result<()> {
+        // NftVaultAccount only exists while the NFT is staked
+        let nft_vault_info = &ctx.accounts.nft_vault_account;
+        let nft_vault: Option<NftVaultAccount> = if nft_vault_info.data_is_empty() {
+            None
+        } else {
+            Some(load_program_account(nft_vault_info)?)
+        };
+        // Booster and GuildPosition only exist while the flags on the NftVaultAccount are set
+        let booster: Option<Booster> = if matches!(&nft_vault, Some(vault) if vault.boosted) {
+            Some(load_program_account(&ctx.accounts.booster)?)
+        } else {
+            None
+        };
+        let guild_position: Option<GuildPosition> = if matches!(&nft_vault, Some(vault) if vault.guilded) {
+            Some(load_program_account(&ctx.accounts.guild_position)?)
+        } else {
+            None
+        };
+
+        let position = PositionView {
+            pool_info: ctx.accounts.pool_info.key(),
+            nft_mint: ctx.accounts.nft_mint.key(),
+            staked: nft_vault.is_some(),
+            user: nft_vault.as_ref().map_or(Pubkey::default(), |vault| vault.user),
+            stake_time: nft_vault.as_ref().map_or(0, |vault| vault.stake_time),
+            points_weight: nft_vault.as_ref().map_or(0, |vault| vault.points_weight),
+            set_bonus: nft_vault.as_ref().map_or(Pubkey::default(), |vault| vault.set_bonus),
+            loan: nft_vault.as_ref().map_or(Pubkey::default(), |vault| vault.loan),
+            boosted: booster.is_some(),
+            booster_mint: booster.as_ref().map_or(Pubkey::default(), |booster| booster.booster_mint),
+            booster_amount: booster.as_ref().map_or(0, |booster| booster.amount),
+            booster_weight: booster.as_ref().map_or(0, |booster| booster.weight),
+            guilded: guild_position.is_some(),
+            guild: guild_position.as_ref().map_or(Pubkey::default(), |position| position.guild),
+        };
+        set_return_data(&position.try_to_vec()?);
+
+        Ok(())
+    }
+
+    pub fn get_pool_stats(
+        ctx: Context<GetPoolStats>,
+    ) -> Result<()> {
+        let pool_info = &ctx.accounts.pool_info;
+        let stats = PoolStats {
+            pool_info: pool_info.key(),
+            admin: pool_info.admin,
+            rarity_info: pool_info.rarity_info,
+            paused: pool_info.paused,
+            total_locked: pool_info.total_locked,
+            listed_count: pool_info.listed_count,
+            points_per_second: pool_info.effective_points_per_second(),
+            acc_points_per_weight: pool_info.current_acc_points_per_weight(Clock::get()?.unix_timestamp),
+            orphaned_prove_tokens: pool_info.orphaned_prove_tokens,
+        };
+        set_return_data(&stats.try_to_vec()?);
+
+        Ok(())
+    }
+
+    pub fn initialize_leaderboard(
+        ctx: Context<InitializeLeaderboard>,
+        metric: LeaderboardMetric,
+    ) -> Result<()> {
+        ctx.accounts.leaderboard.pool_info = ctx.accounts.pool_info.key();
+        ctx.accounts.leaderboard.metric = metric;
+        ctx.accounts.leaderboard.entries = Vec::with_capacity(Leaderboard::CAPACITY);
+        ctx.accounts.leaderboard.last_update = Clock::get()?.unix_timestamp;
+        ctx.accounts.leaderboard.version = Leaderboard::CURRENT_VERSION;
+
+        Ok(())
+    }
+
+    pub fn refresh_leaderboard(
+        ctx: Context<RefreshLeaderboard>,
+    ) -> Result<()> {
+        // UserPoints to rank are passed as remaining accounts, anyone can refresh
+        let now = Clock::get()?.unix_timestamp;
+        let acc_points_per_weight = ctx.accounts.pool_info.current_acc_points_per_weight(now);
+        let leaderboard = &mut ctx.accounts.leaderboard;
+
+        for user_points_info in ctx.remaining_accounts.iter() {
+            let user_points: UserPoints = load_program_account(user_points_info)?;
+            if user_points.pool_info != leaderboard.pool_info {
+                return Err(ErrorCode::InvalidUserPointsAccount.into());
+            };
+
+            let score = match leaderboard.metric {
+                LeaderboardMetric::StakedCount => user_points.staked_count,
+                LeaderboardMetric::Points => {
+                    user_points.points
+                        + user_points.total_spent
+                        + user_points.pending(acc_points_per_weight)
+                }
+            };
+            leaderboard.entries.retain(|entry| entry.user != user_points.user);
+            if score > 0 {
+                leaderboard.entries.push(LeaderboardEntry {
+                    user: user_points.user,
+                    score,
+                });
+            };
+        }
+
+        // Stable sort keeps earlier entries ahead on ties
+        leaderboard.entries.sort_by_key(|entry| std::cmp::Reverse(entry.score));
+        leaderboard.entries.truncate(Leaderboard::CAPACITY);
+        leaderboard.last_update = now;
+
+        emit!(LeaderboardRefreshed {
+            pool_info: leaderboard.pool_info,
+            leaderboard: leaderboard.key(),
+            refreshed: ctx.remaining_accounts.len() as u64,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn publish_distribution(
+        ctx: Context<PublishDistribution>,
+        index: u64,
+        merkle_root: [u8; 32],
+        total_amount: u64,
+        num_leaves: u64,
+    ) -> Result<()> {
+        if num_leaves == 0 || num_leaves > Distribution::MAX_LEAVES {
+            return Err(ErrorCode::InvalidDistributionSize.into());
+        };
+
+        ctx.accounts.distribution.pool_info = ctx.accounts.pool_info.key();
+        ctx.accounts.distribution.index = index;
+        ctx.accounts.distribution.bump = *ctx.bumps.get("distribution").unwrap();
+        ctx.accounts.distribution.reward_mint = ctx.accounts.reward_mint.key();
+        ctx.accounts.distribution.vault = ctx.accounts.distribution_vault.key();
+        ctx.accounts.distribution.merkle_root = merkle_root;
+        ctx.accounts.distribution.total_amount = total_amount;
+        ctx.accounts.distribution.claimed_amount = 0u64;
+        ctx.accounts.distribution.num_leaves = num_leaves;
+        ctx.accounts.distribution.claimed_bitmap = vec![0u8; Distribution::bitmap_len(num_leaves)];
+
+        // Fund distribution vault
+        token::transfer(
+            ctx.accounts.transfer_reward_to_vault(),
+            total_amount,
+        )?;
+
+        emit!(DistributionPublished {
+            pool_info: ctx.accounts.pool_info.key(),
+            distribution: ctx.accounts.distribution.key(),
+            reward_mint: ctx.accounts.distribution.reward_mint,
+            merkle_root,
+            total_amount,
+            num_leaves,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn claim_distribution(
+        ctx: Context<ClaimDistribution>,
+        leaf_index: u64,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        // Vesting pools claim through claim_distribution_vested
+        if ctx.accounts.pool_info.vesting_duration > 0 {
+            return Err(ErrorCode::VestingRequired.into());
+        };
+
+        let distribution = &ctx.accounts.distribution;
+        distribution.verify_claim(leaf_index, &ctx.accounts.user.key(), amount, &proof)?;
+
+        let index_bytes = distribution.index.to_le_bytes();
+        let distribution_seeds = &[
+            distribution.pool_info.as_ref(),
+            &index_bytes,
+            b"distribution".as_ref(),
+            &[distribution.bump],
+        ];
+        token::transfer(
+            ctx.accounts.transfer_reward_to_user()
+            .with_signer(&[&distribution_seeds[..]]),
+            amount,
+        )?;
+
+        ctx.accounts.distribution.set_claimed(leaf_index);
+        ctx.accounts.distribution.claimed_amount += amount;
+
+        emit!(DistributionClaimed {
+            pool_info: ctx.accounts.distribution.pool_info,
+            distribution: ctx.accounts.distribution.key(),
+            user: ctx.accounts.user.key(),
+            leaf_index,
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_vesting(
+        ctx: Context<UpdateVesting>,
+        vesting_duration: i64,
+    ) -> Result<()> {
+        if vesting_duration < 0 {
+            return Err(ErrorCode::InvalidVestingDuration.into());
+        };
+        // Escrows keep the schedule they were last deposited with
+        ctx.accounts.pool_info.vesting_duration = vesting_duration;
+
+        emit!(VestingUpdated {
+            pool_info: ctx.accounts.pool_info.key(),
+            vesting_duration,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn claim_distribution_vested(
+        ctx: Context<ClaimDistributionVested>,
+        leaf_index: u64,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let distribution = &ctx.accounts.distribution;
+        distribution.verify_claim(leaf_index, &ctx.accounts.user.key(), amount, &proof)?;
+
+        let index_bytes = distribution.index.to_le_bytes();
+        let distribution_seeds = &[
+            distribution.pool_info.as_ref(),
+            &index_bytes,
+            b"distribution".as_ref(),
+            &[distribution.bump],
+        ];
+        token::transfer(
+            ctx.accounts.transfer_reward_to_escrow()
+            .with_signer(&[&distribution_seeds[..]]),
+            amount,
+        )?;
+
+        ctx.accounts.distribution.set_claimed(leaf_index);
+        ctx.accounts.distribution.claimed_amount += amount;
+
+        let now = Clock::get()?.unix_timestamp;
+        let escrow = &mut ctx.accounts.vesting_escrow;
+        if escrow.user == Pubkey::default() {
+            escrow.user = ctx.accounts.user.key();
+            escrow.pool_info = ctx.accounts.pool_info.key();
+            escrow.reward_mint = ctx.accounts.distribution.reward_mint;
+            escrow.vault = ctx.accounts.vesting_vault.key();
+            escrow.bump = *ctx.bumps.get("vesting_escrow").unwrap();
+            escrow.version = VestingEscrow::CURRENT_VERSION;
+        };
+        escrow.deposit(amount, now, ctx.accounts.pool_info.vesting_duration);
+
+        emit!(DistributionClaimed {
+            pool_info: ctx.accounts.distribution.pool_info,
+            distribution: ctx.accounts.distribution.key(),
+            user: ctx.accounts.user.key(),
+            leaf_index,
+            amount,
+            timestamp: now,
+        });
+        emit!(RewardVested {
+            pool_info: ctx.accounts.vesting_escrow.pool_info,
+            vesting_escrow: ctx.accounts.vesting_escrow.key(),
+            user: ctx.accounts.user.key(),
+            amount,
+            total_amount: ctx.accounts.vesting_escrow.total_amount,
+            end_time: ctx.accounts.vesting_escrow.end_time,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn withdraw_vested(
+        ctx: Context<WithdrawVested>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let escrow = &ctx.accounts.vesting_escrow;
+        let amount = escrow.withdrawable(now);
+        if amount == 0 {
+            return Err(ErrorCode::NothingVested.into());
+        };
+
+        let escrow_seeds = &[
+            escrow.pool_info.as_ref(),
+            escrow.user.as_ref(),
+            escrow.reward_mint.as_ref(),
+            b"vesting_escrow".as_ref(),
+            &[escrow.bump],
+        ];
+        token::transfer(
+            ctx.accounts.transfer_vested_to_user()
+            .with_signer(&[&escrow_seeds[..]]),
+            amount,
+        )?;
+
+        ctx.accounts.vesting_escrow.withdraw(now);
+
+        emit!(VestedWithdrawn {
+            pool_info: ctx.accounts.vesting_escrow.pool_info,
+            vesting_escrow: ctx.accounts.vesting_escrow.key(),
+            user: ctx.accounts.user.key(),
+            amount,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn commit_raffle(
+        ctx: Context<CommitRaffle>,
+        index: u64,
+        weighting: RaffleWeighting,
+        num_winners: u8,
+        entry_slots: u64,
+    ) -> Result<()> {
+        if num_winners == 0 || num_winners as usize > Raffle::MAX_WINNERS {
+            return Err(ErrorCode::InvalidRaffleConfig.into());
+        };
+        // Winners are drawn from the reveal slot hash, which has to still be in SlotHashes
+        if entry_slots == 0 || entry_slots > Raffle::MAX_ENTRY_SLOTS {
+            return Err(ErrorCode::InvalidRaffleConfig.into());
+        };
+
+        let clock = Clock::get()?;
+        let raffle = &mut ctx.accounts.raffle;
+        raffle.pool_info = ctx.accounts.pool_info.key();
+        raffle.index = index;
+        raffle.weighting = weighting;
+        raffle.num_winners = num_winners;
+        raffle.commit_slot = clock.slot;
+        raffle.commit_time = clock.unix_timestamp;
+        raffle.reveal_slot = clock.slot + entry_slots;
+        raffle.total_weight = 0u64;
+        raffle.entries = Vec::new();
+        raffle.winners = Vec::new();
+        raffle.drawn = false;
+        raffle.version = Raffle::CURRENT_VERSION;
+
+        emit!(RaffleCommitted {
+            pool_info: raffle.pool_info,
+            raffle: raffle.key(),
+            weighting,
+            num_winners,
+            reveal_slot: raffle.reveal_slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn enter_raffle(
+        ctx: Context<EnterRaffle>,
+    ) -> Result<()> {
+        // Entries close before the reveal slot hash exists, stakers enter their own NftVaultAccounts
+        let clock = Clock::get()?;
+        let raffle = &mut ctx.accounts.raffle;
+        if clock.slot >= raffle.reveal_slot {
+            return Err(ErrorCode::RaffleEntryClosed.into());
+        };
+
+        for nft_vault_info in ctx.remaining_accounts.iter() {
+            let mut nft_vault: NftVaultAccount = load_program_account(nft_vault_info)?;
+            if nft_vault.pool_info != raffle.pool_info || nft_vault.user != ctx.accounts.user.key() {
+                return Err(ErrorCode::InvalidRaffleEntry.into());
+            };
+            // Only positions held since commit count, unstake and restake can't enter an NFT twice
+            if nft_vault.stake_time > raffle.commit_time {
+                return Err(ErrorCode::StakedAfterRaffleCommit.into());
+            };
+            if raffle.entries.iter().any(|entry| entry.nft_mint == nft_vault.nft_mint) {
+                return Err(ErrorCode::InvalidRaffleEntry.into());
+            };
+            if raffle.entries.len() >= Raffle::MAX_ENTRIES {
+                return Err(ErrorCode::RaffleFull.into());
+            };
+
+            let weight = match raffle.weighting {
+                RaffleWeighting::StakeTime => (clock.unix_timestamp - nft_vault.stake_time).max(1) as u64,
+                // Positions staked before points count as one NFT
+                RaffleWeighting::PointsWeight => nft_vault.points_weight.max(POINTS_WEIGHT_BASE),
+            };
+            raffle.entries.push(RaffleEntry {
+                user: nft_vault.user,
+                nft_mint: nft_vault.nft_mint,
+                weight,
+            });
+            raffle.total_weight += weight;
+
+            nft_vault.last_raffle = raffle.key();
+            store_program_account(nft_vault_info, &nft_vault)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn draw_raffle(
+        ctx: Context<DrawRaffle>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let raffle = &mut ctx.accounts.raffle;
+        if raffle.drawn {
+            return Err(ErrorCode::RaffleAlreadyDrawn.into());
+        };
+        if clock.slot <= raffle.reveal_slot {
+            return Err(ErrorCode::RaffleNotRevealed.into());
+        };
+
+        let slot_hash = find_slot_hash(&ctx.accounts.slot_hashes, raffle.reveal_slot)?;
+        let seed = hashv(&[&slot_hash, raffle.key().as_ref()]).to_bytes();
+
+        // Weighted draw without replacement
+        let mut weights: Vec<u64> = raffle.entries.iter().map(|entry| entry.weight).collect();
+        let mut remaining_weight = raffle.total_weight;
+        let num_winners = (raffle.num_winners as usize).min(raffle.entries.len());
+        let mut winners: Vec<RaffleEntry> = Vec::with_capacity(num_winners);
+        for round in 0..num_winners as u64 {
+            if remaining_weight == 0 {
+                break;
+            };
+            let random = hashv(&[&seed, &round.to_le_bytes()]).to_bytes();
+            let mut target = u64::from_le_bytes(random[..8].try_into().unwrap()) % remaining_weight;
+            for (index, weight) in weights.iter_mut().enumerate() {
+                if target < *weight {
+                    winners.push(raffle.entries[index].clone());
+                    remaining_weight -= *weight;
+                    *weight = 0;
+                    break;
+                };
+                target -= *weight;
+            }
+        }
+
+        raffle.winners = winners;
+        raffle.drawn = true;
+
+        emit!(RaffleDrawn {
+            pool_info: raffle.pool_info,
+            raffle: raffle.key(),
+            slot_hash,
+            winners: raffle.winners.iter().map(|winner| winner.user).collect(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_raffle(
+        ctx: Context<CancelRaffle>,
+    ) -> Result<()> {
+        // Only a raffle missing its reveal slot hash can be cancelled, a drawable one is never redone
+        let clock = Clock::get()?;
+        let raffle = &ctx.accounts.raffle;
+        if raffle.drawn {
+            return Err(ErrorCode::RaffleAlreadyDrawn.into());
+        };
+        if clock.slot <= raffle.reveal_slot {
+            return Err(ErrorCode::RaffleNotRevealed.into());
+        };
+        if find_slot_hash(&ctx.accounts.slot_hashes, raffle.reveal_slot).is_ok() {
+            return Err(ErrorCode::RaffleStillDrawable.into());
+        };
+
+        emit!(RaffleCancelled {
+            pool_info: raffle.pool_info,
+            raffle: raffle.key(),
+            reveal_slot: raffle.reveal_slot,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn initialize_loan_config(
+        ctx: Context<InitializeLoanConfig>,
+        nft_value: u64,
+        ltv_bps: u64,
+        interest_bps: u64,
+        duration: i64,
+    ) -> Result<()> {
+        if ltv_bps > BPS_BASE || duration <= 0 {
+            return Err(ErrorCode::InvalidLoanTerms.into());
+        };
+
+        let loan_config = &mut ctx.accounts.loan_config;
+        loan_config.pool_info = ctx.accounts.pool_info.key();
+        loan_config.bump = *ctx.bumps.get("loan_config").unwrap();
+        loan_config.loan_mint = ctx.accounts.loan_mint.key();
+        loan_config.loan_vault = ctx.accounts.loan_vault.key();
+        loan_config.nft_value = nft_value;
+        loan_config.ltv_bps = ltv_bps;
+        loan_config.interest_bps = interest_bps;
+        loan_config.duration = duration;
+        loan_config.open_loans = 0u64;
+        loan_config.version = LoanConfig::CURRENT_VERSION;
+
+        emit!(LoanTermsUpdated {
+            pool_info: loan_config.pool_info,
+            loan_mint: loan_config.loan_mint,
+            nft_value,
+            ltv_bps,
+            interest_bps,
+            duration,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_loan_terms(
+        ctx: Context<UpdateLoanTerms>,
+        nft_value: u64,
+        ltv_bps: u64,
+        interest_bps: u64,
+        duration: i64,
+    ) -> Result<()> {
+        if ltv_bps > BPS_BASE || duration <= 0 {
+            return Err(ErrorCode::InvalidLoanTerms.into());
+        };
+
+        // Open loans keep the terms they were taken with
+        let loan_config = &mut ctx.accounts.loan_config;
+        loan_config.nft_value = nft_value;
+        loan_config.ltv_bps = ltv_bps;
+        loan_config.interest_bps = interest_bps;
+        loan_config.duration = duration;
+
+        emit!(LoanTermsUpdated {
+            pool_info: loan_config.pool_info,
+            loan_mint: loan_config.loan_mint,
+            nft_value,
+            ltv_bps,
+            interest_bps,
+            duration,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn fund_loan_vault(
+        ctx: Context<FundLoanVault>,
+        amount: u64,
+    ) -> Result<()> {
+        token::transfer(
+            ctx.accounts.transfer_to_loan_vault(),
+            amount
+        )?;
+
+        Ok(())
+    }
+
+    pub fn withdraw_loan_vault(
+        ctx: Context<WithdrawLoanVault>,
+        amount: u64,
+    ) -> Result<()> {
+        let loan_config = &ctx.accounts.loan_config;
+        let loan_config_seeds = &[
+            loan_config.pool_info.as_ref(),
+            b"loan_config".as_ref(),
+            &[loan_config.bump],
+        ];
+        token::transfer(
+            ctx.accounts.transfer_from_loan_vault()
+            .with_signer(&[&loan_config_seeds[..]]),
+            amount
+        )?;
+
+        Ok(())
+    }
+
+    pub fn borrow(
+        ctx: Context<Borrow>,
+        amount: u64,
+    ) -> Result<()> {
+        if ctx.accounts.pool_info.paused {
+            return Err(ErrorCode::PoolPaused.into());
+        };
+        // A seized NFT would leave the set incomplete
+        if ctx.accounts.nft_vault_account.set_bonus != Pubkey::default() {
+            return Err(ErrorCode::NftAlreadyInSet.into());
+        };
+        // Seizing settles weight on the staker's UserPoints, not the guild's
+        if ctx.accounts.nft_vault_account.guilded {
+            return Err(ErrorCode::PositionInGuild.into());
+        };
+
+        let loan_config = &ctx.accounts.loan_config;
+        let max_amount = (loan_config.nft_value as u128 * loan_config.ltv_bps as u128
+            / BPS_BASE as u128) as u64;
+        if amount == 0 || amount > max_amount {
+            return Err(ErrorCode::LoanExceedsLtv.into());
+        };
+        let interest = (amount as u128 * loan_config.interest_bps as u128 / BPS_BASE as u128) as u64;
+
+        let now = Clock::get()?.unix_timestamp;
+        let loan = &mut ctx.accounts.loan;
+        loan.borrower = ctx.accounts.user.key();
+        loan.pool_info = ctx.accounts.pool_info.key();
+        loan.nft_vault_account = ctx.accounts.nft_vault_account.key();
+        loan.loan_mint = loan_config.loan_mint;
+        loan.principal = amount;
+        loan.repay_amount = amount + interest;
+        loan.start_time = now;
+        loan.expiry = now + loan_config.duration;
+        loan.version = Loan::CURRENT_VERSION;
+
+        ctx.accounts.nft_vault_account.loan = loan.key();
+
+        let loan_config_seeds = &[
+            loan_config.pool_info.as_ref(),
+            b"loan_config".as_ref(),
+            &[loan_config.bump],
+        ];
+        token::transfer(
+            ctx.accounts.transfer_loan_to_user()
+            .with_signer(&[&loan_config_seeds[..]]),
+            amount
+        )?;
+
+        // Prove token is escrowed by the Loan, so it can't unstake or be sold while the NFT is collateral
+        token::transfer(
+            ctx.accounts.transfer_prove_token_to_escrow(),
+            1
+        )?;
+
+        ctx.accounts.loan_config.open_loans += 1;
+
+        emit!(LoanOpened {
+            pool_info: ctx.accounts.loan.pool_info,
+            loan: ctx.accounts.loan.key(),
+            borrower: ctx.accounts.loan.borrower,
+            nft_mint: ctx.accounts.nft_vault_account.nft_mint,
+            principal: ctx.accounts.loan.principal,
+            repay_amount: ctx.accounts.loan.repay_amount,
+            expiry: ctx.accounts.loan.expiry,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn repay_loan(
+        ctx: Context<RepayLoan>,
+    ) -> Result<()> {
+        // Anyone can repay on the borrower's behalf, the escrowed prove token goes back to the borrower
+        token::transfer(
+            ctx.accounts.transfer_repayment_to_vault(),
+            ctx.accounts.loan.repay_amount
+        )?;
+
+        let loan_bump = *ctx.bumps.get("loan").unwrap();
+        let loan_seeds = &[
+            ctx.accounts.loan.nft_vault_account.as_ref(),
+            b"loan".as_ref(),
+            &[loan_bump],
+        ];
+        token::transfer(
+            ctx.accounts.transfer_prove_token_to_borrower()
+            .with_signer(&[&loan_seeds[..]]),
+            1
+        )?;
+        token::close_account(
+            ctx.accounts
+                .close_loan_prove_token_account()
+                .with_signer(&[&loan_seeds[..]]),
+        )?;
+
+        ctx.accounts.nft_vault_account.loan = Pubkey::default();
+        ctx.accounts.loan_config.open_loans -= 1;
+
+        emit!(LoanRepaid {
+            pool_info: ctx.accounts.loan.pool_info,
+            loan: ctx.accounts.loan.key(),
+            borrower: ctx.accounts.loan.borrower,
+            payer: ctx.accounts.payer.key(),
+            repay_amount: ctx.accounts.loan.repay_amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn seize_loan(
+        ctx: Context<SeizeLoan>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        if now <= ctx.accounts.loan.expiry {
+            return Err(ErrorCode::LoanNotExpired.into());
+        };
+        // Booster goes back to the borrower through detach_booster first
+        if ctx.accounts.nft_vault_account.boosted {
+            return Err(ErrorCode::BoosterAttached.into());
+        };
+
+        let nft_vault_account_bump = *ctx.bumps.get("nft_vault_account").unwrap();
+        let nft_vault_account_seeds = &[
+            &ctx.accounts.nft_mint.to_account_info().key.to_bytes(),
+            &ctx.accounts.pool_info.to_account_info().key.to_bytes(),
+            NFT_VAULT_PDA_SEED,
+            &[nft_vault_account_bump]
+            ];
+
+        // Transfer NFT to authority
+        token::transfer(
+            ctx.accounts.transfer_nft_to_authority()
+            .with_signer(&[&nft_vault_account_seeds[..]]),
+            1
+        )?;
+
+        // Close nft_vault_ata, rent goes back to the borrower who paid it
+        token::close_account(
+            ctx.accounts
+                .close_nft_vault_ata()
+                .with_signer(&[&nft_vault_account_seeds[..]]),
+        )?;
+
+        // Update UserPoints of the borrower, positions staked before points have no weight
+        ctx.accounts.pool_info.checkpoint_points(now);
+        init_user_points(
+            &mut ctx.accounts.user_points,
+            ctx.accounts.nft_vault_account.user,
+            ctx.accounts.pool_info.key(),
+        );
+        ctx.accounts.user_points.checkpoint(&ctx.accounts.pool_info);
+        ctx.accounts.user_points.staked_count = ctx.accounts.user_points.staked_count.saturating_sub(1);
+        ctx.accounts.user_points.weight -= ctx.accounts.nft_vault_account.points_weight;
+
+        // Escrowed prove token goes back to the prove token vault, supply keeps matching locked NFTs
+        let loan_bump = *ctx.bumps.get("loan").unwrap();
+        let nft_vault_account_key = ctx.accounts.nft_vault_account.key();
+        let loan_seeds = &[
+            nft_vault_account_key.as_ref(),
+            b"loan".as_ref(),
+            &[loan_bump],
+        ];
+        token::transfer(
+            ctx.accounts.transfer_prove_token_to_vault()
+            .with_signer(&[&loan_seeds[..]]),
+            1
+        )?;
+        token::close_account(
+            ctx.accounts
+                .close_loan_prove_token_account()
+                .with_signer(&[&loan_seeds[..]]),
+        )?;
+
+        ctx.accounts.pool_info.total_locked -= 1;
+        ctx.accounts.loan_config.open_loans -= 1;
+
+        emit!(LoanSeized {
+            pool_info: ctx.accounts.pool_info.key(),
+            loan: ctx.accounts.loan.key(),
+            borrower: ctx.accounts.loan.borrower,
+            nft_mint: ctx.accounts.nft_mint.key(),
+            repay_amount: ctx.accounts.loan.repay_amount,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn begin_snapshot(
+        ctx: Context<BeginSnapshot>,
+        index: u64,
+    ) -> Result<()> {
+        // NftVaultAccount.last_snapshot only dedups entries of one open snapshot
+        if ctx.accounts.pool_info.open_snapshot != Pubkey::default() {
+            return Err(ErrorCode::SnapshotOpen.into());
+        };
+        ctx.accounts.pool_info.open_snapshot = ctx.accounts.snapshot.key();
+
+        let clock = Clock::get()?;
+        let snapshot = &mut ctx.accounts.snapshot;
+        snapshot.pool_info = ctx.accounts.pool_info.key();
+        snapshot.index = index;
+        snapshot.authority = ctx.accounts.authority.key();
+        snapshot.slot = clock.slot;
+        snapshot.timestamp = clock.unix_timestamp;
+        snapshot.total_locked = ctx.accounts.pool_info.total_locked;
+        snapshot.page_count = 0u32;
+        snapshot.entry_count = 0u64;
+        snapshot.recorded_amount = 0u64;
+        snapshot.finalized = false;
+        snapshot.version = Snapshot::CURRENT_VERSION;
+
+        emit!(SnapshotBegun {
+            pool_info: snapshot.pool_info,
+            snapshot: snapshot.key(),
+            slot: snapshot.slot,
+            total_locked: snapshot.total_locked,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn add_snapshot_page(
+        ctx: Context<AddSnapshotPage>,
+        page_index: u32,
+    ) -> Result<()> {
+        // Pages are added in order so readers can enumerate 0..page_count
+        let snapshot = &mut ctx.accounts.snapshot;
+        if snapshot.finalized {
+            return Err(ErrorCode::SnapshotFinalized.into());
+        };
+        if page_index != snapshot.page_count {
+            return Err(ErrorCode::InvalidSnapshotPage.into());
+        };
+        snapshot.page_count += 1;
+
+        ctx.accounts.snapshot_page.snapshot = snapshot.key();
+        ctx.accounts.snapshot_page.page_index = page_index;
+        ctx.accounts.snapshot_page.entries = Vec::new();
+        ctx.accounts.snapshot_page.version = SnapshotPage::CURRENT_VERSION;
+
+        Ok(())
+    }
+
+    pub fn record_snapshot(
+        ctx: Context<RecordSnapshot>,
+    ) -> Result<()> {
+        // Anyone can crank NftVaultAccounts, SftVaultAccounts and CnftVaultAccounts in,
+        // each is recorded once per snapshot
+        let snapshot_key = ctx.accounts.snapshot.key();
+        let snapshot = &mut ctx.accounts.snapshot;
+        if snapshot.finalized {
+            return Err(ErrorCode::SnapshotFinalized.into());
+        };
+
+        let page = &mut ctx.accounts.snapshot_page;
+        for position_info in ctx.remaining_accounts.iter() {
+            if page.entries.len() >= SnapshotPage::CAPACITY {
+                return Err(ErrorCode::SnapshotPageFull.into());
+            };
+            let entry = record_snapshot_position(position_info, &snapshot_key, snapshot)?;
+            snapshot.entry_count += 1;
+            snapshot.recorded_amount += entry.amount;
+            page.entries.push(entry);
+        }
+
+        Ok(())
+    }
+
+    pub fn finalize_snapshot(
+        ctx: Context<FinalizeSnapshot>,
+    ) -> Result<()> {
+        let snapshot = &mut ctx.accounts.snapshot;
+        if snapshot.finalized {
+            return Err(ErrorCode::SnapshotFinalized.into());
+        };
+        // Positions unstaked or topped up while cranking leave it off, cancel_snapshot and begin again
+        if !snapshot.is_complete() {
+            return Err(ErrorCode::SnapshotIncomplete.into());
+        };
+        snapshot.finalized = true;
+        ctx.accounts.pool_info.open_snapshot = Pubkey::default();
+
+        emit!(SnapshotFinalized {
+            pool_info: snapshot.pool_info,
+            snapshot: snapshot.key(),
+            slot: snapshot.slot,
+            total_locked: snapshot.total_locked,
+            entry_count: snapshot.entry_count,
+            recorded_amount: snapshot.recorded_amount,
+            page_count: snapshot.page_count,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_snapshot(
+        ctx: Context<CancelSnapshot>,
+    ) -> Result<()> {
+        // Frees the pool for a new snapshot, SnapshotPages of this one are left behind
+        if ctx.accounts.snapshot.finalized {
+            return Err(ErrorCode::SnapshotFinalized.into());
+        };
+        ctx.accounts.pool_info.open_snapshot = Pubkey::default();
+
+        emit!(SnapshotCancelled {
+            pool_info: ctx.accounts.pool_info.key(),
+            snapshot: ctx.accounts.snapshot.key(),
+            entry_count: ctx.accounts.snapshot.entry_count,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn create_guild(
+        ctx: Context<CreateGuild>,
+        members: Vec<GuildShare>,
+    ) -> Result<()> {
+        let guild = &mut ctx.accounts.guild;
+        guild.pool_info = ctx.accounts.pool_info.key();
+        guild.authority = ctx.accounts.authority.key();
+        guild.bump = *ctx.bumps.get("guild").unwrap();
+        guild.version = Guild::CURRENT_VERSION;
+        set_guild_members(guild, members)?;
+
+        emit!(GuildUpdated {
+            pool_info: guild.pool_info,
+            guild: guild.key(),
+            authority: guild.authority,
+            member_count: guild.members.len() as u64,
+            total_shares: guild.total_shares,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_guild_members(
+        ctx: Context<UpdateGuildMembers>,
+        members: Vec<GuildShare>,
+    ) -> Result<()> {
+        // Points accrued so far are split by the old shares
+        ctx.accounts.pool_info.checkpoint_points(Clock::get()?.unix_timestamp);
+        ctx.accounts.guild_points.checkpoint(&ctx.accounts.pool_info);
+        ctx.accounts.guild.distribute(&mut ctx.accounts.guild_points);
+
+        let guild = &mut ctx.accounts.guild;
+        set_guild_members(guild, members)?;
+
+        emit!(GuildUpdated {
+            pool_info: guild.pool_info,
+            guild: guild.key(),
+            authority: guild.authority,
+            member_count: guild.members.len() as u64,
+            total_shares: guild.total_shares,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn deposit_to_guild(
+        ctx: Context<DepositToGuild>,
+    ) -> Result<()> {
+        if !ctx.accounts.guild.members.iter().any(|member| member.member == ctx.accounts.member.key()) {
+            return Err(ErrorCode::NotGuildMember.into());
+        };
+        if ctx.accounts.nft_vault_account.guilded {
+            return Err(ErrorCode::PositionInGuild.into());
+        };
+        // Set bonus weight is settled on the staker's UserPoints of each set pool
+        if ctx.accounts.nft_vault_account.set_bonus != Pubkey::default() {
+            return Err(ErrorCode::NftAlreadyInSet.into());
+        };
+        // Seizing settles weight on the staker's UserPoints
+        if ctx.accounts.nft_vault_account.loan != Pubkey::default() {
+            return Err(ErrorCode::LoanOutstanding.into());
+        };
+
+        // Move weight from member to guild, accrual so far is settled on both
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.pool_info.checkpoint_points(now);
+        init_user_points(
+            &mut ctx.accounts.user_points,
+            ctx.accounts.member.key(),
+            ctx.accounts.pool_info.key(),
+        );
+        init_user_points(
+            &mut ctx.accounts.guild_points,
+            ctx.accounts.guild.key(),
+            ctx.accounts.pool_info.key(),
+        );
+        ctx.accounts.user_points.checkpoint(&ctx.accounts.pool_info);
+        ctx.accounts.guild_points.checkpoint(&ctx.accounts.pool_info);
+        let points_weight = ctx.accounts.nft_vault_account.points_weight;
+        ctx.accounts.user_points.weight -= points_weight;
+        ctx.accounts.guild_points.weight += points_weight;
+        ctx.accounts.guild_points.staked_count += 1;
+        ctx.accounts.nft_vault_account.guilded = true;
+
+        let guild_position = &mut ctx.accounts.guild_position;
+        guild_position.guild = ctx.accounts.guild.key();
+        guild_position.member = ctx.accounts.member.key();
+        guild_position.nft_vault_account = ctx.accounts.nft_vault_account.key();
+        guild_position.deposit_time = now;
+        guild_position.version = GuildPosition::CURRENT_VERSION;
+
+        ctx.accounts.guild.position_count += 1;
+
+        emit!(GuildDeposited {
+            pool_info: ctx.accounts.pool_info.key(),
+            guild: ctx.accounts.guild.key(),
+            member: ctx.accounts.member.key(),
+            nft_mint: ctx.accounts.nft_vault_account.nft_mint,
+            points_weight,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn withdraw_from_guild(
+        ctx: Context<WithdrawFromGuild>,
+    ) -> Result<()> {
+        // Move weight back from guild to member, accrual so far stays with the guild
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.pool_info.checkpoint_points(now);
+        init_user_points(
+            &mut ctx.accounts.user_points,
+            ctx.accounts.member.key(),
+            ctx.accounts.pool_info.key(),
+        );
+        ctx.accounts.user_points.checkpoint(&ctx.accounts.pool_info);
+        ctx.accounts.guild_points.checkpoint(&ctx.accounts.pool_info);
+        let points_weight = ctx.accounts.nft_vault_account.points_weight;
+        ctx.accounts.guild_points.weight -= points_weight;
+        ctx.accounts.guild_points.staked_count -= 1;
+        ctx.accounts.user_points.weight += points_weight;
+        ctx.accounts.nft_vault_account.guilded = false;
+
+        ctx.accounts.guild.position_count -= 1;
+
+        emit!(GuildWithdrawn {
+            pool_info: ctx.accounts.pool_info.key(),
+            guild: ctx.accounts.guild.key(),
+            authority: ctx.accounts.authority.key(),
+            member: ctx.accounts.member.key(),
+            nft_mint: ctx.accounts.nft_vault_account.nft_mint,
+            points_weight,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn claim_guild_share(
+        ctx: Context<ClaimGuildShare>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.pool_info.checkpoint_points(now);
+        ctx.accounts.guild_points.checkpoint(&ctx.accounts.pool_info);
+        ctx.accounts.guild.distribute(&mut ctx.accounts.guild_points);
+
+        let member_key = ctx.accounts.member.key();
+        let member = match ctx.accounts.guild.members.iter_mut().find(|member| member.member == member_key) {
+            Some(member) => member,
+            None => return Err(ErrorCode::NotGuildMember.into()),
+        };
+        let amount = member.owed;
+        member.owed = 0;
+
+        init_user_points(&mut ctx.accounts.user_points, member_key, ctx.accounts.pool_info.key());
+        ctx.accounts.user_points.checkpoint(&ctx.accounts.pool_info);
+        ctx.accounts.user_points.points += amount;
+
+        emit!(GuildShareClaimed {
+            pool_info: ctx.accounts.pool_info.key(),
+            guild: ctx.accounts.guild.key(),
+            member: member_key,
+            amount,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn configure_booster(
+        ctx: Context<ConfigureBooster>,
+        kind: BoosterKind,
+        amount: u64,
+        multiplier_bps: u64,
+        active: bool,
+    ) -> Result<()> {
+        if multiplier_bps < BPS_BASE || amount == 0 || (kind == BoosterKind::Nft && amount != 1) {
+            return Err(ErrorCode::InvalidBoosterConfig.into());
+        };
+        match kind {
+            BoosterKind::Token => {
+                Account::<Mint>::try_from(&ctx.accounts.source)?;
+            }
+            BoosterKind::Nft => {
+                load_program_account::<RarityInfo>(&ctx.accounts.source)?;
+            }
+        };
+
+        let booster_config = &mut ctx.accounts.booster_config;
+        if booster_config.pool_info == Pubkey::default() {
+            booster_config.pool_info = ctx.accounts.pool_info.key();
+            booster_config.source = ctx.accounts.source.key();
+            booster_config.kind = kind;
+            booster_config.bump = *ctx.bumps.get("booster_config").unwrap();
+            booster_config.version = BoosterConfig::CURRENT_VERSION;
+        } else if booster_config.kind != kind {
+            return Err(ErrorCode::InvalidBoosterConfig.into());
+        };
+        booster_config.amount = amount;
+        booster_config.multiplier_bps = multiplier_bps;
+        booster_config.active = active;
+
+        emit!(BoosterConfigured {
+            pool_info: booster_config.pool_info,
+            booster_config: booster_config.key(),
+            source: booster_config.source,
+            kind,
+            amount,
+            multiplier_bps,
+            active,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn attach_booster(
+        ctx: Context<AttachBooster>,
+    ) -> Result<()> {
+        if ctx.accounts.pool_info.paused {
+            return Err(ErrorCode::PoolPaused.into());
+        };
+        let booster_config = &ctx.accounts.booster_config;
+        if !booster_config.active {
+            return Err(ErrorCode::InvalidBoosterConfig.into());
+        };
+        // Weight of guild positions is on the guild's UserPoints
+        if ctx.accounts.nft_vault_account.guilded {
+            return Err(ErrorCode::PositionInGuild.into());
+        };
+        match booster_config.kind {
+            BoosterKind::Token => {
+                if ctx.accounts.booster_mint.key() != booster_config.source {
+                    return Err(ErrorCode::BoosterMintMismatch.into());
+                };
+            }
+            BoosterKind::Nft => {
+                let rarity_info: RarityInfo = load_program_account(&ctx.accounts.booster_source)?;
+                if !rarity_info.mint_list.contains(&ctx.accounts.booster_mint.key()) {
+                    return Err(ErrorCode::BoosterMintMismatch.into());
+                };
+            }
+        };
+
+        token::transfer(
+            ctx.accounts.transfer_booster_to_vault(),
+            booster_config.amount,
+        )?;
+
+        // Update UserPoints, accrual so far is settled before weight changes
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.pool_info.checkpoint_points(now);
+        init_user_points(
+            &mut ctx.accounts.user_points,
+            ctx.accounts.user.key(),
+            ctx.accounts.pool_info.key(),
+        );
+        ctx.accounts.user_points.checkpoint(&ctx.accounts.pool_info);
+        let weight = (ctx.accounts.nft_vault_account.points_weight as u128
+            * (booster_config.multiplier_bps - BPS_BASE) as u128
+            / BPS_BASE as u128) as u64;
+        ctx.accounts.user_points.weight += weight;
+        ctx.accounts.nft_vault_account.points_weight += weight;
+        ctx.accounts.nft_vault_account.boosted = true;
+
+        let booster = &mut ctx.accounts.booster;
+        booster.user = ctx.accounts.user.key();
+        booster.pool_info = ctx.accounts.pool_info.key();
+        booster.nft_vault_account = ctx.accounts.nft_vault_account.key();
+        booster.booster_config = ctx.accounts.booster_config.key();
+        booster.booster_mint = ctx.accounts.booster_mint.key();
+        booster.vault = ctx.accounts.booster_vault.key();
+        booster.amount = ctx.accounts.booster_config.amount;
+        booster.weight = weight;
+        booster.attach_time = now;
+        booster.bump = *ctx.bumps.get("booster").unwrap();
+        booster.version = Booster::CURRENT_VERSION;
+
+        ctx.accounts.booster_config.attached_count += 1;
+
+        emit!(BoosterAttached {
+            pool_info: ctx.accounts.pool_info.key(),
+            booster: ctx.accounts.booster.key(),
+            user: ctx.accounts.user.key(),
+            nft_mint: ctx.accounts.nft_vault_account.nft_mint,
+            booster_mint: ctx.accounts.booster.booster_mint,
+            amount: ctx.accounts.booster.amount,
+            points_weight: ctx.accounts.nft_vault_account.points_weight,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn detach_booster(
+        ctx: Context<DetachBooster>,
+    ) -> Result<()> {
+        if ctx.accounts.nft_vault_account.guilded {
+            return Err(ErrorCode::PositionInGuild.into());
+        };
+
+        let booster = &ctx.accounts.booster;
+        let booster_seeds = &[
+            booster.nft_vault_account.as_ref(),
+            b"booster".as_ref(),
+            &[booster.bump],
+        ];
+
+        // Booster always goes back to the staker, also when the admin detaches it
+        token::transfer(
+            ctx.accounts.transfer_booster_to_owner()
+            .with_signer(&[&booster_seeds[..]]),
+            booster.amount,
+        )?;
+        token::close_account(
+            ctx.accounts.close_booster_vault()
+            .with_signer(&[&booster_seeds[..]]),
+        )?;
+
+        // Update UserPoints of the staker
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.pool_info.checkpoint_points(now);
+        init_user_points(
+            &mut ctx.accounts.user_points,
+            ctx.accounts.nft_vault_account.user,
+            ctx.accounts.pool_info.key(),
+        );
+        ctx.accounts.user_points.checkpoint(&ctx.accounts.pool_info);
+        ctx.accounts.user_points.weight -= ctx.accounts.booster.weight;
+        ctx.accounts.nft_vault_account.points_weight -= ctx.accounts.booster.weight;
+        ctx.accounts.nft_vault_account.boosted = false;
+
+        ctx.accounts.booster_config.attached_count -= 1;
+
+        emit!(BoosterDetached {
+            pool_info: ctx.accounts.pool_info.key(),
+            booster: ctx.accounts.booster.key(),
+            authority: ctx.accounts.authority.key(),
+            user: ctx.accounts.booster.user,
+            nft_mint: ctx.accounts.nft_vault_account.nft_mint,
+            booster_mint: ctx.accounts.booster.booster_mint,
+            amount: ctx.accounts.booster.amount,
+            points_weight: ctx.accounts.nft_vault_account.points_weight,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+}
+
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(mut, signer)]
+    pub admin: AccountInfo<'info>,
+    pub prove_token_mint: Account<'info, Mint>, // prove token mint
+    #[account(mut)]
+    pub admin_prove_token_account: Box<Account<'info, TokenAccount>>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(mut, seeds = [pool_info.key().as_ref(), b"prove_token_vault".as_ref()], bump)]
+    pub prove_token_authority: AccountInfo<'info>, 
+    #[account(mut, constraint = prove_token_mint.to_account_info().key() == prove_token_vault.mint)]
+    pub prove_token_vault: Box<Account<'info, TokenAccount>>, 
+    #[account(
+        init, 
+        seeds = [rarity_info.key().as_ref(), b"pool_info".as_ref()],
+        bump, 
+        space = PoolInfo::LEN,
+        payer = admin
+    )]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    pub rarity_info: Box<Account<'info, RarityInfo>>, 
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub rarity_program: AccountInfo<'info>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub system_program: AccountInfo<'info>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAdmin<'info> {
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(signer)]
+    pub admin: AccountInfo<'info>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub new_admin: AccountInfo<'info>,
+    #[account(
+        mut,  
+        constraint = pool_info.admin == admin.key()
+    )]
+    pub pool_info: Box<Account<'info, PoolInfo>>, 
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    pub new_admin: Signer<'info>,
+    #[account(
+        mut,  
+        constraint = pool_info.pending_admin != Pubkey::default() @ ErrorCode::NoPendingAdmin,
+        constraint = pool_info.pending_admin == new_admin.key() @ ErrorCode::InvalidPendingAdmin
+    )]
+    pub pool_info: Box<Account<'info, PoolInfo>>, 
+}
+
+#[derive(Accounts)]
+pub struct CancelAdminProposal<'info> {
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(signer)]
+    pub admin: AccountInfo<'info>,
+    #[account(
+        mut,  
+        constraint = pool_info.admin == admin.key(),
+        constraint = pool_info.pending_admin != Pubkey::default() @ ErrorCode::NoPendingAdmin
+    )]
+    pub pool_info: Box<Account<'info, PoolInfo>>, 
+}
+
+#[derive(Accounts)]
+pub struct SetRole<'info> {
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(signer)]
+    pub admin: AccountInfo<'info>,
+    #[account(
+        mut,  
+        constraint = pool_info.admin == admin.key()
+    )]
+    pub pool_info: Box<Account<'info, PoolInfo>>, 
+}
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,  
+        constraint = pool_info.has_role(PoolRole::Pauser, authority.key()) @ ErrorCode::MissingRole
+    )]
+    pub pool_info: Box<Account<'info, PoolInfo>>, 
+}
+
+#[derive(Accounts)]
+pub struct UpdateGate<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,  
+        constraint = pool_info.has_role(PoolRole::ConfigManager, authority.key()) @ ErrorCode::MissingRole
+    )]
+    pub pool_info: Box<Account<'info, PoolInfo>>, 
+}
+
+#[derive(Accounts)]
+pub struct UpdatePointsRate<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,  
+        constraint = pool_info.has_role(PoolRole::ConfigManager, authority.key()) @ ErrorCode::MissingRole
+    )]
+    pub pool_info: Box<Account<'info, PoolInfo>>, 
+}
+
+#[derive(Accounts)]
+pub struct UpdateRateCurve<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,  
+        constraint = pool_info.has_role(PoolRole::ConfigManager, authority.key()) @ ErrorCode::MissingRole
+    )]
+    pub pool_info: Box<Account<'info, PoolInfo>>, 
+    #[account(constraint = pool_info.rarity_info == rarity_info.key())]
+    pub rarity_info: Box<Account<'info, RarityInfo>>, 
+}
+
+#[derive(Accounts)]
+pub struct SpendPoints<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,  
+        constraint = pool_info.has_role(PoolRole::RewardManager, authority.key()) @ ErrorCode::MissingRole
+    )]
+    pub pool_info: Box<Account<'info, PoolInfo>>, 
+    #[account(mut, constraint = user_points.pool_info == pool_info.key())]
+    pub user_points: Box<Account<'info, UserPoints>>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateReferral<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,  
+        constraint = pool_info.has_role(PoolRole::ConfigManager, authority.key()) @ ErrorCode::MissingRole
+    )]
+    pub pool_info: Box<Account<'info, PoolInfo>>, 
+}
+
+#[derive(Accounts)]
+pub struct ClaimReferral<'info> {
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+    #[account(mut)]
+    pub pool_info: Box<Account<'info, PoolInfo>>, 
+    #[account(
+        mut,
+        constraint = user_points.pool_info == pool_info.key(),
+        constraint = user_points.referrer != Pubkey::default() @ ErrorCode::NoReferrer,
+        constraint = user_points.referrer == referrer.key() @ ErrorCode::NotReferrer
+    )]
+    pub user_points: Box<Account<'info, UserPoints>>,
+    #[account(
+        init_if_needed,
+        seeds = [pool_info.key().as_ref(), referrer.key().as_ref(), b"user_points".as_ref()],
+        bump,
+        payer = referrer,
+        space = UserPoints::LEN
+    )]
+    pub referrer_points: Box<Account<'info, UserPoints>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Stake<'info> {
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(mut, signer)]
+    pub user: AccountInfo<'info>,
+    #[account(
+        mut, 
+        seeds = [rarity_info.key().as_ref(), b"pool_info".as_ref()],
+        bump,
+        constraint = prove_token_mint.to_account_info().key() == pool_info.prove_token_mint
+    )]
+    pub pool_info: Box<Account<'info, PoolInfo>>, 
+    pub prove_token_mint: Box<Account<'info, Mint>>,
+    pub nft_mint: Box<Account<'info, Mint>>, 
+    #[account(constraint = pool_info.rarity_info == rarity_info.key())]
+    pub rarity_info: Box<Account<'info, RarityInfo>>, 
+    #[account(mut, 
+        constraint = nft_mint.to_account_info().key() == user_nft_account.mint, 
+        constraint = user.to_account_info().key() == user_nft_account.owner)]
+    pub user_nft_account: Box<Account<'info, TokenAccount>>, 
+    #[account(mut, constraint = prove_token_mint.to_account_info().key() == user_prove_token_account.mint)]
+    pub user_prove_token_account: Box<Account<'info, TokenAccount>>, 
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(mut, seeds = [pool_info.key().as_ref(), b"prove_token_vault".as_ref()], bump, 
+        constraint = pool_info.prove_token_authority == prove_token_authority.to_account_info().key())]
+    pub prove_token_authority: AccountInfo<'info>, 
+    #[account(mut, 
+        constraint = prove_token_mint.to_account_info().key() == prove_token_vault.mint, 
+        constraint = prove_token_vault.owner == prove_token_authority.to_account_info().key())]
+    pub prove_token_vault: Box<Account<'info, TokenAccount>>, 
+    #[account(
+        init,
+        seeds = [
+            nft_mint.key().as_ref(), 
+            pool_info.key().as_ref(),
+            b"nft_vault".as_ref()
+            ], 
+        bump,
+        payer = user,
+        space = NftVaultAccount::LEN
+    )]
+    pub nft_vault_account: Box<Account<'info, NftVaultAccount>>, 
+    #[account(
+        init,
+        associated_token::mint = nft_mint,
+        associated_token::authority = nft_vault_account,
+        payer = user
+    )]
+    pub nft_vault_ata: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        seeds = [pool_info.key().as_ref(), user.key().as_ref(), b"user_points".as_ref()],
+        bump,
+        payer = user,
+        space = UserPoints::LEN
+    )]
+    pub user_points: Box<Account<'info, UserPoints>>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub system_program: AccountInfo<'info>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: AccountInfo<'info>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct Unstake<'info> {
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(mut, signer)]
+    pub user: AccountInfo<'info>,
+    #[account(
+        mut, 
+        seeds = [rarity_info.key().as_ref(), b"pool_info".as_ref()],
+        bump,
+        constraint = prove_token_mint.to_account_info().key() == pool_info.prove_token_mint
+    )]
+    pub pool_info: Box<Account<'info, PoolInfo>>, 
+    pub prove_token_mint: Account<'info, Mint>,
+    pub nft_mint: Account<'info, Mint>, 
+    #[account(constraint = pool_info.rarity_info == rarity_info.key())]
+    pub rarity_info: Box<Account<'info, RarityInfo>>, 
+    /// CHECK: Wallet receiving the NFT, user unless redirected
+    pub recipient: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        associated_token::mint = nft_mint,
+        associated_token::authority = recipient,
+        payer = user
+    )]
+    pub user_nft_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut, 
+        constraint = nft_mint.to_account_info().key() == nft_vault_ata.mint, 
+        constraint = nft_vault_account.to_account_info().key() == nft_vault_ata.owner,
+    )]
+    pub nft_vault_ata: Box<Account<'info, TokenAccount>>, 
+    #[account(mut, constraint = prove_token_mint.to_account_info().key() == user_prove_token_account.mint)]
+    pub user_prove_token_account: Box<Account<'info, TokenAccount>>, 
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(mut, seeds = [pool_info.key().as_ref(), b"prove_token_vault".as_ref()], bump,
+    constraint = pool_info.prove_token_authority == prove_token_authority.to_account_info().key())]
+    pub prove_token_authority: AccountInfo<'info>, 
+    #[account(mut, 
+        constraint = prove_token_mint.to_account_info().key() == prove_token_vault.mint, 
+        constraint = prove_token_vault.owner == prove_token_authority.to_account_info().key())]
+    pub prove_token_vault: Box<Account<'info, TokenAccount>>, 
+    #[account(
+        mut,
+        seeds = [
+            nft_mint.key().as_ref(), 
+            pool_info.key().as_ref(),
+            b"nft_vault".as_ref()
+            ], 
+        bump,
+    )]
+    /// CHECK: Older layouts do not deserialize into NftVaultAccount, the instruction migrates it first
+    pub nft_vault_account: AccountInfo<'info>, 
+    #[account(
+        init_if_needed,
+        seeds = [pool_info.key().as_ref(), user.key().as_ref(), b"user_points".as_ref()],
+        bump,
+        payer = user,
+        space = UserPoints::LEN
+    )]
+    pub user_points: Box<Account<'info, UserPoints>>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct ForceUnstake<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(mut, constraint = pool_info.admin == admin.key())]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    pub nft_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [
+            nft_mint.key().as_ref(), 
+            pool_info.key().as_ref(),
+            b"nft_vault".as_ref()
+            ], 
+        bump,
+        close = owner
+    )]
+    pub nft_vault_account: Box<Account<'info, NftVaultAccount>>, 
+    #[account(mut, 
+        constraint = nft_mint.to_account_info().key() == nft_vault_ata.mint, 
+        constraint = nft_vault_account.to_account_info().key() == nft_vault_ata.owner,
+    )]
+    pub nft_vault_ata: Box<Account<'info, TokenAccount>>, 
+    /// CHECK: Recorded staker, receives the NFT and rent
+    #[account(mut, constraint = nft_vault_account.user == owner.key())]
+    pub owner: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        associated_token::mint = nft_mint,
+        associated_token::authority = owner,
+        payer = admin
+    )]
+    pub owner_nft_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        seeds = [pool_info.key().as_ref(), owner.key().as_ref(), b"user_points".as_ref()],
+        bump,
+        payer = admin,
+        space = UserPoints::LEN
+    )]
+    pub user_points: Box<Account<'info, UserPoints>>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct ForceUnstakeSft<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(mut, constraint = pool_info.admin == admin.key())]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    pub nft_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [
+            nft_mint.key().as_ref(), 
+            pool_info.key().as_ref(),
+            owner.key().as_ref(),
+            b"sft_vault".as_ref()
+            ], 
+        bump,
+        close = owner
+    )]
+    pub sft_vault_account: Box<Account<'info, SftVaultAccount>>, 
+    #[account(mut, 
+        constraint = nft_mint.to_account_info().key() == sft_vault_ata.mint, 
+        constraint = sft_vault_account.key() == sft_vault_ata.owner,
+    )]
+    pub sft_vault_ata: Box<Account<'info, TokenAccount>>, 
+    /// CHECK: Recorded staker, receives the SFTs and rent
+    #[account(mut, constraint = sft_vault_account.user == owner.key())]
+    pub owner: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        associated_token::mint = nft_mint,
+        associated_token::authority = owner,
+        payer = admin
+    )]
+    pub owner_nft_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [pool_info.key().as_ref(), owner.key().as_ref(), b"user_points".as_ref()],
+        bump,
+    )]
+    pub user_points: Box<Account<'info, UserPoints>>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(leaf: CompressedLeaf)]
+pub struct ForceUnstakeCompressed<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(mut, constraint = pool_info.admin == admin.key())]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(
+        mut,
+        seeds = [
+            merkle_tree.key().as_ref(),
+            &leaf.nonce.to_le_bytes(),
+            pool_info.key().as_ref(),
+            b"cnft_vault".as_ref()
+            ],
+        bump,
+        close = owner
+    )]
+    pub cnft_vault_account: Box<Account<'info, CnftVaultAccount>>,
+    /// CHECK: Recorded staker, receives the leaf and rent
+    #[account(mut, constraint = cnft_vault_account.user == owner.key())]
+    pub owner: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [pool_info.key().as_ref(), owner.key().as_ref(), b"user_points".as_ref()],
+        bump,
+    )]
+    pub user_points: Box<Account<'info, UserPoints>>,
+    /// CHECK: Tree config PDA, checked by Bubblegum
+    pub tree_authority: AccountInfo<'info>,
+    /// CHECK: Concurrent merkle tree, checked by account compression
+    #[account(mut)]
+    pub merkle_tree: AccountInfo<'info>,
+    /// CHECK: Checked by address
+    #[account(address = bubblegum::noop_program::ID)]
+    pub log_wrapper: AccountInfo<'info>,
+    /// CHECK: Checked by address
+    #[account(address = bubblegum::compression_program::ID)]
+    pub compression_program: AccountInfo<'info>,
+    /// CHECK: Checked by address
+    #[account(address = bubblegum::ID)]
+    pub bubblegum_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePosition<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(mut)]
+    pub old_pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(
+        mut,
+        constraint = new_pool_info.key() != old_pool_info.key() @ ErrorCode::InvalidMigrationAccount,
+        constraint = new_pool_info.admin == old_pool_info.admin @ ErrorCode::InvalidMigrationAccount
+    )]
+    pub new_pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(constraint = old_pool_info.rarity_info == old_rarity_info.key())]
+    pub old_rarity_info: Box<Account<'info, RarityInfo>>,
+    #[account(constraint = new_pool_info.rarity_info == new_rarity_info.key())]
+    pub new_rarity_info: Box<Account<'info, RarityInfo>>,
+    pub nft_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [
+            nft_mint.key().as_ref(), 
+            old_pool_info.key().as_ref(),
+            b"nft_vault".as_ref()
+            ], 
+        bump,
+        constraint = old_nft_vault_account.user == user.key() @ ErrorCode::NftVaultMismatch,
+        close = user
+    )]
+    pub old_nft_vault_account: Box<Account<'info, NftVaultAccount>>,
+    #[account(mut, 
+        constraint = nft_mint.key() == old_nft_vault_ata.mint, 
+        constraint = old_nft_vault_account.key() == old_nft_vault_ata.owner,
+    )]
+    pub old_nft_vault_ata: Box<Account<'info, TokenAccount>>, 
+    #[account(
+        init,
+        seeds = [
+            nft_mint.key().as_ref(), 
+            new_pool_info.key().as_ref(),
+            b"nft_vault".as_ref()
+            ], 
+        bump,
+        payer = user,
+        space = NftVaultAccount::LEN
+    )]
+    pub new_nft_vault_account: Box<Account<'info, NftVaultAccount>>,
+    #[account(
+        init,
+        associated_token::mint = nft_mint,
+        associated_token::authority = new_nft_vault_account,
+        payer = user
+    )]
+    pub new_nft_vault_ata: Box<Account<'info, TokenAccount>>,
+    #[account(mut, constraint = old_pool_info.prove_token_mint == user_old_prove_token_account.mint)]
+    pub user_old_prove_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut, constraint = old_pool_info.prove_token_vault == old_prove_token_vault.key())]
+    pub old_prove_token_vault: Box<Account<'info, TokenAccount>>,
+    #[account(mut, constraint = new_pool_info.prove_token_mint == user_new_prove_token_account.mint)]
+    pub user_new_prove_token_account: Box<Account<'info, TokenAccount>>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(seeds = [new_pool_info.key().as_ref(), b"prove_token_vault".as_ref()], bump,
+        constraint = new_pool_info.prove_token_authority == new_prove_token_authority.key())]
+    pub new_prove_token_authority: AccountInfo<'info>,
+    #[account(mut, constraint = new_pool_info.prove_token_vault == new_prove_token_vault.key())]
+    pub new_prove_token_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        seeds = [old_pool_info.key().as_ref(), old_nft_vault_account.user.as_ref(), b"user_points".as_ref()],
+        bump,
+        payer = user,
+        space = UserPoints::LEN
+    )]
+    pub old_user_points: Box<Account<'info, UserPoints>>,
+    #[account(
+        init_if_needed,
+        seeds = [new_pool_info.key().as_ref(), user.key().as_ref(), b"user_points".as_ref()],
+        bump,
+        payer = user,
+        space = UserPoints::LEN
+    )]
+    pub new_user_points: Box<Account<'info, UserPoints>>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: AccountInfo<'info>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateSft<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut, constraint = pool_info.has_role(PoolRole::ConfigManager, authority.key()) @ ErrorCode::MissingRole)]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(mut, 
+        constraint = pool_info.prove_token_mint == authority_prove_token_account.mint,
+        constraint = authority.key() == authority_prove_token_account.owner)]
+    pub authority_prove_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut, constraint = pool_info.prove_token_vault == prove_token_vault.key())]
+    pub prove_token_vault: Box<Account<'info, TokenAccount>>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct StakeSft<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut, 
+        seeds = [rarity_info.key().as_ref(), b"pool_info".as_ref()],
+        bump,
+        constraint = prove_token_mint.to_account_info().key() == pool_info.prove_token_mint
+    )]
+    pub pool_info: Box<Account<'info, PoolInfo>>, 
+    pub prove_token_mint: Box<Account<'info, Mint>>,
+    pub nft_mint: Box<Account<'info, Mint>>, 
+    #[account(constraint = pool_info.rarity_info == rarity_info.key())]
+    pub rarity_info: Box<Account<'info, RarityInfo>>, 
+    #[account(mut, 
+        constraint = nft_mint.to_account_info().key() == user_nft_account.mint, 
+        constraint = user.key() == user_nft_account.owner)]
+    pub user_nft_account: Box<Account<'info, TokenAccount>>, 
+    #[account(mut, constraint = prove_token_mint.to_account_info().key() == user_prove_token_account.mint)]
+    pub user_prove_token_account: Box<Account<'info, TokenAccount>>, 
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(seeds = [pool_info.key().as_ref(), b"prove_token_vault".as_ref()], bump, 
+        constraint = pool_info.prove_token_authority == prove_token_authority.to_account_info().key())]
+    pub prove_token_authority: AccountInfo<'info>, 
+    #[account(mut, 
+        constraint = prove_token_mint.to_account_info().key() == prove_token_vault.mint, 
+        constraint = prove_token_vault.owner == prove_token_authority.to_account_info().key())]
+    pub prove_token_vault: Box<Account<'info, TokenAccount>>, 
+    #[account(
+        init_if_needed,
+        seeds = [
+            nft_mint.key().as_ref(), 
+            pool_info.key().as_ref(),
+            user.key().as_ref(),
+            b"sft_vault".as_ref()
+            ], 
+        bump,
+        payer = user,
+        space = SftVaultAccount::LEN
+    )]
+    pub sft_vault_account: Box<Account<'info, SftVaultAccount>>, 
+    #[account(
+        init_if_needed,
+        associated_token::mint = nft_mint,
+        associated_token::authority = sft_vault_account,
+        payer = user
+    )]
+    pub sft_vault_ata: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        seeds = [pool_info.key().as_ref(), user.key().as_ref(), b"user_points".as_ref()],
+        bump,
+        payer = user,
+        space = UserPoints::LEN
+    )]
+    pub user_points: Box<Account<'info, UserPoints>>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: AccountInfo<'info>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct UnstakeSft<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut, 
+        seeds = [rarity_info.key().as_ref(), b"pool_info".as_ref()],
+        bump,
+        constraint = prove_token_mint.to_account_info().key() == pool_info.prove_token_mint
+    )]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    pub prove_token_mint: Box<Account<'info, Mint>>,
+    pub nft_mint: Box<Account<'info, Mint>>, 
+    #[account(constraint = pool_info.rarity_info == rarity_info.key())]
+    pub rarity_info: Box<Account<'info, RarityInfo>>, 
+    #[account(mut, 
+        constraint = nft_mint.to_account_info().key() == user_nft_account.mint, 
+        constraint = user.key() == user_nft_account.owner)]
+    pub user_nft_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut, constraint = prove_token_mint.to_account_info().key() == user_prove_token_account.mint)]
+    pub user_prove_token_account: Box<Account<'info, TokenAccount>>, 
+    #[account(mut, 
+        constraint = prove_token_mint.to_account_info().key() == prove_token_vault.mint, 
+        constraint = prove_token_vault.key() == pool_info.prove_token_vault)]
+    pub prove_token_vault: Box<Account<'info, TokenAccount>>, 
+    #[account(
+        mut,
+        seeds = [
+            nft_mint.key().as_ref(), 
+            pool_info.key().as_ref(),
+            user.key().as_ref(),
+            b"sft_vault".as_ref()
+            ], 
+        bump,
+    )]
+    pub sft_vault_account: Box<Account<'info, SftVaultAccount>>, 
+    #[account(mut, 
+        constraint = nft_mint.to_account_info().key() == sft_vault_ata.mint, 
+        constraint = sft_vault_account.key() == sft_vault_ata.owner,
+    )]
+    pub sft_vault_ata: Box<Account<'info, TokenAccount>>, 
+    #[account(
+        mut,
+        seeds = [pool_info.key().as_ref(), user.key().as_ref(), b"user_points".as_ref()],
+        bump,
+    )]
+    pub user_points: Box<Account<'info, UserPoints>>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(leaf: CompressedLeaf)]
+pub struct StakeCompressed<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut, 
+        seeds = [rarity_info.key().as_ref(), b"pool_info".as_ref()],
+        bump,
+        constraint = prove_token_mint.to_account_info().key() == pool_info.prove_token_mint
+    )]
+    pub pool_info: Box<Account<'info, PoolInfo>>, 
+    pub prove_token_mint: Account<'info, Mint>,
+    #[account(constraint = pool_info.rarity_info == rarity_info.key())]
+    pub rarity_info: Box<Account<'info, RarityInfo>>, 
+    #[account(mut, 
+        constraint = prove_token_mint.to_account_info().key() == user_prove_token_account.mint,
+        constraint = user.key() == user_prove_token_account.owner)]
+    pub user_prove_token_account: Box<Account<'info, TokenAccount>>, 
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(seeds = [pool_info.key().as_ref(), b"prove_token_vault".as_ref()], bump,
+    constraint = pool_info.prove_token_authority == prove_token_authority.to_account_info().key())]
+    pub prove_token_authority: AccountInfo<'info>, 
+    #[account(mut, 
+        constraint = prove_token_mint.to_account_info().key() == prove_token_vault.mint, 
+        constraint = prove_token_vault.owner == prove_token_authority.to_account_info().key())]
+    pub prove_token_vault: Box<Account<'info, TokenAccount>>, 
+    #[account(
+        init,
+        seeds = [
+            merkle_tree.key().as_ref(),
+            &leaf.nonce.to_le_bytes(),
+            pool_info.key().as_ref(),
+            b"cnft_vault".as_ref()
+            ],
+        bump,
+        payer = user,
+        space = CnftVaultAccount::LEN
+    )]
+    pub cnft_vault_account: Box<Account<'info, CnftVaultAccount>>,
+    #[account(
+        init_if_needed,
+        seeds = [pool_info.key().as_ref(), user.key().as_ref(), b"user_points".as_ref()],
+        bump,
+        payer = user,
+        space = UserPoints::LEN
+    )]
+    pub user_points: Box<Account<'info, UserPoints>>,
+    /// CHECK: Tree config PDA, checked by Bubblegum
+    pub tree_authority: AccountInfo<'info>,
+    /// CHECK: Leaf delegate, the user if none was set, checked by Bubblegum
+    pub leaf_delegate: AccountInfo<'info>,
+    /// CHECK: Concurrent merkle tree, checked by account compression
+    #[account(mut)]
+    pub merkle_tree: AccountInfo<'info>,
+    /// CHECK: Checked by address
+    #[account(address = bubblegum::noop_program::ID)]
+    pub log_wrapper: AccountInfo<'info>,
+    /// CHECK: Checked by address
+    #[account(address = bubblegum::compression_program::ID)]
+    pub compression_program: AccountInfo<'info>,
+    /// CHECK: Checked by address
+    #[account(address = bubblegum::ID)]
+    pub bubblegum_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(leaf: CompressedLeaf)]
+pub struct UnstakeCompressed<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut, 
+        seeds = [rarity_info.key().as_ref(), b"pool_info".as_ref()],
+        bump,
+        constraint = prove_token_mint.to_account_info().key() == pool_info.prove_token_mint
+    )]
+    pub pool_info: Box<Account<'info, PoolInfo>>, 
+    pub prove_token_mint: Account<'info, Mint>,
+    #[account(constraint = pool_info.rarity_info == rarity_info.key())]
+    pub rarity_info: Box<Account<'info, RarityInfo>>, 
+    #[account(mut, constraint = prove_token_mint.to_account_info().key() == user_prove_token_account.mint)]
+    pub user_prove_token_account: Box<Account<'info, TokenAccount>>, 
+    #[account(mut, 
+        constraint = prove_token_mint.to_account_info().key() == prove_token_vault.mint, 
+        constraint = prove_token_vault.key() == pool_info.prove_token_vault)]
+    pub prove_token_vault: Box<Account<'info, TokenAccount>>, 
+    #[account(
+        mut,
+        seeds = [
+            merkle_tree.key().as_ref(),
+            &leaf.nonce.to_le_bytes(),
+            pool_info.key().as_ref(),
+            b"cnft_vault".as_ref()
+            ],
+        bump,
+        constraint = cnft_vault_account.user == user.key() @ ErrorCode::CnftVaultMismatch,
+        close = user
+    )]
+    pub cnft_vault_account: Box<Account<'info, CnftVaultAccount>>,
+    #[account(
+        mut,
+        seeds = [pool_info.key().as_ref(), cnft_vault_account.user.as_ref(), b"user_points".as_ref()],
+        bump,
+    )]
+    pub user_points: Box<Account<'info, UserPoints>>,
+    /// CHECK: Tree config PDA, checked by Bubblegum
+    pub tree_authority: AccountInfo<'info>,
+    /// CHECK: Concurrent merkle tree, checked by account compression
+    #[account(mut)]
+    pub merkle_tree: AccountInfo<'info>,
+    /// CHECK: Checked by address
+    #[account(address = bubblegum::noop_program::ID)]
+    pub log_wrapper: AccountInfo<'info>,
+    /// CHECK: Checked by address
+    #[account(address = bubblegum::compression_program::ID)]
+    pub compression_program: AccountInfo<'info>,
+    /// CHECK: Checked by address
+    #[account(address = bubblegum::ID)]
+    pub bubblegum_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(nonce: u64)]
+pub struct InitializeSet<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(constraint = pool_info.has_role(PoolRole::ConfigManager, authority.key()) @ ErrorCode::MissingRole)]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(
+        init,
+        seeds = [pool_info.key().as_ref(), &nonce.to_le_bytes(), b"set_info".as_ref()],
+        bump,
+        space = SetInfo::LEN,
+        payer = authority
+    )]
+    pub set_info: Box<Account<'info, SetInfo>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterSet<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub set_info: Box<Account<'info, SetInfo>>,
+    #[account(
+        init,
+        seeds = [set_info.key().as_ref(), user.key().as_ref(), b"set_bonus".as_ref()],
+        bump,
+        space = SetBonus::LEN,
+        payer = user
+    )]
+    pub set_bonus: Box<Account<'info, SetBonus>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePool<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    /// CHECK: Old layouts do not deserialize into PoolInfo, the instruction checks discriminator and admin
+    #[account(mut, owner = crate::ID)]
+    pub pool_info: AccountInfo<'info>,
+    pub rarity_info: Box<Account<'info, RarityInfo>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AuditPool<'info> {
+    #[account(
+        seeds = [rarity_info.key().as_ref(), b"pool_info".as_ref()],
+        bump,
+    )]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(constraint = pool_info.rarity_info == rarity_info.key())]
+    pub rarity_info: Box<Account<'info, RarityInfo>>,
+    #[account(constraint = pool_info.prove_token_vault == prove_token_vault.key())]
+    pub prove_token_vault: Box<Account<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct GetPosition<'info> {
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    /// CHECK: Only its key is used to derive nft_vault_account
+    pub nft_mint: AccountInfo<'info>,
+    /// CHECK: Empty when the NFT is not staked, deserialized otherwise
+    #[account(
+        seeds = [
+            nft_mint.key().as_ref(), 
+            pool_info.key().as_ref(),
+            b"nft_vault".as_ref()
+            ], 
+        bump,
+    )]
+    pub nft_vault_account: AccountInfo<'info>,
+    /// CHECK: Deserialized only when the NftVaultAccount is boosted
+    #[account(
+        seeds = [nft_vault_account.key().as_ref(), b"booster".as_ref()],
+        bump,
+    )]
+    pub booster: AccountInfo<'info>,
+    /// CHECK: Deserialized only when the NftVaultAccount is guilded
+    #[account(
+        seeds = [nft_vault_account.key().as_ref(), b"guild_position".as_ref()],
+        bump,
+    )]
+    pub guild_position: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetPoolStats<'info> {
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeLeaderboard<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(constraint = pool_info.has_role(PoolRole::ConfigManager, authority.key()) @ ErrorCode::MissingRole)]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(
+        init,
+        seeds = [pool_info.key().as_ref(), b"leaderboard".as_ref()],
+        bump,
+        payer = authority,
+        space = Leaderboard::LEN
+    )]
+    pub leaderboard: Box<Account<'info, Leaderboard>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RefreshLeaderboard<'info> {
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(
+        mut,
+        seeds = [pool_info.key().as_ref(), b"leaderboard".as_ref()],
+        bump,
+    )]
+    pub leaderboard: Box<Account<'info, Leaderboard>>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64, merkle_root: [u8; 32], total_amount: u64, num_leaves: u64)]
+pub struct PublishDistribution<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(constraint = pool_info.has_role(PoolRole::RewardManager, authority.key()) @ ErrorCode::MissingRole)]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    pub reward_mint: Box<Account<'info, Mint>>,
+    #[account(mut, 
+        constraint = reward_mint.key() == authority_reward_account.mint,
+        constraint = authority.key() == authority_reward_account.owner)]
+    pub authority_reward_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init,
+        seeds = [pool_info.key().as_ref(), &index.to_le_bytes(), b"distribution".as_ref()],
+        bump,
+        payer = authority,
+        space = Distribution::space(num_leaves)
+    )]
+    pub distribution: Box<Account<'info, Distribution>>,
+    #[account(
+        init,
+        associated_token::mint = reward_mint,
+        associated_token::authority = distribution,
+        payer = authority
+    )]
+    pub distribution_vault: Box<Account<'info, TokenAccount>>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: AccountInfo<'info>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimDistribution<'info> {
+    pub user: Signer<'info>,
+    #[account(constraint = distribution.pool_info == pool_info.key())]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(mut)]
+    pub distribution: Box<Account<'info, Distribution>>,
+    #[account(mut, constraint = distribution.vault == distribution_vault.key())]
+    pub distribution_vault: Box<Account<'info, TokenAccount>>,
+    #[account(mut, constraint = distribution.reward_mint == user_reward_account.mint)]
+    pub user_reward_account: Box<Account<'info, TokenAccount>>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateVesting<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,  
+        constraint = pool_info.has_role(PoolRole::RewardManager, authority.key()) @ ErrorCode::MissingRole
+    )]
+    pub pool_info: Box<Account<'info, PoolInfo>>, 
+}
+
+#[derive(Accounts)]
+pub struct ClaimDistributionVested<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(constraint = distribution.pool_info == pool_info.key())]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(mut)]
+    pub distribution: Box<Account<'info, Distribution>>,
+    #[account(mut, constraint = distribution.vault == distribution_vault.key())]
+    pub distribution_vault: Box<Account<'info, TokenAccount>>,
+    #[account(constraint = distribution.reward_mint == reward_mint.key())]
+    pub reward_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init_if_needed,
+        seeds = [pool_info.key().as_ref(), user.key().as_ref(), reward_mint.key().as_ref(), b"vesting_escrow".as_ref()],
+        bump,
+        payer = user,
+        space = VestingEscrow::LEN
+    )]
+    pub vesting_escrow: Box<Account<'info, VestingEscrow>>,
+    #[account(
+        init_if_needed,
+        associated_token::mint = reward_mint,
+        associated_token::authority = vesting_escrow,
+        payer = user
+    )]
+    pub vesting_vault: Box<Account<'info, TokenAccount>>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: AccountInfo<'info>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawVested<'info> {
+    pub user: Signer<'info>,
+    #[account(mut, constraint = vesting_escrow.user == user.key())]
+    pub vesting_escrow: Box<Account<'info, VestingEscrow>>,
+    #[account(mut, constraint = vesting_escrow.vault == vesting_vault.key())]
+    pub vesting_vault: Box<Account<'info, TokenAccount>>,
+    #[account(mut, constraint = vesting_escrow.reward_mint == user_reward_account.mint)]
+    pub user_reward_account: Box<Account<'info, TokenAccount>>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct CommitRaffle<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(constraint = pool_info.has_role(PoolRole::RewardManager, authority.key()) @ ErrorCode::MissingRole)]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(
+        init,
+        seeds = [pool_info.key().as_ref(), &index.to_le_bytes(), b"raffle".as_ref()],
+        bump,
+        payer = authority,
+        space = Raffle::LEN
+    )]
+    pub raffle: Box<Account<'info, Raffle>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EnterRaffle<'info> {
+    pub user: Signer<'info>,
+    #[account(mut)]
+    pub raffle: Box<Account<'info, Raffle>>,
+}
+
+#[derive(Accounts)]
+pub struct DrawRaffle<'info> {
+    #[account(mut)]
+    pub raffle: Box<Account<'info, Raffle>>,
+    /// CHECK: SlotHashes sysvar is parsed manually, it is too large to deserialize
+    #[account(address = sysvar::slot_hashes::ID)]
+    pub slot_hashes: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelRaffle<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(constraint = pool_info.has_role(PoolRole::RewardManager, authority.key()) @ ErrorCode::MissingRole)]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(mut, constraint = raffle.pool_info == pool_info.key(), close = authority)]
+    pub raffle: Box<Account<'info, Raffle>>,
+    /// CHECK: SlotHashes sysvar is parsed manually, it is too large to deserialize
+    #[account(address = sysvar::slot_hashes::ID)]
+    pub slot_hashes: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeLoanConfig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(constraint = pool_info.has_role(PoolRole::ConfigManager, authority.key()) @ ErrorCode::MissingRole)]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    pub loan_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        seeds = [pool_info.key().as_ref(), b"loan_config".as_ref()],
+        bump,
+        payer = authority,
+        space = LoanConfig::LEN
+    )]
+    pub loan_config: Box<Account<'info, LoanConfig>>,
+    #[account(
+        init,
+        associated_token::mint = loan_mint,
+        associated_token::authority = loan_config,
+        payer = authority
+    )]
+    pub loan_vault: Box<Account<'info, TokenAccount>>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: AccountInfo<'info>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateLoanTerms<'info> {
+    pub authority: Signer<'info>,
+    #[account(constraint = pool_info.has_role(PoolRole::ConfigManager, authority.key()) @ ErrorCode::MissingRole)]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(mut, seeds = [pool_info.key().as_ref(), b"loan_config".as_ref()], bump = loan_config.bump)]
+    pub loan_config: Box<Account<'info, LoanConfig>>,
+}
+
+#[derive(Accounts)]
+pub struct FundLoanVault<'info> {
+    pub authority: Signer<'info>,
+    #[account(constraint = pool_info.has_role(PoolRole::RewardManager, authority.key()) @ ErrorCode::MissingRole)]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(seeds = [pool_info.key().as_ref(), b"loan_config".as_ref()], bump = loan_config.bump)]
+    pub loan_config: Box<Account<'info, LoanConfig>>,
+    #[account(mut, constraint = loan_config.loan_vault == loan_vault.key())]
+    pub loan_vault: Box<Account<'info, TokenAccount>>,
+    #[account(mut, 
+        constraint = loan_config.loan_mint == authority_loan_account.mint,
+        constraint = authority.key() == authority_loan_account.owner)]
+    pub authority_loan_account: Box<Account<'info, TokenAccount>>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawLoanVault<'info> {
+    pub authority: Signer<'info>,
+    #[account(constraint = pool_info.has_role(PoolRole::RewardManager, authority.key()) @ ErrorCode::MissingRole)]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(seeds = [pool_info.key().as_ref(), b"loan_config".as_ref()], bump = loan_config.bump)]
+    pub loan_config: Box<Account<'info, LoanConfig>>,
+    #[account(mut, constraint = loan_config.loan_vault == loan_vault.key())]
+    pub loan_vault: Box<Account<'info, TokenAccount>>,
+    #[account(mut, 
+        constraint = loan_config.loan_mint == authority_loan_account.mint,
+        constraint = authority.key() == authority_loan_account.owner)]
+    pub authority_loan_account: Box<Account<'info, TokenAccount>>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Borrow<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(mut, seeds = [pool_info.key().as_ref(), b"loan_config".as_ref()], bump = loan_config.bump)]
+    pub loan_config: Box<Account<'info, LoanConfig>>,
+    #[account(
+        mut,
+        constraint = nft_vault_account.pool_info == pool_info.key() @ ErrorCode::NftVaultMismatch,
+        constraint = nft_vault_account.user == user.key() @ ErrorCode::NftVaultMismatch
+    )]
+    pub nft_vault_account: Box<Account<'info, NftVaultAccount>>,
+    #[account(
+        init,
+        seeds = [nft_vault_account.key().as_ref(), b"loan".as_ref()],
+        bump,
+        payer = user,
+        space = Loan::LEN
+    )]
+    pub loan: Box<Account<'info, Loan>>,
+    #[account(mut, constraint = loan_config.loan_vault == loan_vault.key())]
+    pub loan_vault: Box<Account<'info, TokenAccount>>,
+    #[account(mut, constraint = loan_config.loan_mint == user_loan_account.mint)]
+    pub user_loan_account: Box<Account<'info, TokenAccount>>,
+    #[account(constraint = prove_token_mint.key() == pool_info.prove_token_mint)]
+    pub prove_token_mint: Box<Account<'info, Mint>>,
+    #[account(mut, 
+        constraint = prove_token_mint.key() == user_prove_token_account.mint,
+        constraint = user.key() == user_prove_token_account.owner)]
+    pub user_prove_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init,
+        associated_token::mint = prove_token_mint,
+        associated_token::authority = loan,
+        payer = user
+    )]
+    pub loan_prove_token_account: Box<Account<'info, TokenAccount>>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: AccountInfo<'info>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct RepayLoan<'info> {
+    pub payer: Signer<'info>,
+    #[account(constraint = loan.pool_info == pool_info.key())]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(mut, seeds = [loan.pool_info.as_ref(), b"loan_config".as_ref()], bump = loan_config.bump)]
+    pub loan_config: Box<Account<'info, LoanConfig>>,
+    #[account(mut, constraint = loan.nft_vault_account == nft_vault_account.key())]
+    pub nft_vault_account: Box<Account<'info, NftVaultAccount>>,
+    #[account(
+        mut,
+        seeds = [nft_vault_account.key().as_ref(), b"loan".as_ref()],
+        bump,
+        constraint = loan.borrower == borrower.key(),
+        close = borrower
+    )]
+    pub loan: Box<Account<'info, Loan>>,
+    /// CHECK: Receives the Loan rent, checked against loan.borrower
+    #[account(mut)]
+    pub borrower: AccountInfo<'info>,
+    #[account(mut, constraint = loan_config.loan_vault == loan_vault.key())]
+    pub loan_vault: Box<Account<'info, TokenAccount>>,
+    #[account(mut, constraint = loan.loan_mint == payer_loan_account.mint)]
+    pub payer_loan_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut, 
+        constraint = loan_prove_token_account.key()
+            == get_associated_token_address(&loan.key(), &pool_info.prove_token_mint))]
+    pub loan_prove_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut, 
+        constraint = pool_info.prove_token_mint == borrower_prove_token_account.mint,
+        constraint = borrower.key() == borrower_prove_token_account.owner)]
+    pub borrower_prove_token_account: Box<Account<'info, TokenAccount>>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SeizeLoan<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut, constraint = pool_info.has_role(PoolRole::RewardManager, authority.key()) @ ErrorCode::MissingRole)]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(mut, seeds = [pool_info.key().as_ref(), b"loan_config".as_ref()], bump = loan_config.bump)]
+    pub loan_config: Box<Account<'info, LoanConfig>>,
+    pub nft_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [
+            nft_mint.key().as_ref(), 
+            pool_info.key().as_ref(),
+            b"nft_vault".as_ref()
+            ], 
+        bump,
+        close = borrower
+    )]
+    pub nft_vault_account: Box<Account<'info, NftVaultAccount>>,
+    #[account(mut, 
+        constraint = nft_mint.to_account_info().key() == nft_vault_ata.mint, 
+        constraint = nft_vault_account.to_account_info().key() == nft_vault_ata.owner,
+    )]
+    pub nft_vault_ata: Box<Account<'info, TokenAccount>>, 
+    #[account(
+        mut,
+        seeds = [nft_vault_account.key().as_ref(), b"loan".as_ref()],
+        bump,
+        constraint = loan.borrower == borrower.key(),
+        close = borrower
+    )]
+    pub loan: Box<Account<'info, Loan>>,
+    /// CHECK: Receives the NftVaultAccount, Loan and escrow rent, checked against loan.borrower
+    #[account(mut)]
+    pub borrower: AccountInfo<'info>,
+    #[account(mut, 
+        constraint = loan_prove_token_account.key()
+            == get_associated_token_address(&loan.key(), &pool_info.prove_token_mint))]
+    pub loan_prove_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut, constraint = pool_info.prove_token_vault == prove_token_vault.key())]
+    pub prove_token_vault: Box<Account<'info, TokenAccount>>,
+    #[account(mut, 
+        constraint = nft_mint.to_account_info().key() == authority_nft_account.mint, 
+        constraint = authority.key() == authority_nft_account.owner)]
+    pub authority_nft_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        seeds = [pool_info.key().as_ref(), nft_vault_account.user.as_ref(), b"user_points".as_ref()],
+        bump,
+        payer = authority,
+        space = UserPoints::LEN
+    )]
+    pub user_points: Box<Account<'info, UserPoints>>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct BeginSnapshot<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut, constraint = pool_info.has_role(PoolRole::RewardManager, authority.key()) @ ErrorCode::MissingRole)]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(
+        init,
+        seeds = [pool_info.key().as_ref(), &index.to_le_bytes(), b"snapshot".as_ref()],
+        bump,
+        payer = authority,
+        space = Snapshot::LEN
+    )]
+    pub snapshot: Box<Account<'info, Snapshot>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(page_index: u32)]
+pub struct AddSnapshotPage<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut, constraint = snapshot.authority == authority.key() @ ErrorCode::MissingRole)]
+    pub snapshot: Box<Account<'info, Snapshot>>,
+    #[account(
+        init,
+        seeds = [snapshot.key().as_ref(), &page_index.to_le_bytes(), b"snapshot_page".as_ref()],
+        bump,
+        payer = authority,
+        space = SnapshotPage::LEN
+    )]
+    pub snapshot_page: Box<Account<'info, SnapshotPage>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RecordSnapshot<'info> {
+    #[account(mut)]
+    pub snapshot: Box<Account<'info, Snapshot>>,
+    #[account(mut, constraint = snapshot_page.snapshot == snapshot.key() @ ErrorCode::InvalidSnapshotPage)]
+    pub snapshot_page: Box<Account<'info, SnapshotPage>>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeSnapshot<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut, constraint = snapshot.pool_info == pool_info.key())]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(mut, constraint = snapshot.authority == authority.key() @ ErrorCode::MissingRole)]
+    pub snapshot: Box<Account<'info, Snapshot>>,
+}
+
+#[derive(Accounts)]
+pub struct CancelSnapshot<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut, constraint = pool_info.has_role(PoolRole::RewardManager, authority.key()) @ ErrorCode::MissingRole)]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(mut, constraint = snapshot.pool_info == pool_info.key(), close = authority)]
+    pub snapshot: Box<Account<'info, Snapshot>>,
+}
+
+#[derive(Accounts)]
+pub struct CreateGuild<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(
+        init,
+        seeds = [pool_info.key().as_ref(), authority.key().as_ref(), b"guild".as_ref()],
+        bump,
+        payer = authority,
+        space = Guild::LEN
+    )]
+    pub guild: Box<Account<'info, Guild>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateGuildMembers<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(
+        mut,
+        seeds = [pool_info.key().as_ref(), authority.key().as_ref(), b"guild".as_ref()],
+        bump = guild.bump
+    )]
+    pub guild: Box<Account<'info, Guild>>,
+    #[account(
+        init_if_needed,
+        seeds = [pool_info.key().as_ref(), guild.key().as_ref(), b"user_points".as_ref()],
+        bump,
+        payer = authority,
+        space = UserPoints::LEN
+    )]
+    pub guild_points: Box<Account<'info, UserPoints>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositToGuild<'info> {
+    #[account(mut)]
+    pub member: Signer<'info>,
+    #[account(mut)]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(mut, constraint = guild.pool_info == pool_info.key())]
+    pub guild: Box<Account<'info, Guild>>,
+    #[account(
+        mut,
+        constraint = nft_vault_account.pool_info == pool_info.key() @ ErrorCode::NftVaultMismatch,
+        constraint = nft_vault_account.user == member.key() @ ErrorCode::NftVaultMismatch
+    )]
+    pub nft_vault_account: Box<Account<'info, NftVaultAccount>>,
+    #[account(
+        init,
+        seeds = [nft_vault_account.key().as_ref(), b"guild_position".as_ref()],
+        bump,
+        payer = member,
+        space = GuildPosition::LEN
+    )]
+    pub guild_position: Box<Account<'info, GuildPosition>>,
+    #[account(
+        init_if_needed,
+        seeds = [pool_info.key().as_ref(), member.key().as_ref(), b"user_points".as_ref()],
+        bump,
+        payer = member,
+        space = UserPoints::LEN
+    )]
+    pub user_points: Box<Account<'info, UserPoints>>,
+    #[account(
+        init_if_needed,
+        seeds = [pool_info.key().as_ref(), guild.key().as_ref(), b"user_points".as_ref()],
+        bump,
+        payer = member,
+        space = UserPoints::LEN
+    )]
+    pub guild_points: Box<Account<'info, UserPoints>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFromGuild<'info> {
+    /// Member or admin, who clears positions for force unstake
+    #[account(
+        mut,
+        constraint = authority.key() == guild_position.member
+            || authority.key() == pool_info.admin @ ErrorCode::NftVaultMismatch
+    )]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(mut, constraint = guild.pool_info == pool_info.key())]
+    pub guild: Box<Account<'info, Guild>>,
+    #[account(mut, constraint = nft_vault_account.pool_info == pool_info.key() @ ErrorCode::NftVaultMismatch)]
+    pub nft_vault_account: Box<Account<'info, NftVaultAccount>>,
+    #[account(
+        mut,
+        seeds = [nft_vault_account.key().as_ref(), b"guild_position".as_ref()],
+        bump,
+        constraint = guild_position.guild == guild.key(),
+        constraint = guild_position.member == member.key(),
+        close = member
+    )]
+    pub guild_position: Box<Account<'info, GuildPosition>>,
+    /// CHECK: Depositing member receiving the GuildPosition rent, checked against guild_position.member
+    #[account(mut)]
+    pub member: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        seeds = [pool_info.key().as_ref(), member.key().as_ref(), b"user_points".as_ref()],
+        bump,
+        payer = authority,
+        space = UserPoints::LEN
+    )]
+    pub user_points: Box<Account<'info, UserPoints>>,
+    #[account(
+        mut,
+        seeds = [pool_info.key().as_ref(), guild.key().as_ref(), b"user_points".as_ref()],
+        bump
+    )]
+    pub guild_points: Box<Account<'info, UserPoints>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimGuildShare<'info> {
+    #[account(mut)]
+    pub member: Signer<'info>,
+    #[account(mut)]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(mut, constraint = guild.pool_info == pool_info.key())]
+    pub guild: Box<Account<'info, Guild>>,
+    #[account(
+        init_if_needed,
+        seeds = [pool_info.key().as_ref(), member.key().as_ref(), b"user_points".as_ref()],
+        bump,
+        payer = member,
+        space = UserPoints::LEN
+    )]
+    pub user_points: Box<Account<'info, UserPoints>>,
+    #[account(
+        mut,
+        seeds = [pool_info.key().as_ref(), guild.key().as_ref(), b"user_points".as_ref()],
+        bump
+    )]
+    pub guild_points: Box<Account<'info, UserPoints>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureBooster<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(constraint = pool_info.has_role(PoolRole::ConfigManager, authority.key()) @ ErrorCode::MissingRole)]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    /// CHECK: Booster mint or booster RarityInfo, checked against kind
+    pub source: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        seeds = [pool_info.key().as_ref(), source.key().as_ref(), b"booster_config".as_ref()],
+        bump,
+        payer = authority,
+        space = BoosterConfig::LEN
+    )]
+    pub booster_config: Box<Account<'info, BoosterConfig>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AttachBooster<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(mut)]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(
+        mut,
+        constraint = nft_vault_account.pool_info == pool_info.key() @ ErrorCode::NftVaultMismatch,
+        constraint = nft_vault_account.user == user.key() @ ErrorCode::NftVaultMismatch
+    )]
+    pub nft_vault_account: Box<Account<'info, NftVaultAccount>>,
+    #[account(
+        mut,
+        seeds = [pool_info.key().as_ref(), booster_config.source.as_ref(), b"booster_config".as_ref()],
+        bump = booster_config.bump
+    )]
+    pub booster_config: Box<Account<'info, BoosterConfig>>,
+    /// CHECK: RarityInfo listing booster NFTs, checked against booster_config.source
+    #[account(constraint = booster_config.source == booster_source.key())]
+    pub booster_source: AccountInfo<'info>,
+    pub booster_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        seeds = [nft_vault_account.key().as_ref(), b"booster".as_ref()],
+        bump,
+        payer = user,
+        space = Booster::LEN
+    )]
+    pub booster: Box<Account<'info, Booster>>,
+    #[account(
+        init,
+        associated_token::mint = booster_mint,
+        associated_token::authority = booster,
+        payer = user
+    )]
+    pub booster_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = booster_mint.key() == user_booster_account.mint,
+        constraint = user.key() == user_booster_account.owner
+    )]
+    pub user_booster_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        seeds = [pool_info.key().as_ref(), user.key().as_ref(), b"user_points".as_ref()],
+        bump,
+        payer = user,
+        space = UserPoints::LEN
+    )]
+    pub user_points: Box<Account<'info, UserPoints>>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: AccountInfo<'info>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct DetachBooster<'info> {
+    /// Staker or admin, who clears positions for force unstake
+    #[account(
+        mut,
+        constraint = authority.key() == booster.user
+            || authority.key() == pool_info.admin @ ErrorCode::NftVaultMismatch
+    )]
+    pub authority: Signer<'info>,
+    #[account(mut, constraint = booster.pool_info == pool_info.key())]
+    pub pool_info: Box<Account<'info, PoolInfo>>,
+    #[account(mut, constraint = booster.nft_vault_account == nft_vault_account.key())]
+    pub nft_vault_account: Box<Account<'info, NftVaultAccount>>,
+    #[account(mut, constraint = booster.booster_config == booster_config.key())]
+    pub booster_config: Box<Account<'info, BoosterConfig>>,
+    #[account(mut, constraint = booster.user == owner.key(), close = owner)]
+    pub booster: Box<Account<'info, Booster>>,
+    /// CHECK: Staker receiving the booster and the Booster rent, checked against booster.user
+    #[account(mut)]
+    pub owner: AccountInfo<'info>,
+    #[account(constraint = booster.booster_mint == booster_mint.key())]
+    pub booster_mint: Box<Account<'info, Mint>>,
+    #[account(mut, constraint = booster.vault == booster_vault.key())]
+    pub booster_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        associated_token::mint = booster_mint,
+        associated_token::authority = owner,
+        payer = authority
+    )]
+    pub owner_booster_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        seeds = [pool_info.key().as_ref(), booster.user.as_ref(), b"user_points".as_ref()],
+        bump,
+        payer = authority,
+        space = UserPoints::LEN
+    )]
+    pub user_points: Box<Account<'info, UserPoints>>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: AccountInfo<'info>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+// program account
+#[account]
+pub struct PoolInfo {
+    pub admin: Pubkey, 
+    pub prove_token_authority: Pubkey, 
+    pub prove_token_vault: Pubkey,
+    pub prove_token_mint: Pubkey, 
+    pub rarity_info: Pubkey, 
     /// This field is deprecated.
     pub mint_list_length: u64, 
     pub total_locked: u64,
+    /// Mint users must hold to stake, Pubkey::default() if the pool is not gated.
+    pub gate_mint: Pubkey,
+    pub gate_min_balance: u64,
+    pub version: u8,
+    /// Proposed admin waiting to accept, Pubkey::default() if none.
+    pub pending_admin: Pubkey,
+    /// Role holders besides admin, Pubkey::default() if unassigned.
+    pub pauser: Pubkey,
+    pub reward_manager: Pubkey,
+    pub config_manager: Pubkey,
+    /// Paused pool rejects stake, unstake is still allowed.
+    pub paused: bool,
+    /// Points accrued per POINTS_WEIGHT_BASE of weight per second, rarer NFTs weigh more.
+    pub points_per_second: u64,
+    /// Points accrued per POINTS_WEIGHT_BASE since initialize, scaled by POINTS_PRECISION.
+    pub acc_points_per_weight: u128,
+    pub last_points_update: i64,
+    /// Share of a referred staker's accrual credited to the referrer, in basis points.
+    pub referral_share_bps: u64,
+    /// Most points one referrer can earn from referrals, points owed above it wait until it is raised.
+    pub referral_cap: u64,
+    /// Rate follows utilisation between these bounds instead of points_per_second when enabled.
+    pub curve_enabled: bool,
+    pub curve_min_points_per_second: u64,
+    pub curve_max_points_per_second: u64,
+    /// RarityInfo.mint_list.len() as of the last stake or unstake.
+    pub listed_count: u64,
+    /// Prove tokens of positions closed without returning them, e.g. force unstaked.
+    pub orphaned_prove_tokens: u64,
+    /// Accept stake_sft of mints with supply above one.
+    pub sft_enabled: bool,
+    /// Prove tokens funded into prove_token_vault, at initialize and by update_sft.
+    pub prove_token_supply: u64,
+    /// Seconds claimed distribution rewards vest over, 0 pays them out directly.
+    pub vesting_duration: i64,
+    /// Snapshot being recorded, Pubkey::default() if none. One at a time keeps
+    /// NftVaultAccount.last_snapshot enough to record each position once.
+    pub open_snapshot: Pubkey,
+}
+
+impl PoolInfo {
+    /// 1: version and reserved space, 2: pending_admin, 3: roles and paused, 4: points, 5: referral,
+    /// 6: utilisation rate curve, 7: orphaned_prove_tokens, 8: SFT, 9: vesting_duration,
+    /// 10: open_snapshot
+    pub const CURRENT_VERSION: u8 = 10;
+    pub const LEN: usize = 512;    // used: 8 + 32 * 5 + 8 + 8 + 32 + 8 + 1 + 32 + 32 * 3 + 1 + 8 + 16 + 8 + 8 + 8 + 1 + 8 * 3 + 8 + 1 + 8 + 8 + 32
+
+    /// Rate since the last checkpoint, utilisation only changes on stake and unstake which checkpoint first.
+    pub fn effective_points_per_second(&self) -> u64 {
+        if !self.curve_enabled {
+            return self.points_per_second;
+        };
+        let utilisation_bps = if self.listed_count == 0 {
+            0
+        } else {
+            (self.total_locked.min(self.listed_count) as u128 * BPS_BASE as u128
+                / self.listed_count as u128) as u64
+        };
+        // Linear from max rate when empty down to min rate when every listed NFT is staked
+        let range = self.curve_max_points_per_second - self.curve_min_points_per_second;
+        self.curve_max_points_per_second
+            - (range as u128 * utilisation_bps as u128 / BPS_BASE as u128) as u64
+    }
+
+    /// acc_points_per_weight as if checkpointed at now.
+    pub fn current_acc_points_per_weight(&self, now: i64) -> u128 {
+        // Pools migrated from before points start accruing from their first checkpoint
+        if self.last_points_update == 0 || now <= self.last_points_update {
+            return self.acc_points_per_weight;
+        };
+        let elapsed = (now - self.last_points_update) as u128;
+        self.acc_points_per_weight
+            + self.effective_points_per_second() as u128 * elapsed * POINTS_PRECISION
+                / POINTS_WEIGHT_BASE as u128
+    }
+
+    /// Accrue points_per_second up to now into acc_points_per_weight.
+    pub fn checkpoint_points(&mut self, now: i64) {
+        self.acc_points_per_weight = self.current_acc_points_per_weight(now);
+        if now > self.last_points_update {
+            self.last_points_update = now;
+        };
+    }
+
+    /// Admin holds every role, so an unassigned role is never locked.
+    pub fn has_role(&self, role: PoolRole, authority: Pubkey) -> bool {
+        if authority == self.admin {
+            return true;
+        };
+        let holder = match role {
+            PoolRole::Pauser => self.pauser,
+            PoolRole::RewardManager => self.reward_manager,
+            PoolRole::ConfigManager => self.config_manager,
+        };
+        holder != Pubkey::default() && holder == authority
+    }
+}
+
+/// One-off reward drop, claimed against a Merkle root of (leaf_index, user, amount).
+#[account]
+pub struct Distribution {
+    pub pool_info: Pubkey,
+    pub index: u64,
+    pub bump: u8,
+    pub reward_mint: Pubkey,
+    pub vault: Pubkey,
+    pub merkle_root: [u8; 32],
+    pub total_amount: u64,
+    pub claimed_amount: u64,
+    pub num_leaves: u64,
+    /// Bit leaf_index is set once the leaf is claimed.
+    pub claimed_bitmap: Vec<u8>,
+}
+
+impl Distribution {
+    /// Keeps the account within the 10KB limit of accounts created by CPI.
+    pub const MAX_LEAVES: u64 = 65_536;
+
+    /// Bytes holding one bit per leaf.
+    pub fn bitmap_len(num_leaves: u64) -> usize {
+        ((num_leaves + 7) >> 3) as usize
+    }
+
+    /// Discriminator, pool_info, index, bump, reward_mint, vault, merkle_root, total_amount,
+    /// claimed_amount, num_leaves and the claimed_bitmap Vec, sized exactly as nothing is added later.
+    pub fn space(num_leaves: u64) -> usize {
+        8 + 32 + 8 + 1 + 32 + 32 + 32 + 8 + 8 + 8 + 4 + Self::bitmap_len(num_leaves)
+    }
+
+    pub fn is_claimed(&self, leaf_index: u64) -> bool {
+        self.claimed_bitmap[(leaf_index / 8) as usize] & (1 << (leaf_index % 8)) != 0
+    }
+
+    pub fn set_claimed(&mut self, leaf_index: u64) {
+        self.claimed_bitmap[(leaf_index / 8) as usize] |= 1 << (leaf_index % 8);
+    }
+
+    /// Checks leaf (leaf_index, user, amount) is in the tree and unclaimed.
+    pub fn verify_claim(&self, leaf_index: u64, user: &Pubkey, amount: u64, proof: &[[u8; 32]]) -> Result<()> {
+        if leaf_index >= self.num_leaves {
+            return Err(ErrorCode::InvalidMerkleProof.into());
+        };
+        if self.is_claimed(leaf_index) {
+            return Err(ErrorCode::DistributionAlreadyClaimed.into());
+        };
+
+        let leaf = hashv(&[
+            &leaf_index.to_le_bytes(),
+            &user.to_bytes(),
+            &amount.to_le_bytes(),
+        ]).to_bytes();
+        if !verify_merkle_proof(proof, self.merkle_root, leaf) {
+            return Err(ErrorCode::InvalidMerkleProof.into());
+        };
+        Ok(())
+    }
+}
+
+/// Claimed rewards of a user unlocking linearly, one escrow per pool and reward mint.
+#[account]
+pub struct VestingEscrow {
+    pub user: Pubkey,
+    pub pool_info: Pubkey,
+    pub reward_mint: Pubkey,
+    /// Associated token account of reward_mint owned by this VestingEscrow.
+    pub vault: Pubkey,
+    pub bump: u8,
+    /// Amount vesting from start_time to end_time.
+    pub total_amount: u64,
+    pub withdrawn_amount: u64,
+    /// Unlocked but not withdrawn when the schedule was last restarted.
+    pub unlocked_carry: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub version: u8,
+}
+
+impl VestingEscrow {
+    pub const CURRENT_VERSION: u8 = 1;
+    pub const LEN: usize = 256;    // used: 8 + 32 * 4 + 1 + 8 * 5 + 1
+
+    pub fn vested(&self, now: i64) -> u64 {
+        if now >= self.end_time {
+            return self.total_amount;
+        };
+        if now <= self.start_time {
+            return 0;
+        };
+        (self.total_amount as u128 * (now - self.start_time) as u128
+            / (self.end_time - self.start_time) as u128) as u64
+    }
+
+    pub fn withdrawable(&self, now: i64) -> u64 {
+        self.unlocked_carry + self.vested(now) - self.withdrawn_amount
+    }
+
+    /// Restarts the schedule at now with the locked remainder plus amount.
+    pub fn deposit(&mut self, amount: u64, now: i64, duration: i64) {
+        let vested = self.vested(now);
+        self.unlocked_carry += vested - self.withdrawn_amount;
+        self.total_amount = self.total_amount - vested + amount;
+        self.withdrawn_amount = 0;
+        self.start_time = now;
+        self.end_time = now + duration;
+    }
+
+    /// Marks everything unlocked so far as withdrawn.
+    pub fn withdraw(&mut self, now: i64) {
+        self.unlocked_carry = 0;
+        self.withdrawn_amount = self.vested(now);
+    }
+}
+
+/// Raffle among staked NFTs of a pool, drawn from the hash of reveal_slot.
+#[account]
+pub struct Raffle {
+    pub pool_info: Pubkey,
+    pub index: u64,
+    pub weighting: RaffleWeighting,
+    pub num_winners: u8,
+    pub commit_slot: u64,
+    /// Entries close at this slot, its hash seeds the draw.
+    pub reveal_slot: u64,
+    pub total_weight: u64,
+    pub entries: Vec<RaffleEntry>,
+    pub winners: Vec<RaffleEntry>,
+    pub drawn: bool,
+    pub version: u8,
+    /// Positions staked after this time can't enter.
+    pub commit_time: i64,
+}
+
+impl Raffle {
+    pub const MAX_ENTRIES: usize = 120;
+    pub const MAX_WINNERS: usize = 10;
+    /// SlotHashes keeps the last 512 slots, leave time to draw after reveal.
+    pub const MAX_ENTRY_SLOTS: u64 = 256;
+    /// 1: version and reserved space, 2: commit_time
+    pub const CURRENT_VERSION: u8 = 2;
+    pub const LEN: usize = 10_000;    // used: 8 + 32 + 8 + 1 + 1 + 8 * 3 + 4 + 72 * 120 + 4 + 72 * 10 + 1 + 1 + 8, under the 10KB CPI allocation limit
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct RaffleEntry {
+    pub user: Pubkey,
+    pub nft_mint: Pubkey,
+    pub weight: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum RaffleWeighting {
+    /// Seconds staked when entered.
+    StakeTime,
+    /// NftVaultAccount.points_weight, which follows the pool's rarity.
+    PointsWeight,
+}
+
+/// Lending terms of a pool, the pool's rarity is the tier the LTV applies to.
+#[account]
+pub struct LoanConfig {
+    pub pool_info: Pubkey,
+    pub bump: u8,
+    pub loan_mint: Pubkey,
+    /// Associated token account of loan_mint owned by this LoanConfig.
+    pub loan_vault: Pubkey,
+    /// Value of one staked NFT in loan_mint units.
+    pub nft_value: u64,
+    /// Most a staker can borrow against one NFT, in basis points of nft_value.
+    pub ltv_bps: u64,
+    /// Flat interest added to the principal, in basis points.
+    pub interest_bps: u64,
+    /// Seconds until an unpaid loan can be seized.
+    pub duration: i64,
+    pub open_loans: u64,
+    pub version: u8,
+}
+
+impl LoanConfig {
+    pub const CURRENT_VERSION: u8 = 1;
+    pub const LEN: usize = 256;    // used: 8 + 32 + 1 + 32 * 2 + 8 * 5 + 1
+}
+
+/// Loan against a staked NFT, the position's prove token is escrowed in this Loan's associated token account.
+#[account]
+pub struct Loan {
+    pub borrower: Pubkey,
+    pub pool_info: Pubkey,
+    pub nft_vault_account: Pubkey,
+    pub loan_mint: Pubkey,
+    pub principal: u64,
+    /// Principal plus interest, due by expiry.
+    pub repay_amount: u64,
+    pub start_time: i64,
+    pub expiry: i64,
+    pub version: u8,
+}
+
+impl Loan {
+    pub const CURRENT_VERSION: u8 = 1;
+    pub const LEN: usize = 256;    // used: 8 + 32 * 4 + 8 * 4 + 1
+}
+
+/// Staked (user, mint, stake_time, amount) of a pool as of slot, entries are kept in SnapshotPages.
+#[account]
+pub struct Snapshot {
+    pub pool_info: Pubkey,
+    pub index: u64,
+    pub authority: Pubkey,
+    pub slot: u64,
+    pub timestamp: i64,
+    /// PoolInfo.total_locked at slot, recorded_amount has to match to finalize.
+    pub total_locked: u64,
+    pub page_count: u32,
+    pub entry_count: u64,
+    pub finalized: bool,
+    pub version: u8,
+    /// Tokens locked by the recorded positions, an SFT entry counts its amount.
+    pub recorded_amount: u64,
+}
+
+impl Snapshot {
+    /// 1: initial, 2: recorded_amount
+    pub const CURRENT_VERSION: u8 = 2;
+    pub const LEN: usize = 256;    // used: 8 + 32 + 8 + 32 + 8 * 3 + 4 + 8 + 1 + 1 + 8
+
+    pub fn is_complete(&self) -> bool {
+        self.recorded_amount == self.total_locked
+    }
+}
+
+#[account]
+pub struct SnapshotPage {
+    pub snapshot: Pubkey,
+    pub page_index: u32,
+    pub entries: Vec<SnapshotEntry>,
+    pub version: u8,
+}
+
+impl SnapshotPage {
+    pub const CAPACITY: usize = 116;
+    /// 1: initial, 2: SnapshotEntry.amount
+    pub const CURRENT_VERSION: u8 = 2;
+    pub const LEN: usize = 9_400;    // used: 8 + 32 + 4 + 4 + 80 * 116 + 1, under the 10KB CPI allocation limit
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SnapshotEntry {
+    pub user: Pubkey,
+    /// Mint, or asset id of a compressed NFT.
+    pub nft_mint: Pubkey,
+    pub stake_time: i64,
+    /// Staked SFT amount, 1 for NFTs.
+    pub amount: u64,
+}
+
+/// Top stakers of a pool, sorted by score descending.
+#[account]
+pub struct Leaderboard {
+    pub pool_info: Pubkey,
+    pub metric: LeaderboardMetric,
+    pub entries: Vec<LeaderboardEntry>,
+    pub last_update: i64,
+    pub version: u8,
+}
+
+impl Leaderboard {
+    pub const CAPACITY: usize = 20;
+    pub const CURRENT_VERSION: u8 = 1;
+    pub const LEN: usize = 1024;    // used: 8 + 32 + 1 + 4 + (32 + 8) * 20 + 8 + 1
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct LeaderboardEntry {
+    pub user: Pubkey,
+    pub score: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum LeaderboardMetric {
+    /// NFTs currently staked in the pool.
+    StakedCount,
+    /// Points accrued in the pool, spent points included.
+    Points,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum PoolRole {
+    /// Pauses and resumes stake, force unstakes positions.
+    Pauser,
+    /// Funds and spends pool rewards and the loan vault, seizes overdue loans.
+    RewardManager,
+    /// Updates pool configuration such as the gate, SFT support and boosters.
+    ConfigManager,
+}
+
+#[account]
+pub struct NftVaultAccount {
+    pub user: Pubkey,
+    pub pool_info: Pubkey,
+    pub nft_mint: Pubkey,
+    /// SetBonus this NFT is counted in, Pubkey::default() if none.
+    pub set_bonus: Pubkey,
+    pub version: u8,
+    /// Weight added to the staker's UserPoints, 0 if staked before points.
+    pub points_weight: u64,
+    pub stake_time: i64,
+    /// Raffle this NFT was last entered in.
+    pub last_raffle: Pubkey,
+    /// Open Loan against this NFT, Pubkey::default() if none. Unstake is blocked while set.
+    pub loan: Pubkey,
+    /// Snapshot this NFT was last recorded in.
+    pub last_snapshot: Pubkey,
+    /// Booster PDA of this NFT is attached. Unstake is blocked while set.
+    pub boosted: bool,
+    /// GuildPosition PDA of this NFT exists, points_weight counts on the guild's UserPoints.
+    /// Unstake is blocked while set.
+    pub guilded: bool,
+}
+
+impl NftVaultAccount {
+    /// 1: version and reserved space, 2: points_weight, 3: stake_time and last_raffle, 4: loan,
+    /// 5: last_snapshot, 6: boosted, 7: guilded
+    pub const CURRENT_VERSION: u8 = 7;
+    pub const LEN: usize = 512;    // used: 8 + 32 * 4 + 1 + 8 + 8 + 32 + 32 + 32 + 1 + 1
+}
+
+/// Members pooling staked NFTs, points of the guild's UserPoints are split by shares.
+#[account]
+pub struct Guild {
+    pub pool_info: Pubkey,
+    /// Creator, the only one updating members.
+    pub authority: Pubkey,
+    pub bump: u8,
+    pub members: Vec<GuildMember>,
+    pub total_shares: u64,
+    pub position_count: u64,
+    /// Points moved from the guild's UserPoints to members so far.
+    pub distributed_points: u64,
+    pub version: u8,
+}
+
+impl Guild {
+    pub const MAX_MEMBERS: usize = 32;
+    pub const CURRENT_VERSION: u8 = 1;
+    pub const LEN: usize = 2_000;    // used: 8 + 32 * 2 + 1 + 4 + 48 * 32 + 8 * 3 + 1
+
+    /// Splits points settled on the guild's UserPoints by shares, rounding dust stays for the next split.
+    pub fn distribute(&mut self, guild_points: &mut UserPoints) {
+        if self.total_shares == 0 {
+            return;
+        };
+        let points = guild_points.points;
+        let mut distributed = 0u64;
+        for member in self.members.iter_mut() {
+            let part = (points as u128 * member.shares as u128 / self.total_shares as u128) as u64;
+            member.owed += part;
+            distributed += part;
+        }
+        guild_points.points -= distributed;
+        self.distributed_points += distributed;
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct GuildMember {
+    pub member: Pubkey,
+    pub shares: u64,
+    /// Points distributed to member but not claimed yet.
+    pub owed: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct GuildShare {
+    pub member: Pubkey,
+    pub shares: u64,
+}
+
+/// NFT of member deposited into guild, only member or the pool admin withdraws it.
+#[account]
+pub struct GuildPosition {
+    pub guild: Pubkey,
+    pub member: Pubkey,
+    pub nft_vault_account: Pubkey,
+    pub deposit_time: i64,
+    pub version: u8,
+}
+
+impl GuildPosition {
+    pub const CURRENT_VERSION: u8 = 1;
+    pub const LEN: usize = 128;    // used: 8 + 32 * 3 + 8 + 1
+}
+
+/// Booster admitted by a pool, source is the booster mint or the RarityInfo listing booster NFTs.
+#[account]
+pub struct BoosterConfig {
+    pub pool_info: Pubkey,
+    pub source: Pubkey,
+    pub kind: BoosterKind,
+    /// Tokens escrowed per attached booster, 1 for NFTs.
+    pub amount: u64,
+    /// Points weight multiplier of a boosted NFT in basis points, BPS_BASE is 1x.
+    pub multiplier_bps: u64,
+    /// Inactive boosters can't be attached, attached ones keep their weight until detached.
+    pub active: bool,
+    pub attached_count: u64,
+    pub bump: u8,
+    pub version: u8,
+}
+
+impl BoosterConfig {
+    pub const CURRENT_VERSION: u8 = 1;
+    pub const LEN: usize = 256;    // used: 8 + 32 * 2 + 1 + 8 * 2 + 1 + 8 + 1 + 1
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum BoosterKind {
+    /// Fungible SPL token, source is its mint.
+    Token,
+    /// Any NFT listed in the RarityInfo at source.
+    Nft,
+}
+
+/// Booster escrowed for a staked NFT, one per NftVaultAccount.
+#[account]
+pub struct Booster {
+    pub user: Pubkey,
+    pub pool_info: Pubkey,
+    pub nft_vault_account: Pubkey,
+    pub booster_config: Pubkey,
+    pub booster_mint: Pubkey,
+    /// Associated token account of booster_mint owned by this Booster.
+    pub vault: Pubkey,
+    pub amount: u64,
+    /// Points weight added to the NftVaultAccount, removed again on detach.
+    pub weight: u64,
+    pub attach_time: i64,
+    pub bump: u8,
+    pub version: u8,
+}
+
+impl Booster {
+    pub const CURRENT_VERSION: u8 = 1;
+    pub const LEN: usize = 256;    // used: 8 + 32 * 6 + 8 * 3 + 1 + 1
+}
+
+/// Non-transferable points of user in a pool, only spent by the pool's reward manager.
+#[account]
+pub struct UserPoints {
+    pub user: Pubkey,
+    pub pool_info: Pubkey,
+    /// Staked positions, an SFT position counts once whatever its amount.
+    pub staked_count: u64,
+    /// Sum of points_weight of staked NftVaultAccounts.
+    pub weight: u64,
+    /// Spendable points settled up to the last checkpoint.
+    pub points: u64,
+    pub total_spent: u64,
+    /// PoolInfo.acc_points_per_weight at the last checkpoint.
+    pub acc_points_per_weight_paid: u128,
+    pub version: u8,
+    /// Wallet that referred user, Pubkey::default() if none.
+    pub referrer: Pubkey,
+    /// Referral points accrued for referrer but not credited yet.
+    pub referral_owed: u64,
+    /// Points credited to this user as a referrer, bounded by PoolInfo.referral_cap.
+    pub referral_earned: u64,
+}
+
+impl UserPoints {
+    /// 1: version and reserved space, 2: referral
+    pub const CURRENT_VERSION: u8 = 2;
+    pub const LEN: usize = 256;    // used: 8 + 32 * 2 + 8 * 4 + 16 + 1 + 32 + 8 + 8
+
+    /// Points accrued by weight since the last checkpoint.
+    pub fn pending(&self, acc_points_per_weight: u128) -> u64 {
+        (self.weight as u128 * (acc_points_per_weight - self.acc_points_per_weight_paid)
+            / POINTS_PRECISION) as u64
+    }
+
+    /// Settle points accrued by weight since the pool's last checkpoint.
+    pub fn checkpoint(&mut self, pool_info: &PoolInfo) {
+        let pending = self.pending(pool_info.acc_points_per_weight);
+        self.points += pending;
+        if self.referrer != Pubkey::default() {
+            self.referral_owed += (pending as u128 * pool_info.referral_share_bps as u128
+                / BPS_BASE as u128) as u64;
+        };
+        self.acc_points_per_weight_paid = pool_info.acc_points_per_weight;
+    }
+
+    /// Credit points owed by referred up to referral_cap, the excess stays owed for when the cap is raised.
+    pub fn credit_referral(&mut self, referred: &mut UserPoints, referral_cap: u64) -> u64 {
+        let remaining_cap = referral_cap.saturating_sub(self.referral_earned);
+        let amount = referred.referral_owed.min(remaining_cap);
+        referred.referral_owed -= amount;
+        self.points += amount;
+        self.referral_earned += amount;
+        amount
+    }
+}
+
+/// Position of one user's staked SFTs of a mint, partially unstakable.
+#[account]
+pub struct SftVaultAccount {
+    pub user: Pubkey,
+    pub pool_info: Pubkey,
+    pub nft_mint: Pubkey,
+    pub amount: u64,
+    pub stake_time: i64,
+    pub points_weight: u64,
+    pub version: u8,
+    /// Snapshot this position was last recorded in.
+    pub last_snapshot: Pubkey,
+}
+
+impl SftVaultAccount {
+    /// 1: initial, 2: last_snapshot
+    pub const CURRENT_VERSION: u8 = 2;
+    pub const LEN: usize = 256;    // used: 8 + 32 * 3 + 8 * 3 + 1 + 32
+}
+
+/// Position of a staked compressed NFT, owner of its leaf while staked.
+#[account]
+pub struct CnftVaultAccount {
+    pub user: Pubkey,
+    pub pool_info: Pubkey,
+    pub asset_id: Pubkey,
+    pub merkle_tree: Pubkey,
+    pub nonce: u64,
+    pub stake_time: i64,
+    pub points_weight: u64,
+    pub version: u8,
+    /// Snapshot this position was last recorded in.
+    pub last_snapshot: Pubkey,
+}
+
+impl CnftVaultAccount {
+    /// 1: initial, 2: last_snapshot
+    pub const CURRENT_VERSION: u8 = 2;
+    pub const LEN: usize = 256;    // used: 8 + 32 * 4 + 8 * 3 + 1 + 32
+}
+
+/// Leaf of a compressed NFT as Bubblegum's transfer takes it.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct CompressedLeaf {
+    pub root: [u8; 32],
+    pub data_hash: [u8; 32],
+    pub creator_hash: [u8; 32],
+    pub nonce: u64,
+    pub index: u32,
+}
+
+/// A full set is one staked NFT from each listed RarityInfo.
+#[account]
+pub struct SetInfo {
+    /// Pool whose config manager defined the set, its RarityInfo is one of rarity_infos.
+    pub pool_info: Pubkey,
+    pub rarity_infos: Vec<Pubkey>,
+    /// Bonus in basis points on top of the base multiplier.
+    pub bonus_bps: u64,
+}
+
+impl SetInfo {
+    pub const LEN: usize = 308;    // used: 8 + 32 + 4 + 32 * 8 + 8
+}
+
+/// A completed set registered by user, closed as soon as one of its NFTs is unstaked.
+#[account]
+pub struct SetBonus {
+    pub user: Pubkey,
+    pub set_info: Pubkey,
+    pub nft_vaults: Vec<Pubkey>,
+    pub bonus_bps: u64,
+    /// Weight added to each NftVaultAccount in nft_vaults order, taken back out on dissolve.
+    pub bonus_weights: Vec<u64>,
+}
+
+impl SetBonus {
+    pub const LEN: usize = 412;    // used: 8 + 32 + 32 + 4 + 32 * 8 + 8 + 4 + 8 * 8
+}
+
+/// Returned by audit_pool through return data.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PoolAudit {
+    pub total_locked: u64,
+    pub prove_token_vault_amount: u64,
+    pub mint_list_length: u64,
+    pub checked_nft_vaults: u64,
+    /// Checked NftVaultAccounts still on an older version, migrated on their next unstake.
+    pub legacy_nft_vaults: u64,
+}
+
+/// Return data of get_position.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PositionView {
+    pub pool_info: Pubkey,
+    pub nft_mint: Pubkey,
+    pub staked: bool,
+    /// Fields below are zero when not staked.
+    pub user: Pubkey,
+    pub stake_time: i64,
+    pub points_weight: u64,
+    pub set_bonus: Pubkey,
+    pub loan: Pubkey,
+    pub boosted: bool,
+    /// Booster attached to the NFT, zero when not boosted.
+    pub booster_mint: Pubkey,
+    pub booster_amount: u64,
+    pub booster_weight: u64,
+    pub guilded: bool,
+    /// Guild the NFT is deposited to, Pubkey::default() when not guilded.
+    pub guild: Pubkey,
+}
+
+/// Return data of get_pool_stats.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PoolStats {
+    pub pool_info: Pubkey,
+    pub admin: Pubkey,
+    pub rarity_info: Pubkey,
+    pub paused: bool,
+    pub total_locked: u64,
+    pub listed_count: u64,
+    /// Rate after the utilisation curve.
+    pub points_per_second: u64,
+    /// Accumulator as of the current clock.
+    pub acc_points_per_weight: u128,
+    pub orphaned_prove_tokens: u64,
+}
+
+// events
+#[event]
+pub struct PoolInitialized {
+    pub pool_info: Pubkey,
+    pub admin: Pubkey,
+    pub rarity_info: Pubkey,
+    pub prove_token_mint: Pubkey,
+    pub prove_token_amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminUpdated {
+    pub pool_info: Pubkey,
+    pub old_admin: Pubkey,
+    pub new_admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminProposed {
+    pub pool_info: Pubkey,
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminProposalCancelled {
+    pub pool_info: Pubkey,
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RoleUpdated {
+    pub pool_info: Pubkey,
+    pub role: PoolRole,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PausedUpdated {
+    pub pool_info: Pubkey,
+    pub authority: Pubkey,
+    pub paused: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct GateUpdated {
+    pub pool_info: Pubkey,
+    pub gate_mint: Pubkey,
+    pub gate_min_balance: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolMigrated {
+    pub pool_info: Pubkey,
+    pub version: u8,
+    pub migrated_nft_vaults: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PointsRateUpdated {
+    pub pool_info: Pubkey,
+    pub points_per_second: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RateCurveUpdated {
+    pub pool_info: Pubkey,
+    pub curve_enabled: bool,
+    pub curve_min_points_per_second: u64,
+    pub curve_max_points_per_second: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PointsSpent {
+    pub pool_info: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub points: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LeaderboardRefreshed {
+    pub pool_info: Pubkey,
+    pub leaderboard: Pubkey,
+    pub refreshed: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferralUpdated {
+    pub pool_info: Pubkey,
+    pub referral_share_bps: u64,
+    pub referral_cap: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferrerRecorded {
+    pub pool_info: Pubkey,
+    pub user: Pubkey,
+    pub referrer: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferralClaimed {
+    pub pool_info: Pubkey,
+    pub user: Pubkey,
+    pub referrer: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DistributionPublished {
+    pub pool_info: Pubkey,
+    pub distribution: Pubkey,
+    pub reward_mint: Pubkey,
+    pub merkle_root: [u8; 32],
+    pub total_amount: u64,
+    pub num_leaves: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DistributionClaimed {
+    pub pool_info: Pubkey,
+    pub distribution: Pubkey,
+    pub user: Pubkey,
+    pub leaf_index: u64,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct GuildUpdated {
+    pub pool_info: Pubkey,
+    pub guild: Pubkey,
+    pub authority: Pubkey,
+    pub member_count: u64,
+    pub total_shares: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct GuildDeposited {
+    pub pool_info: Pubkey,
+    pub guild: Pubkey,
+    pub member: Pubkey,
+    pub nft_mint: Pubkey,
+    pub points_weight: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct GuildWithdrawn {
+    pub pool_info: Pubkey,
+    pub guild: Pubkey,
+    pub authority: Pubkey,
+    pub member: Pubkey,
+    pub nft_mint: Pubkey,
+    pub points_weight: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct GuildShareClaimed {
+    pub pool_info: Pubkey,
+    pub guild: Pubkey,
+    pub member: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BoosterConfigured {
+    pub pool_info: Pubkey,
+    pub booster_config: Pubkey,
+    pub source: Pubkey,
+    pub kind: BoosterKind,
+    pub amount: u64,
+    pub multiplier_bps: u64,
+    pub active: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BoosterAttached {
+    pub pool_info: Pubkey,
+    pub booster: Pubkey,
+    pub user: Pubkey,
+    pub nft_mint: Pubkey,
+    pub booster_mint: Pubkey,
+    pub amount: u64,
+    pub points_weight: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BoosterDetached {
+    pub pool_info: Pubkey,
+    pub booster: Pubkey,
+    pub authority: Pubkey,
+    pub user: Pubkey,
+    pub nft_mint: Pubkey,
+    pub booster_mint: Pubkey,
+    pub amount: u64,
+    pub points_weight: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VestingUpdated {
+    pub pool_info: Pubkey,
+    pub vesting_duration: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardVested {
+    pub pool_info: Pubkey,
+    pub vesting_escrow: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub total_amount: u64,
+    pub end_time: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VestedWithdrawn {
+    pub pool_info: Pubkey,
+    pub vesting_escrow: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RaffleCommitted {
+    pub pool_info: Pubkey,
+    pub raffle: Pubkey,
+    pub weighting: RaffleWeighting,
+    pub num_winners: u8,
+    pub reveal_slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RaffleDrawn {
+    pub pool_info: Pubkey,
+    pub raffle: Pubkey,
+    pub slot_hash: [u8; 32],
+    pub winners: Vec<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RaffleCancelled {
+    pub pool_info: Pubkey,
+    pub raffle: Pubkey,
+    pub reveal_slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LoanTermsUpdated {
+    pub pool_info: Pubkey,
+    pub loan_mint: Pubkey,
+    pub nft_value: u64,
+    pub ltv_bps: u64,
+    pub interest_bps: u64,
+    pub duration: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LoanOpened {
+    pub pool_info: Pubkey,
+    pub loan: Pubkey,
+    pub borrower: Pubkey,
+    pub nft_mint: Pubkey,
+    pub principal: u64,
+    pub repay_amount: u64,
+    pub expiry: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LoanRepaid {
+    pub pool_info: Pubkey,
+    pub loan: Pubkey,
+    pub borrower: Pubkey,
+    pub payer: Pubkey,
+    pub repay_amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LoanSeized {
+    pub pool_info: Pubkey,
+    pub loan: Pubkey,
+    pub borrower: Pubkey,
+    pub nft_mint: Pubkey,
+    pub repay_amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SftUpdated {
+    pub pool_info: Pubkey,
+    pub sft_enabled: bool,
+    pub fund_amount: u64,
+    pub prove_token_supply: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SnapshotBegun {
+    pub pool_info: Pubkey,
+    pub snapshot: Pubkey,
+    pub slot: u64,
+    pub total_locked: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SnapshotCancelled {
+    pub pool_info: Pubkey,
+    pub snapshot: Pubkey,
+    pub entry_count: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SnapshotFinalized {
+    pub pool_info: Pubkey,
+    pub snapshot: Pubkey,
+    pub slot: u64,
+    pub total_locked: u64,
+    pub entry_count: u64,
+    pub recorded_amount: u64,
+    pub page_count: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct Staked {
+    pub pool_info: Pubkey,
+    pub user: Pubkey,
+    pub nft_mint: Pubkey,
+    pub amount: u64,
+    pub total_locked: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct Unstaked {
+    pub pool_info: Pubkey,
+    pub user: Pubkey,
+    pub nft_mint: Pubkey,
+    pub amount: u64,
+    pub total_locked: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PositionMigrated {
+    pub old_pool_info: Pubkey,
+    pub new_pool_info: Pubkey,
+    pub user: Pubkey,
+    pub nft_mint: Pubkey,
+    pub stake_time: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ForceUnstaked {
+    pub pool_info: Pubkey,
+    pub admin: Pubkey,
+    pub user: Pubkey,
+    /// Mint, or asset id of a compressed NFT.
+    pub nft_mint: Pubkey,
+    pub amount: u64,
+    pub total_locked: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UnstakeRedirected {
+    pub pool_info: Pubkey,
+    pub user: Pubkey,
+    pub recipient: Pubkey,
+    pub nft_mint: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SetInitialized {
+    pub set_info: Pubkey,
+    pub pool_info: Pubkey,
+    pub authority: Pubkey,
+    pub rarity_infos: Vec<Pubkey>,
+    pub bonus_bps: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SetRegistered {
+    pub set_info: Pubkey,
+    pub set_bonus: Pubkey,
+    pub user: Pubkey,
+    pub bonus_bps: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SetDissolved {
+    pub set_info: Pubkey,
+    pub set_bonus: Pubkey,
+    pub user: Pubkey,
+    pub timestamp: i64,
+}
+
+#[error_code]
+pub enum ErrorCode {
+    #[msg("ProveTokenAuthority verification failed. Mismatch in findProgramAddress.")]
+    InvalidProveTokenAuthority,
+    #[msg("ProveTokenATA verification failed. Mismatch in findAssociatedTokenAddress.")]
+    InvalidProveTokenATA,
+    #[msg("RarityInfo verification failed. Mismatch in createWithSeed.")]
+    InvalidRarityInfoAccount,
+    #[msg("Mint not found in allowed mint list.")]
+    MintNotFound,
+    #[msg("Gate token account is missing or does not belong to user and gate mint.")]
+    InvalidGateTokenAccount,
+    #[msg("User holds less gate token than the pool requires for staking.")]
+    InsufficientGateTokenBalance,
+    #[msg("A set must list between 2 and 8 RarityInfo.")]
+    InvalidSetSize,
+    #[msg("RarityInfo is listed more than once in the set.")]
+    DuplicateSetRarityInfo,
+    #[msg("Staked NFTs do not complete the set.")]
+    IncompleteSet,
+    #[msg("NFT is already counted in a set bonus.")]
+    NftAlreadyInSet,
+    #[msg("SetBonus or its NftVaultAccounts are missing from remaining accounts.")]
+    InvalidSetBonusAccount,
+    #[msg("Account is not a PoolInfo of admin or one of its NftVaultAccounts.")]
+    InvalidMigrationAccount,
+    #[msg("PoolInfo total_locked is greater than the RarityInfo mint list.")]
+    TotalLockedExceedsMintList,
+    #[msg("Prove token vault balance and total_locked do not add up to the funded amount.")]
+    ProveTokenVaultMismatch,
+    #[msg("NftVaultAccount or nft_vault_ata does not hold a staked NFT of this pool.")]
+    NftVaultMismatch,
+    #[msg("PoolInfo has no pending admin.")]
+    NoPendingAdmin,
+    #[msg("Signer is not the pending admin of PoolInfo.")]
+    InvalidPendingAdmin,
+    #[msg("Signer does not hold the pool role required by this instruction.")]
+    MissingRole,
+    #[msg("Pool is paused.")]
+    PoolPaused,
+    #[msg("User does not have enough points.")]
+    InsufficientPoints,
+    #[msg("UserPoints does not belong to this pool.")]
+    InvalidUserPointsAccount,
+    #[msg("Referral share cannot exceed 10000 basis points.")]
+    InvalidReferralShare,
+    #[msg("User was not referred.")]
+    NoReferrer,
+    #[msg("Distribution must have between 1 and 65536 leaves.")]
+    InvalidDistributionSize,
+    #[msg("Merkle proof does not match the distribution root.")]
+    InvalidMerkleProof,
+    #[msg("Distribution leaf is already claimed.")]
+    DistributionAlreadyClaimed,
+    #[msg("Rate curve minimum is greater than its maximum.")]
+    InvalidRateCurve,
+    #[msg("Raffle needs 1 to 10 winners and 1 to 256 entry slots.")]
+    InvalidRaffleConfig,
+    #[msg("Raffle entries are closed.")]
+    RaffleEntryClosed,
+    #[msg("NftVaultAccount is not of the raffle's pool or is already entered.")]
+    InvalidRaffleEntry,
+    #[msg("Raffle has no room for more entries.")]
+    RaffleFull,
+    #[msg("Raffle reveal slot has not passed yet.")]
+    RaffleNotRevealed,
+    #[msg("Raffle is already drawn.")]
+    RaffleAlreadyDrawn,
+    #[msg("Reveal slot hash is no longer in SlotHashes.")]
+    SlotHashNotFound,
+    #[msg("Loan LTV must be at most 10000 basis points and duration positive.")]
+    InvalidLoanTerms,
+    #[msg("Borrow amount is zero or above the pool's loan to value.")]
+    LoanExceedsLtv,
+    #[msg("NFT has an open loan.")]
+    LoanOutstanding,
+    #[msg("Loan has not expired yet.")]
+    LoanNotExpired,
+    #[msg("Return data is missing or was not set by nft_staking.")]
+    InvalidReturnData,
+    #[msg("Compressed leaf does not belong to this CnftVaultAccount.")]
+    CnftVaultMismatch,
+    #[msg("Pool does not accept SFT stakes.")]
+    SftNotEnabled,
+    #[msg("SFT amount is zero or above the staked amount.")]
+    InvalidSftAmount,
+    #[msg("Snapshot is finalized.")]
+    SnapshotFinalized,
+    #[msg("Snapshot page is out of order or of another snapshot.")]
+    InvalidSnapshotPage,
+    #[msg("NftVaultAccount is not of the snapshot's pool, already recorded or staked after the snapshot.")]
+    InvalidSnapshotEntry,
+    #[msg("Snapshot page has no room for more entries.")]
+    SnapshotPageFull,
+    #[msg("Vesting duration must not be negative.")]
+    InvalidVestingDuration,
+    #[msg("Pool vests claimed rewards, claim through claim_distribution_vested.")]
+    VestingRequired,
+    #[msg("Nothing has vested since the last withdrawal.")]
+    NothingVested,
+    #[msg("Booster is not configured correctly or not active.")]
+    InvalidBoosterConfig,
+    #[msg("Booster mint is not admitted by the booster config.")]
+    BoosterMintMismatch,
+    #[msg("Detach the booster first.")]
+    BoosterAttached,
+    #[msg("Guild needs 1 to 32 distinct members with nonzero shares.")]
+    InvalidGuildMembers,
+    #[msg("Wallet is not a member of the guild.")]
+    NotGuildMember,
+    #[msg("Withdraw the position from its guild first.")]
+    PositionInGuild,
+    #[msg("Removed guild member has unclaimed points.")]
+    GuildMemberOwed,
+    #[msg("NftVaultAccount was staked after the raffle was committed.")]
+    StakedAfterRaffleCommit,
+    #[msg("Reveal slot hash is still in SlotHashes, draw the raffle instead.")]
+    RaffleStillDrawable,
+    #[msg("Mint has decimals or a supply of one, it is not an SFT.")]
+    NotSftMint,
+    #[msg("Pool already has an open snapshot, finalize or cancel it first.")]
+    SnapshotOpen,
+    #[msg("Snapshot entry_count does not match total_locked.")]
+    SnapshotIncomplete,
+    #[msg("Set bonus is above MAX_SET_BONUS_BPS.")]
+    SetBonusTooHigh,
+    #[msg("Pool defining the set is not one of its RarityInfos.")]
+    SetPoolNotListed,
+    #[msg("Signer is not the user's referrer.")]
+    NotReferrer,
+    #[msg("Arithmetic overflow.")]
+    MathOverflow,
+    #[msg("Mint has decimals or a supply other than one, it is not an NFT.")]
+    NotNftMint,
+}
+
+// utils
+impl<'info> Initialize<'info> {
+    fn transfer_prove_token_to_vault(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .admin_prove_token_account
+                .to_account_info()
+                .clone(),
+            to: self
+            .prove_token_vault
+            .to_account_info()
+            .clone(),
+            authority: self.admin.clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+}
+
+impl<'info> Stake<'info> {
+    fn tansfer_nft_to_vault(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .user_nft_account
+                .to_account_info()
+                .clone(),
+            to: self.nft_vault_ata.to_account_info().clone(),
+            authority: self.user.clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+
+    fn transfer_prove_token_to_user(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .prove_token_vault
+                .to_account_info()
+                .clone(),
+            to: self
+            .user_prove_token_account
+            .to_account_info()
+            .clone(),
+            authority: self.prove_token_authority.clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+}
+
+impl<'info> PublishDistribution<'info> {
+    fn transfer_reward_to_vault(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .authority_reward_account
+                .to_account_info()
+                .clone(),
+            to: self.distribution_vault.to_account_info().clone(),
+            authority: self.authority.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+}
+
+impl<'info> ClaimDistribution<'info> {
+    fn transfer_reward_to_user(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .distribution_vault
+                .to_account_info()
+                .clone(),
+            to: self.user_reward_account.to_account_info().clone(),
+            authority: self.distribution.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
 }
 
-#[account]
-pub struct NftVaultAccount {
-    pub user: Pubkey,
-    pub pool_info: Pubkey,
-    pub nft_mint: Pubkey
+impl<'info> FundLoanVault<'info> {
+    fn transfer_to_loan_vault(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .authority_loan_account
+                .to_account_info()
+                .clone(),
+            to: self.loan_vault.to_account_info().clone(),
+            authority: self.authority.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
 }
 
-#[error_code]
-pub enum ErrorCode {
-    #[msg("ProveTokenAuthority verification failed. Mismatch in findProgramAddress.")]
-    InvalidProveTokenAuthority,
-    #[msg("ProveTokenATA verification failed. Mismatch in findAssociatedTokenAddress.")]
-    InvalidProveTokenATA,
-    #[msg("RarityInfo verification failed. Mismatch in createWithSeed.")]
-    InvalidRarityInfoAccount,
-    #[msg("Mint not found in allowed mint list.")]
-    MintNotFound,
+impl<'info> WithdrawLoanVault<'info> {
+    fn transfer_from_loan_vault(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .loan_vault
+                .to_account_info()
+                .clone(),
+            to: self.authority_loan_account.to_account_info().clone(),
+            authority: self.loan_config.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+}
+
+impl<'info> Borrow<'info> {
+    fn transfer_loan_to_user(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .loan_vault
+                .to_account_info()
+                .clone(),
+            to: self.user_loan_account.to_account_info().clone(),
+            authority: self.loan_config.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+
+    fn transfer_prove_token_to_escrow(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .user_prove_token_account
+                .to_account_info()
+                .clone(),
+            to: self.loan_prove_token_account.to_account_info().clone(),
+            authority: self.user.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+}
+
+impl<'info> RepayLoan<'info> {
+    fn transfer_repayment_to_vault(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .payer_loan_account
+                .to_account_info()
+                .clone(),
+            to: self.loan_vault.to_account_info().clone(),
+            authority: self.payer.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+
+    fn transfer_prove_token_to_borrower(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .loan_prove_token_account
+                .to_account_info()
+                .clone(),
+            to: self.borrower_prove_token_account.to_account_info().clone(),
+            authority: self.loan.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+
+    fn close_loan_prove_token_account(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        let cpi_accounts = CloseAccount {
+            account: self.loan_prove_token_account.to_account_info().clone(),
+            destination: self.borrower.clone(),
+            authority: self.loan.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+}
+
+impl<'info> SeizeLoan<'info> {
+    fn transfer_nft_to_authority(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .nft_vault_ata
+                .to_account_info()
+                .clone(),
+            to: self.authority_nft_account.to_account_info().clone(),
+            authority: self.nft_vault_account.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+
+    fn close_nft_vault_ata(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        let cpi_accounts = CloseAccount {
+            account: self.nft_vault_ata.to_account_info().clone(),
+            destination: self.borrower.clone(),
+            authority: self.nft_vault_account.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+
+    fn transfer_prove_token_to_vault(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .loan_prove_token_account
+                .to_account_info()
+                .clone(),
+            to: self.prove_token_vault.to_account_info().clone(),
+            authority: self.loan.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+
+    fn close_loan_prove_token_account(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        let cpi_accounts = CloseAccount {
+            account: self.loan_prove_token_account.to_account_info().clone(),
+            destination: self.borrower.clone(),
+            authority: self.loan.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+}
+
+impl<'info> ForceUnstake<'info> {
+    fn transfer_nft_to_owner(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .nft_vault_ata
+                .to_account_info()
+                .clone(),
+            to: self.owner_nft_account.to_account_info().clone(),
+            authority: self.nft_vault_account.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+
+    fn close_nft_vault_ata(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        let cpi_accounts = CloseAccount {
+            account: self.nft_vault_ata.to_account_info().clone(),
+            destination: self.owner.clone(),
+            authority: self.nft_vault_account.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+}
+
+impl<'info> ForceUnstakeSft<'info> {
+    fn transfer_sft_to_owner(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .sft_vault_ata
+                .to_account_info()
+                .clone(),
+            to: self.owner_nft_account.to_account_info().clone(),
+            authority: self.sft_vault_account.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+
+    fn close_sft_vault_ata(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        let cpi_accounts = CloseAccount {
+            account: self.sft_vault_ata.to_account_info().clone(),
+            destination: self.owner.clone(),
+            authority: self.sft_vault_account.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+}
+
+impl<'info> MigratePosition<'info> {
+    fn transfer_nft_to_new_vault(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .old_nft_vault_ata
+                .to_account_info()
+                .clone(),
+            to: self.new_nft_vault_ata.to_account_info().clone(),
+            authority: self.old_nft_vault_account.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+
+    fn close_old_nft_vault_ata(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        let cpi_accounts = CloseAccount {
+            account: self.old_nft_vault_ata.to_account_info().clone(),
+            destination: self.user.to_account_info().clone(),
+            authority: self.old_nft_vault_account.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+
+    fn transfer_old_prove_token_to_vault(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .user_old_prove_token_account
+                .to_account_info()
+                .clone(),
+            to: self.old_prove_token_vault.to_account_info().clone(),
+            authority: self.user.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+
+    fn transfer_new_prove_token_to_user(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .new_prove_token_vault
+                .to_account_info()
+                .clone(),
+            to: self.user_new_prove_token_account.to_account_info().clone(),
+            authority: self.new_prove_token_authority.clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+}
+
+impl<'info> UpdateSft<'info> {
+    fn transfer_prove_token_to_vault(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .authority_prove_token_account
+                .to_account_info()
+                .clone(),
+            to: self.prove_token_vault.to_account_info().clone(),
+            authority: self.authority.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+}
+
+impl<'info> StakeSft<'info> {
+    fn transfer_sft_to_vault(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .user_nft_account
+                .to_account_info()
+                .clone(),
+            to: self.sft_vault_ata.to_account_info().clone(),
+            authority: self.user.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+
+    fn transfer_prove_token_to_user(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .prove_token_vault
+                .to_account_info()
+                .clone(),
+            to: self.user_prove_token_account.to_account_info().clone(),
+            authority: self.prove_token_authority.clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+}
+
+impl<'info> UnstakeSft<'info> {
+    fn transfer_sft_to_user(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .sft_vault_ata
+                .to_account_info()
+                .clone(),
+            to: self.user_nft_account.to_account_info().clone(),
+            authority: self.sft_vault_account.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+
+    fn transfer_prove_token_to_vault(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .user_prove_token_account
+                .to_account_info()
+                .clone(),
+            to: self.prove_token_vault.to_account_info().clone(),
+            authority: self.user.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+
+    fn close_sft_vault_ata(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        let cpi_accounts = CloseAccount {
+            account: self.sft_vault_ata.to_account_info().clone(),
+            destination: self.user.to_account_info().clone(),
+            authority: self.sft_vault_account.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+}
+
+impl<'info> StakeCompressed<'info> {
+    fn transfer_prove_token_to_user(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .prove_token_vault
+                .to_account_info()
+                .clone(),
+            to: self.user_prove_token_account.to_account_info().clone(),
+            authority: self.prove_token_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+}
+
+impl<'info> UnstakeCompressed<'info> {
+    fn transfer_prove_token_to_vault(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .user_prove_token_account
+                .to_account_info()
+                .clone(),
+            to: self.prove_token_vault.to_account_info().clone(),
+            authority: self.user.to_account_info(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+}
+
+impl<'info> ClaimDistributionVested<'info> {
+    fn transfer_reward_to_escrow(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self
+                .distribution_vault
+                .to_account_info()
+                .clone(),
+            to: self.vesting_vault.to_account_info().clone(),
+            authority: self.distribution.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
 }
 
-// utils
-impl<'info> Initialize<'info> {
-    fn transfer_prove_token_to_vault(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+impl<'info> WithdrawVested<'info> {
+    fn transfer_vested_to_user(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
         let cpi_accounts = Transfer {
             from: self
-                .admin_prove_token_account
+                .vesting_vault
                 .to_account_info()
                 .clone(),
-            to: self
-            .prove_token_vault
-            .to_account_info()
-            .clone(),
-            authority: self.admin.clone(),
+            to: self.user_reward_account.to_account_info().clone(),
+            authority: self.vesting_escrow.to_account_info().clone(),
         };
         CpiContext::new(self.token_program.clone(), cpi_accounts)
     }
 }
 
-impl<'info> Stake<'info> {
-    fn tansfer_nft_to_vault(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+impl<'info> AttachBooster<'info> {
+    fn transfer_booster_to_vault(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
         let cpi_accounts = Transfer {
             from: self
-                .user_nft_account
+                .user_booster_account
                 .to_account_info()
                 .clone(),
-            to: self.nft_vault_ata.to_account_info().clone(),
-            authority: self.user.clone(),
+            to: self.booster_vault.to_account_info().clone(),
+            authority: self.user.to_account_info().clone(),
         };
         CpiContext::new(self.token_program.clone(), cpi_accounts)
     }
+}
 
-    fn transfer_prove_token_to_user(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+impl<'info> DetachBooster<'info> {
+    fn transfer_booster_to_owner(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
         let cpi_accounts = Transfer {
             from: self
-                .prove_token_vault
+                .booster_vault
                 .to_account_info()
                 .clone(),
-            to: self
-            .user_prove_token_account
-            .to_account_info()
-            .clone(),
-            authority: self.prove_token_authority.clone(),
+            to: self.owner_booster_account.to_account_info().clone(),
+            authority: self.booster.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.clone(), cpi_accounts)
+    }
+
+    fn close_booster_vault(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        let cpi_accounts = CloseAccount {
+            account: self.booster_vault.to_account_info().clone(),
+            destination: self.owner.clone(),
+            authority: self.booster.to_account_info().clone(),
         };
         CpiContext::new(self.token_program.clone(), cpi_accounts)
     }
@@ -443,6 +6165,445 @@ impl<'info> Unstake<'info> {
     }
 }
 
+/// Metaplex Bubblegum, invoked by its instruction layout as its crate needs a newer Anchor.
+pub mod bubblegum {
+    use super::*;
+    use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+    use anchor_lang::solana_program::program::invoke_signed;
+
+    declare_id!("BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY");
+
+    pub mod compression_program {
+        use super::*;
+        declare_id!("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+    }
+
+    pub mod noop_program {
+        use super::*;
+        declare_id!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+    }
+
+    pub struct TransferAccounts<'info> {
+        pub tree_authority: AccountInfo<'info>,
+        pub leaf_owner: AccountInfo<'info>,
+        pub leaf_delegate: AccountInfo<'info>,
+        pub new_leaf_owner: AccountInfo<'info>,
+        pub merkle_tree: AccountInfo<'info>,
+        pub log_wrapper: AccountInfo<'info>,
+        pub compression_program: AccountInfo<'info>,
+        pub system_program: AccountInfo<'info>,
+        pub bubblegum_program: AccountInfo<'info>,
+    }
+
+    pub fn asset_id(merkle_tree: &Pubkey, nonce: u64) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"asset".as_ref(), merkle_tree.as_ref(), &nonce.to_le_bytes()],
+            &ID
+        ).0
+    }
+
+    /// Leaf owner or delegate has to sign, directly or through signer_seeds.
+    pub fn transfer<'info>(
+        accounts: TransferAccounts<'info>,
+        proof: &[AccountInfo<'info>],
+        leaf: &CompressedLeaf,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        let mut data = hash(b"global:transfer").to_bytes()[..8].to_vec();
+        data.extend(leaf.try_to_vec()?);
+
+        let mut account_metas = vec![
+            AccountMeta::new_readonly(accounts.tree_authority.key(), false),
+            AccountMeta::new_readonly(accounts.leaf_owner.key(), accounts.leaf_owner.is_signer || !signer_seeds.is_empty()),
+            AccountMeta::new_readonly(accounts.leaf_delegate.key(), false),
+            AccountMeta::new_readonly(accounts.new_leaf_owner.key(), false),
+            AccountMeta::new(accounts.merkle_tree.key(), false),
+            AccountMeta::new_readonly(accounts.log_wrapper.key(), false),
+            AccountMeta::new_readonly(accounts.compression_program.key(), false),
+            AccountMeta::new_readonly(accounts.system_program.key(), false),
+        ];
+        account_metas.extend(proof.iter().map(|node| AccountMeta::new_readonly(node.key(), false)));
+
+        let mut account_infos = vec![
+            accounts.tree_authority,
+            accounts.leaf_owner,
+            accounts.leaf_delegate,
+            accounts.new_leaf_owner,
+            accounts.merkle_tree,
+            accounts.log_wrapper,
+            accounts.compression_program,
+            accounts.system_program,
+            accounts.bubblegum_program,
+        ];
+        account_infos.extend(proof.iter().cloned());
+
+        invoke_signed(
+            &Instruction { program_id: ID, accounts: account_metas, data },
+            &account_infos,
+            signer_seeds,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Typed wrappers of the view instructions for calling programs.
+#[cfg(feature = "cpi")]
+pub mod view {
+    use super::*;
+    use anchor_lang::solana_program::program::get_return_data;
+
+    pub fn get_position<'a, 'b, 'c, 'info>(
+        ctx: CpiContext<'a, 'b, 'c, 'info, cpi::accounts::GetPosition<'info>>,
+    ) -> Result<PositionView> {
+        cpi::get_position(ctx)?;
+        decode_return_data()
+    }
+
+    pub fn get_pool_stats<'a, 'b, 'c, 'info>(
+        ctx: CpiContext<'a, 'b, 'c, 'info, cpi::accounts::GetPoolStats<'info>>,
+    ) -> Result<PoolStats> {
+        cpi::get_pool_stats(ctx)?;
+        decode_return_data()
+    }
+
+    fn decode_return_data<T: AnchorDeserialize>() -> Result<T> {
+        match get_return_data() {
+            Some((program_id, data)) if program_id == ID => {
+                T::try_from_slice(&data).map_err(|_| ErrorCode::InvalidReturnData.into())
+            }
+            _ => Err(ErrorCode::InvalidReturnData.into()),
+        }
+    }
+}
+
+/// Clears set_bonus and the bonus weight of the other NftVaultAccounts of the set and closes SetBonus
+/// to destination. The unstaked NftVaultAccount's own bonus leaves with its points_weight.
+/// Remaining accounts are SetBonus followed by (PoolInfo, NftVaultAccount, UserPoints) triples of the
+/// other NftVaultAccounts in SetBonus order.
+fn dissolve_set_bonus(
+    nft_vault_key: &Pubkey,
+    nft_vault: &NftVaultAccount,
+    remaining_accounts: &[AccountInfo],
+    destination: &AccountInfo,
+) -> Result<()> {
+    let set_bonus_info = match remaining_accounts.first() {
+        Some(account) if account.key() == nft_vault.set_bonus => account,
+        _ => return Err(ErrorCode::InvalidSetBonusAccount.into()),
+    };
+    let set_bonus: SetBonus = load_program_account(set_bonus_info)?;
+
+    let mut other_triples = remaining_accounts[1..].chunks(3);
+    for (index, other_nft_vault_key) in set_bonus.nft_vaults.iter().enumerate() {
+        if other_nft_vault_key == nft_vault_key {
+            continue;
+        }
+        let triple = match other_triples.next() {
+            Some(triple) if triple.len() == 3 && triple[1].key() == *other_nft_vault_key => triple,
+            _ => return Err(ErrorCode::InvalidSetBonusAccount.into()),
+        };
+        let mut other_nft_vault: NftVaultAccount = load_program_account(&triple[1])?;
+        if other_nft_vault.pool_info != triple[0].key() {
+            return Err(ErrorCode::InvalidSetBonusAccount.into());
+        };
+        let bonus_weight = set_bonus.bonus_weights.get(index).copied().unwrap_or(0);
+        shift_points_weight(&triple[0], &triple[2], &other_nft_vault.user, bonus_weight, false)?;
+        other_nft_vault.points_weight -= bonus_weight;
+        other_nft_vault.set_bonus = Pubkey::default();
+        store_program_account(&triple[1], &other_nft_vault)?;
+    }
+
+    close_program_account(set_bonus_info, destination)?;
+
+    emit!(SetDissolved {
+        set_info: set_bonus.set_info,
+        set_bonus: set_bonus_info.key(),
+        user: set_bonus.user,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Weight of one staked NFT of rarity, matched case-insensitively against RARITY_POINTS_WEIGHTS.
+pub fn rarity_points_weight(rarity: &[u8; 16]) -> u64 {
+    let len = rarity.iter().position(|byte| *byte == 0).unwrap_or(rarity.len());
+    RARITY_POINTS_WEIGHTS
+        .iter()
+        .find(|(tier, _)| tier.as_bytes().eq_ignore_ascii_case(&rarity[..len]))
+        .map_or(POINTS_WEIGHT_BASE, |(_, weight)| *weight)
+}
+
+/// Weight of amount staked SFTs of rarity, each weighs as one NFT.
+fn sft_points_weight(amount: u64, rarity: &[u8; 16]) -> Result<u64> {
+    amount
+        .checked_mul(rarity_points_weight(rarity))
+        .ok_or_else(|| ErrorCode::MathOverflow.into())
+}
+
+/// Fills a UserPoints created by init_if_needed, no-op once it is initialized.
+fn init_user_points(user_points: &mut UserPoints, user: Pubkey, pool_info: Pubkey) {
+    if user_points.user == Pubkey::default() {
+        user_points.user = user;
+        user_points.pool_info = pool_info;
+        user_points.version = UserPoints::CURRENT_VERSION;
+    };
+}
+
+/// Weight a set adds on top of a member position's points_weight.
+fn set_bonus_weight(points_weight: u64, bonus_bps: u64) -> u64 {
+    (points_weight as u128 * bonus_bps as u128 / BPS_BASE as u128) as u64
+}
+
+/// Adds or removes weight on user's UserPoints of a pool passed as raw accounts, settling accrual first.
+fn shift_points_weight(
+    pool_info_info: &AccountInfo,
+    user_points_info: &AccountInfo,
+    user: &Pubkey,
+    weight: u64,
+    add: bool,
+) -> Result<()> {
+    // Positions staked before points carry no weight and may have no UserPoints
+    if weight == 0 {
+        return Ok(());
+    };
+    let (user_points_key, _) = Pubkey::find_program_address(
+        &[pool_info_info.key.as_ref(), user.as_ref(), b"user_points".as_ref()],
+        &crate::ID,
+    );
+    if user_points_info.key() != user_points_key {
+        return Err(ErrorCode::InvalidSetBonusAccount.into());
+    };
+
+    let mut pool_info: PoolInfo = load_program_account(pool_info_info)?;
+    let mut user_points: UserPoints = load_program_account(user_points_info)?;
+    pool_info.checkpoint_points(Clock::get()?.unix_timestamp);
+    user_points.checkpoint(&pool_info);
+    if add {
+        user_points.weight += weight;
+    } else {
+        user_points.weight -= weight;
+    };
+    store_program_account(pool_info_info, &pool_info)?;
+    store_program_account(user_points_info, &user_points)
+}
+
+/// Checks user holds gate_min_balance of the pool's gate mint, passes for ungated pools.
+fn check_gate(pool_info: &PoolInfo, user: &Pubkey, gate_token_account: Option<&AccountInfo>) -> Result<()> {
+    if pool_info.gate_mint == Pubkey::default() {
+        return Ok(());
+    };
+    let gate_token_account = match gate_token_account {
+        Some(account) if *account.owner == token::ID => {
+            TokenAccount::try_deserialize(&mut &account.data.borrow()[..])?
+        }
+        _ => return Err(ErrorCode::InvalidGateTokenAccount.into()),
+    };
+    if gate_token_account.mint != pool_info.gate_mint || gate_token_account.owner != *user {
+        return Err(ErrorCode::InvalidGateTokenAccount.into());
+    };
+    if gate_token_account.amount < pool_info.gate_min_balance {
+        return Err(ErrorCode::InsufficientGateTokenBalance.into());
+    };
+    Ok(())
+}
+
+/// Replaces guild members, owed points are kept for members staying in the guild.
+fn set_guild_members(guild: &mut Guild, members: Vec<GuildShare>) -> Result<()> {
+    if members.is_empty() || members.len() > Guild::MAX_MEMBERS {
+        return Err(ErrorCode::InvalidGuildMembers.into());
+    };
+    for (index, share) in members.iter().enumerate() {
+        if share.shares == 0 || members[..index].iter().any(|other| other.member == share.member) {
+            return Err(ErrorCode::InvalidGuildMembers.into());
+        };
+    }
+    // Removed members claim what they are owed first
+    for old_member in guild.members.iter() {
+        if old_member.owed > 0 && !members.iter().any(|share| share.member == old_member.member) {
+            return Err(ErrorCode::GuildMemberOwed.into());
+        };
+    }
+
+    let new_members: Vec<GuildMember> = members
+        .iter()
+        .map(|share| GuildMember {
+            member: share.member,
+            shares: share.shares,
+            owed: guild.members.iter()
+                .find(|old_member| old_member.member == share.member)
+                .map_or(0, |old_member| old_member.owed),
+        })
+        .collect();
+    guild.total_shares = new_members.iter().map(|member| member.shares).sum();
+    guild.members = new_members;
+    Ok(())
+}
+
+fn load_program_account<T: AccountDeserialize + Owner>(info: &AccountInfo) -> Result<T> {
+    if *info.owner != T::owner() {
+        return Err(anchor_lang::error::ErrorCode::AccountOwnedByWrongProgram.into());
+    };
+    T::try_deserialize(&mut &info.data.borrow()[..])
+}
+
+fn store_program_account<T: AccountSerialize>(info: &AccountInfo, account: &T) -> Result<()> {
+    if !info.is_writable {
+        return Err(anchor_lang::error::ErrorCode::ConstraintMut.into());
+    };
+    account.try_serialize(&mut &mut info.data.borrow_mut()[..])
+}
+
+fn close_program_account(info: &AccountInfo, destination: &AccountInfo) -> Result<()> {
+    if !info.is_writable {
+        return Err(anchor_lang::error::ErrorCode::ConstraintMut.into());
+    };
+    let lamports = info.lamports();
+    **info.lamports.borrow_mut() = 0;
+    **destination.lamports.borrow_mut() += lamports;
+    info.data.borrow_mut().fill(0);
+    Ok(())
+}
+
+fn realloc_program_account<'info>(
+    info: &AccountInfo<'info>,
+    new_len: usize,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    if info.data_len() >= new_len {
+        return Ok(());
+    };
+    let lamports = Rent::get()?.minimum_balance(new_len).saturating_sub(info.lamports());
+    if lamports > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: info.clone(),
+                },
+            ),
+            lamports,
+        )?;
+    };
+    info.realloc(new_len, true)?;
+    Ok(())
+}
+
+/// Reallocs a NftVaultAccount of an older layout to NftVaultAccount::LEN and bumps its version.
+fn migrate_nft_vault<'info>(
+    nft_vault_info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<NftVaultAccount> {
+    if *nft_vault_info.owner != crate::ID
+        || nft_vault_info.data_len() < 8
+        || nft_vault_info.data.borrow()[..8] != NftVaultAccount::discriminator()
+    {
+        return Err(ErrorCode::InvalidMigrationAccount.into());
+    };
+    // Fields added after the old layout are read from the zeroed tail as their default
+    realloc_program_account(nft_vault_info, NftVaultAccount::LEN, payer, system_program)?;
+    let mut nft_vault: NftVaultAccount = load_program_account(nft_vault_info)?;
+    if nft_vault.version < NftVaultAccount::CURRENT_VERSION {
+        nft_vault.version = NftVaultAccount::CURRENT_VERSION;
+        // Stake time was not recorded before, count from migration
+        if nft_vault.stake_time == 0 {
+            nft_vault.stake_time = Clock::get()?.unix_timestamp;
+        };
+        store_program_account(nft_vault_info, &nft_vault)?;
+    };
+    Ok(nft_vault)
+}
+
+/// NftVaultAccount of any version without migrating it, the tail missing from old layouts reads as zeroed.
+fn read_nft_vault(nft_vault_info: &AccountInfo) -> Result<NftVaultAccount> {
+    if *nft_vault_info.owner != crate::ID {
+        return Err(ErrorCode::NftVaultMismatch.into());
+    };
+    let mut data = nft_vault_info.data.borrow().to_vec();
+    if data.len() < NftVaultAccount::LEN {
+        data.resize(NftVaultAccount::LEN, 0);
+    };
+    NftVaultAccount::try_deserialize(&mut &data[..])
+}
+
+/// Entry of a staked NftVaultAccount, SftVaultAccount or CnftVaultAccount, marked recorded in snapshot.
+/// Positions of other pools, staked after the snapshot began or already recorded are rejected.
+fn record_snapshot_position(position_info: &AccountInfo, snapshot_key: &Pubkey, snapshot: &Snapshot) -> Result<SnapshotEntry> {
+    let discriminator: [u8; 8] = {
+        let data = position_info.data.borrow();
+        if data.len() < 8 {
+            return Err(ErrorCode::InvalidSnapshotEntry.into());
+        };
+        data[..8].try_into().unwrap()
+    };
+    let check = |pool_info: Pubkey, last_snapshot: Pubkey, stake_time: i64| -> Result<()> {
+        if pool_info != snapshot.pool_info || last_snapshot == *snapshot_key || stake_time > snapshot.timestamp {
+            return Err(ErrorCode::InvalidSnapshotEntry.into());
+        };
+        Ok(())
+    };
+
+    if discriminator == NftVaultAccount::discriminator() {
+        let mut position: NftVaultAccount = load_program_account(position_info)?;
+        check(position.pool_info, position.last_snapshot, position.stake_time)?;
+        position.last_snapshot = *snapshot_key;
+        store_program_account(position_info, &position)?;
+        Ok(SnapshotEntry { user: position.user, nft_mint: position.nft_mint, stake_time: position.stake_time, amount: 1 })
+    } else if discriminator == SftVaultAccount::discriminator() {
+        let mut position: SftVaultAccount = load_program_account(position_info)?;
+        check(position.pool_info, position.last_snapshot, position.stake_time)?;
+        position.last_snapshot = *snapshot_key;
+        store_program_account(position_info, &position)?;
+        Ok(SnapshotEntry { user: position.user, nft_mint: position.nft_mint, stake_time: position.stake_time, amount: position.amount })
+    } else if discriminator == CnftVaultAccount::discriminator() {
+        let mut position: CnftVaultAccount = load_program_account(position_info)?;
+        check(position.pool_info, position.last_snapshot, position.stake_time)?;
+        position.last_snapshot = *snapshot_key;
+        store_program_account(position_info, &position)?;
+        Ok(SnapshotEntry { user: position.user, nft_mint: position.asset_id, stake_time: position.stake_time, amount: 1 })
+    } else {
+        Err(ErrorCode::InvalidSnapshotEntry.into())
+    }
+}
+
+/// Pairs are hashed in sorted order, so proofs carry no left/right flags.
+fn verify_merkle_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let mut computed = leaf;
+    for node in proof.iter() {
+        computed = if computed <= *node {
+            hashv(&[&computed, node]).to_bytes()
+        } else {
+            hashv(&[node, &computed]).to_bytes()
+        };
+    }
+    computed == root
+}
+
+/// Hash of the first slot at or after target, SlotHashes is sorted by slot descending.
+/// Fails once target has left SlotHashes, the oldest slot kept is then not the first after target.
+fn find_slot_hash(slot_hashes: &AccountInfo, target_slot: u64) -> Result<[u8; 32]> {
+    let data = slot_hashes.data.borrow();
+    let len = u64::from_le_bytes(data[..8].try_into().unwrap()) as usize;
+    let mut found: Option<(u64, [u8; 32])> = None;
+    let mut passed_target = false;
+    for index in 0..len {
+        let offset = 8 + index * 40;
+        let slot = u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+        if slot < target_slot {
+            passed_target = true;
+            break;
+        };
+        found = Some((slot, data[offset + 8..offset + 40].try_into().unwrap()));
+    }
+    match found {
+        Some((slot, hash)) if passed_target || slot == target_slot => Ok(hash),
+        _ => Err(ErrorCode::SlotHashNotFound.into()),
+    }
+}
+
 pub fn seedhash(collection: String, rarity: String, nonce: u64, rarity_info: String)
 -> String {
     let mut _hash:String;
@@ -458,4 +6619,635 @@ pub fn seedhash(collection: String, rarity: String, nonce: u64, rarity_info: Str
                 .concat())
                 .as_bytes()));
     _hash
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Account of T as stored right after allocation, every field at its zero value.
+    fn zeroed<T: AccountDeserialize + Discriminator>(len: usize) -> T {
+        let mut data = vec![0u8; len];
+        data[..8].copy_from_slice(&T::discriminator());
+        T::try_deserialize(&mut &data[..]).unwrap()
+    }
+
+    fn merkle_parent(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+        if a <= b {
+            hashv(&[&a, &b]).to_bytes()
+        } else {
+            hashv(&[&b, &a]).to_bytes()
+        }
+    }
+
+    fn claim_leaf(leaf_index: u64, user: &Pubkey, amount: u64) -> [u8; 32] {
+        hashv(&[&leaf_index.to_le_bytes(), &user.to_bytes(), &amount.to_le_bytes()]).to_bytes()
+    }
+
+    #[test]
+    fn merkle_proof_empty_matches_only_root() {
+        let leaf = [7u8; 32];
+        assert!(verify_merkle_proof(&[], leaf, leaf));
+        assert!(!verify_merkle_proof(&[], [8u8; 32], leaf));
+    }
+
+    #[test]
+    fn merkle_proof_verifies_every_leaf() {
+        let leaves = [[1u8; 32], [2u8; 32], [3u8; 32], [4u8; 32]];
+        let left = merkle_parent(leaves[0], leaves[1]);
+        let right = merkle_parent(leaves[2], leaves[3]);
+        let root = merkle_parent(left, right);
+
+        assert!(verify_merkle_proof(&[leaves[1], right], root, leaves[0]));
+        assert!(verify_merkle_proof(&[leaves[0], right], root, leaves[1]));
+        assert!(verify_merkle_proof(&[leaves[3], left], root, leaves[2]));
+        assert!(verify_merkle_proof(&[leaves[2], left], root, leaves[3]));
+        // Wrong sibling, wrong order of levels and a truncated proof are rejected
+        assert!(!verify_merkle_proof(&[leaves[2], right], root, leaves[0]));
+        assert!(!verify_merkle_proof(&[right, leaves[1]], root, leaves[0]));
+        assert!(!verify_merkle_proof(&[leaves[1]], root, leaves[0]));
+    }
+
+    fn points_pool(points_per_second: u64) -> PoolInfo {
+        let mut pool_info: PoolInfo = zeroed(PoolInfo::LEN);
+        pool_info.points_per_second = points_per_second;
+        pool_info.last_points_update = 1_000;
+        pool_info
+    }
+
+    fn curve_pool(total_locked: u64, listed_count: u64) -> PoolInfo {
+        let mut pool_info = points_pool(7);
+        pool_info.curve_enabled = true;
+        pool_info.curve_min_points_per_second = 100;
+        pool_info.curve_max_points_per_second = 1_100;
+        pool_info.total_locked = total_locked;
+        pool_info.listed_count = listed_count;
+        pool_info
+    }
+
+    #[test]
+    fn effective_rate_is_flat_without_curve() {
+        let mut pool_info = curve_pool(5, 10);
+        pool_info.curve_enabled = false;
+        assert_eq!(pool_info.effective_points_per_second(), 7);
+    }
+
+    #[test]
+    fn effective_rate_follows_utilisation() {
+        assert_eq!(curve_pool(0, 10).effective_points_per_second(), 1_100);
+        assert_eq!(curve_pool(5, 10).effective_points_per_second(), 600);
+        assert_eq!(curve_pool(10, 10).effective_points_per_second(), 100);
+        // Rounding favours the higher rate: 1/3 utilisation is 3_333 bps, 1_000 * 3_333 / 10_000 = 333
+        assert_eq!(curve_pool(1, 3).effective_points_per_second(), 767);
+    }
+
+    #[test]
+    fn effective_rate_edges() {
+        // Nothing listed reads as empty, over-locked SFT pools cap at full utilisation
+        assert_eq!(curve_pool(0, 0).effective_points_per_second(), 1_100);
+        assert_eq!(curve_pool(30, 10).effective_points_per_second(), 100);
+
+        let mut flat = curve_pool(5, 10);
+        flat.curve_max_points_per_second = 100;
+        assert_eq!(flat.effective_points_per_second(), 100);
+    }
+
+    #[test]
+    fn accrual_uses_effective_rate() {
+        let mut pool_info = curve_pool(5, 10);
+        pool_info.checkpoint_points(1_010);
+        let mut user_points: UserPoints = zeroed(UserPoints::LEN);
+        user_points.weight = POINTS_WEIGHT_BASE;
+        user_points.checkpoint(&pool_info);
+        assert_eq!(user_points.points, 6_000);
+    }
+
+    #[test]
+    fn distribution_claim_checks_index_and_bitmap() {
+        let user = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let leaf = claim_leaf(0, &user, 100);
+        let sibling = claim_leaf(1, &other, 50);
+        let mut distribution: Distribution = zeroed(Distribution::space(2));
+        distribution.num_leaves = 2;
+        distribution.claimed_bitmap = vec![0u8; Distribution::bitmap_len(2)];
+        distribution.merkle_root = merkle_parent(leaf, sibling);
+
+        assert!(distribution.verify_claim(0, &user, 100, &[sibling]).is_ok());
+        assert!(distribution.verify_claim(0, &user, 101, &[sibling]).is_err());
+        assert!(distribution.verify_claim(0, &other, 100, &[sibling]).is_err());
+        assert!(distribution.verify_claim(2, &user, 100, &[sibling]).is_err());
+
+        distribution.set_claimed(0);
+        assert!(distribution.is_claimed(0));
+        assert!(!distribution.is_claimed(1));
+        assert!(distribution.verify_claim(0, &user, 100, &[sibling]).is_err());
+    }
+
+    #[test]
+    fn vesting_zero_duration_unlocks_at_once() {
+        let mut escrow: VestingEscrow = zeroed(VestingEscrow::LEN);
+        escrow.deposit(7, 1_000, 0);
+        assert_eq!(escrow.withdrawable(1_000), 7);
+
+        escrow.withdraw(1_000);
+        assert_eq!(escrow.withdrawable(1_000), 0);
+        escrow.deposit(4, 1_000, 0);
+        assert_eq!(escrow.withdrawable(1_000), 4);
+    }
+
+    #[test]
+    fn vesting_rounds_down_until_the_end() {
+        let mut escrow: VestingEscrow = zeroed(VestingEscrow::LEN);
+        escrow.deposit(10, 1_000, 3);
+        assert_eq!(escrow.withdrawable(1_000), 0);
+        assert_eq!(escrow.withdrawable(1_001), 3);
+        assert_eq!(escrow.withdrawable(1_002), 6);
+        assert_eq!(escrow.withdrawable(1_003), 10);
+        assert_eq!(escrow.withdrawable(2_000), 10);
+    }
+
+    #[test]
+    fn vesting_deposit_carries_unlocked_remainder() {
+        let mut escrow: VestingEscrow = zeroed(VestingEscrow::LEN);
+        escrow.deposit(10, 1_000, 3);
+        escrow.withdraw(1_001);
+        assert_eq!(escrow.withdrawable(1_002), 3);
+
+        // Unlocked 3 carries over, the locked 4 restarts vesting with the new 5
+        escrow.deposit(5, 1_002, 3);
+        assert_eq!(escrow.unlocked_carry, 3);
+        assert_eq!(escrow.total_amount, 9);
+        assert_eq!(escrow.withdrawable(1_002), 3);
+        assert_eq!(escrow.withdrawable(1_003), 6);
+        // Everything deposited is paid out once, 3 withdrawn before plus 12
+        assert_eq!(escrow.withdrawable(1_005), 12);
+    }
+
+    fn guild_of(shares: &[u64]) -> Guild {
+        let mut guild: Guild = zeroed(Guild::LEN);
+        let members = shares
+            .iter()
+            .map(|&shares| GuildShare { member: Pubkey::new_unique(), shares })
+            .collect();
+        set_guild_members(&mut guild, members).unwrap();
+        guild
+    }
+
+    #[test]
+    fn guild_distribute_keeps_rounding_remainder() {
+        let mut guild = guild_of(&[1, 1, 1]);
+        let mut guild_points: UserPoints = zeroed(UserPoints::LEN);
+        guild_points.points = 10;
+
+        guild.distribute(&mut guild_points);
+        assert!(guild.members.iter().all(|member| member.owed == 3));
+        assert_eq!(guild_points.points, 1);
+        assert_eq!(guild.distributed_points, 9);
+
+        // Dust joins the next split
+        guild_points.points += 2;
+        guild.distribute(&mut guild_points);
+        assert!(guild.members.iter().all(|member| member.owed == 4));
+        assert_eq!(guild_points.points, 0);
+        assert_eq!(guild.distributed_points, 12);
+    }
+
+    #[test]
+    fn guild_distribute_follows_shares() {
+        let mut guild = guild_of(&[3, 1]);
+        let mut guild_points: UserPoints = zeroed(UserPoints::LEN);
+        guild_points.points = 7;
+
+        guild.distribute(&mut guild_points);
+        assert_eq!(guild.members[0].owed, 5);
+        assert_eq!(guild.members[1].owed, 1);
+        assert_eq!(guild_points.points, 1);
+    }
+
+    #[test]
+    fn guild_without_shares_distributes_nothing() {
+        let mut guild: Guild = zeroed(Guild::LEN);
+        let mut guild_points: UserPoints = zeroed(UserPoints::LEN);
+        guild_points.points = 10;
+
+        guild.distribute(&mut guild_points);
+        assert_eq!(guild_points.points, 10);
+        assert_eq!(guild.distributed_points, 0);
+
+        // Members can't be set with zero shares
+        let member = Pubkey::new_unique();
+        assert!(set_guild_members(&mut guild, vec![GuildShare { member, shares: 0 }]).is_err());
+    }
+
+    #[test]
+    fn set_bonus_increases_accrued_points() {
+        let mut pool_info = points_pool(10);
+        let mut plain: UserPoints = zeroed(UserPoints::LEN);
+        let mut in_set: UserPoints = zeroed(UserPoints::LEN);
+        plain.weight = POINTS_WEIGHT_BASE;
+        in_set.weight = POINTS_WEIGHT_BASE + set_bonus_weight(POINTS_WEIGHT_BASE, 2_500);
+
+        pool_info.checkpoint_points(1_100);
+        plain.checkpoint(&pool_info);
+        in_set.checkpoint(&pool_info);
+
+        assert_eq!(plain.points, 1_000);
+        assert_eq!(in_set.points, 1_250);
+    }
+
+    #[test]
+    fn rarity_points_weight_follows_tier() {
+        assert_eq!(rarity_points_weight(&nft_rarity::tofixlength("common".to_string())), POINTS_WEIGHT_BASE);
+        assert_eq!(rarity_points_weight(&nft_rarity::tofixlength("Legendary".to_string())), 30_000);
+        assert_eq!(rarity_points_weight(&nft_rarity::tofixlength("rare".to_string())), 15_000);
+        // Unknown tiers and prefixes of a tier weigh as common
+        assert_eq!(rarity_points_weight(&nft_rarity::tofixlength("rar".to_string())), POINTS_WEIGHT_BASE);
+        assert_eq!(rarity_points_weight(&[0u8; 16]), POINTS_WEIGHT_BASE);
+    }
+
+    #[test]
+    fn set_bonus_weight_rounds_down() {
+        assert_eq!(set_bonus_weight(0, 2_500), 0);
+        assert_eq!(set_bonus_weight(3, 5_000), 1);
+        assert_eq!(set_bonus_weight(POINTS_WEIGHT_BASE, 0), 0);
+    }
+
+    /// SlotHashes data holding (slot, hash) pairs, the hash is the slot's low byte repeated.
+    fn slot_hashes_data(slots: &[u64]) -> Vec<u8> {
+        let mut data = (slots.len() as u64).to_le_bytes().to_vec();
+        for slot in slots.iter() {
+            data.extend_from_slice(&slot.to_le_bytes());
+            data.extend_from_slice(&[*slot as u8; 32]);
+        }
+        data
+    }
+
+    fn slot_hash_of(slots: &[u64], target_slot: u64) -> Result<[u8; 32]> {
+        let key = sysvar::slot_hashes::ID;
+        let owner = sysvar::ID;
+        let mut lamports = 0u64;
+        let mut data = slot_hashes_data(slots);
+        let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        find_slot_hash(&info, target_slot)
+    }
+
+    #[test]
+    fn slot_hash_of_reveal_slot() {
+        let slots = [105, 104, 103, 102, 101];
+        assert_eq!(slot_hash_of(&slots, 103).unwrap(), [103u8; 32]);
+        // The oldest slot kept is still the reveal slot itself
+        assert_eq!(slot_hash_of(&slots, 101).unwrap(), [101u8; 32]);
+    }
+
+    #[test]
+    fn slot_hash_skips_to_next_produced_slot() {
+        // Slot 103 was skipped by its leader, 104 is the first hash after it
+        assert_eq!(slot_hash_of(&[106, 105, 104, 102, 101], 103).unwrap(), [104u8; 32]);
+    }
+
+    #[test]
+    fn slot_hash_missing_once_out_of_window() {
+        // Reveal slot not produced yet
+        assert!(slot_hash_of(&[105, 104, 103], 106).is_err());
+        // Reveal slot dropped out of SlotHashes, 103 is not the first hash after 100
+        assert!(slot_hash_of(&[105, 104, 103], 100).is_err());
+        assert!(slot_hash_of(&[], 100).is_err());
+    }
+
+    #[test]
+    fn set_accounts_fit_the_largest_set() {
+        let set_info = SetInfo {
+            pool_info: Pubkey::new_unique(),
+            rarity_infos: vec![Pubkey::new_unique(); 8],
+            bonus_bps: BPS_BASE,
+        };
+        assert!(8 + set_info.try_to_vec().unwrap().len() <= SetInfo::LEN);
+
+        let set_bonus = SetBonus {
+            user: Pubkey::new_unique(),
+            set_info: Pubkey::new_unique(),
+            nft_vaults: vec![Pubkey::new_unique(); 8],
+            bonus_bps: BPS_BASE,
+            bonus_weights: vec![u64::MAX; 8],
+        };
+        assert!(8 + set_bonus.try_to_vec().unwrap().len() <= SetBonus::LEN);
+    }
+
+    #[test]
+    fn read_nft_vault_decodes_legacy_layout() {
+        let key = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let pool_info = Pubkey::new_unique();
+        let nft_mint = Pubkey::new_unique();
+        // Layout before versioning: discriminator, user, pool_info and nft_mint
+        let mut data = NftVaultAccount::discriminator().to_vec();
+        data.extend_from_slice(&user.to_bytes());
+        data.extend_from_slice(&pool_info.to_bytes());
+        data.extend_from_slice(&nft_mint.to_bytes());
+        assert_eq!(data.len(), 104);
+        let mut lamports = 0;
+        let owner = crate::ID;
+        let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+
+        let nft_vault = read_nft_vault(&info).unwrap();
+        assert_eq!(nft_vault.user, user);
+        assert_eq!(nft_vault.pool_info, pool_info);
+        assert_eq!(nft_vault.nft_mint, nft_mint);
+        assert_eq!(nft_vault.version, 0);
+        assert!(!nft_vault.boosted);
+        // Left as is, audit only reads
+        assert_eq!(info.data_len(), 104);
+    }
+
+    #[test]
+    fn read_nft_vault_rejects_foreign_accounts() {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = vec![0u8; NftVaultAccount::LEN];
+        data[..8].copy_from_slice(&NftVaultAccount::discriminator());
+        let other_program = Pubkey::new_unique();
+        let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &other_program, false, 0);
+        assert!(read_nft_vault(&info).is_err());
+
+        let mut lamports = 0;
+        let mut data = vec![0u8; PoolInfo::LEN];
+        data[..8].copy_from_slice(&PoolInfo::discriminator());
+        let owner = crate::ID;
+        let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        assert!(read_nft_vault(&info).is_err());
+    }
+
+    #[test]
+    fn referral_excess_stays_owed() {
+        let mut referrer: UserPoints = zeroed(UserPoints::LEN);
+        let mut referred: UserPoints = zeroed(UserPoints::LEN);
+        referred.referral_owed = 70;
+
+        assert_eq!(referrer.credit_referral(&mut referred, 0), 0);
+        assert_eq!(referred.referral_owed, 70);
+
+        assert_eq!(referrer.credit_referral(&mut referred, 50), 50);
+        assert_eq!(referred.referral_owed, 20);
+        assert_eq!(referrer.points, 50);
+        // Cap is on the referrer's lifetime earnings, the rest is credited once raised
+        assert_eq!(referrer.credit_referral(&mut referred, 50), 0);
+        assert_eq!(referrer.credit_referral(&mut referred, 100), 20);
+        assert_eq!(referred.referral_owed, 0);
+        assert_eq!(referrer.referral_earned, 70);
+    }
+
+    /// Storage behind one AccountInfo handed to try_accounts.
+    struct TestAccount {
+        key: Pubkey,
+        owner: Pubkey,
+        lamports: u64,
+        data: Vec<u8>,
+        signer: bool,
+        executable: bool,
+    }
+
+    impl TestAccount {
+        fn new(key: Pubkey, owner: Pubkey, data: Vec<u8>) -> Self {
+            TestAccount { key, owner, lamports: 1_000_000, data, signer: false, executable: false }
+        }
+
+        fn program_account<T: AccountSerialize>(key: Pubkey, account: &T, len: usize) -> Self {
+            let mut data = Vec::with_capacity(len);
+            account.try_serialize(&mut data).unwrap();
+            data.resize(len, 0);
+            TestAccount::new(key, crate::ID, data)
+        }
+
+        fn packed<T: anchor_lang::solana_program::program_pack::Pack>(key: Pubkey, state: T) -> Self {
+            let mut data = vec![0u8; T::LEN];
+            T::pack(state, &mut data).unwrap();
+            TestAccount::new(key, token::ID, data)
+        }
+
+        fn executable(key: Pubkey) -> Self {
+            let mut account = TestAccount::new(key, Pubkey::default(), vec![]);
+            account.executable = true;
+            account
+        }
+
+        fn info(&mut self) -> AccountInfo<'_> {
+            AccountInfo::new(&self.key, self.signer, true, &mut self.lamports, &mut self.data, &self.owner, self.executable, 0)
+        }
+    }
+
+    fn error_code_number(err: Error) -> u32 {
+        match err {
+            Error::AnchorError(err) => err.error_code_number,
+            Error::ProgramError(err) => panic!("unexpected program error {:?}", err),
+        }
+    }
+
+    /// Accounts of unstake_compressed for a position staked by staker, signed by signer.
+    fn unstake_compressed_accounts(
+        staker: Pubkey,
+        signer: Pubkey,
+        token_program: Pubkey,
+        leaf: &CompressedLeaf,
+    ) -> Vec<TestAccount> {
+        let rarity_info_key = Pubkey::new_unique();
+        let pool_info_key = Pubkey::find_program_address(&[rarity_info_key.as_ref(), b"pool_info"], &crate::ID).0;
+        let prove_token_mint = Pubkey::new_unique();
+        let prove_token_vault = Pubkey::new_unique();
+        let merkle_tree = Pubkey::new_unique();
+        let cnft_vault_key = Pubkey::find_program_address(
+            &[merkle_tree.as_ref(), &leaf.nonce.to_le_bytes(), pool_info_key.as_ref(), b"cnft_vault"],
+            &crate::ID,
+        ).0;
+        let user_points_key = Pubkey::find_program_address(
+            &[pool_info_key.as_ref(), staker.as_ref(), b"user_points"],
+            &crate::ID,
+        ).0;
+
+        let mut pool_info: PoolInfo = zeroed(PoolInfo::LEN);
+        pool_info.rarity_info = rarity_info_key;
+        pool_info.prove_token_mint = prove_token_mint;
+        pool_info.prove_token_vault = prove_token_vault;
+        let mut rarity_info_data = RarityInfo::discriminator().to_vec();
+        rarity_info_data.resize(128, 0);
+        let token_account = |owner: Pubkey| spl_token::state::Account {
+            mint: prove_token_mint,
+            owner,
+            amount: 1,
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        };
+        let mut cnft_vault: CnftVaultAccount = zeroed(CnftVaultAccount::LEN);
+        cnft_vault.user = staker;
+        cnft_vault.pool_info = pool_info_key;
+        cnft_vault.merkle_tree = merkle_tree;
+        cnft_vault.nonce = leaf.nonce;
+        let mut user_points: UserPoints = zeroed(UserPoints::LEN);
+        user_points.user = staker;
+        user_points.pool_info = pool_info_key;
+
+        let mut user = TestAccount::new(signer, Pubkey::default(), vec![]);
+        user.signer = true;
+        vec![
+            user,
+            TestAccount::program_account(pool_info_key, &pool_info, PoolInfo::LEN),
+            TestAccount::packed(prove_token_mint, spl_token::state::Mint {
+                decimals: 0,
+                is_initialized: true,
+                ..Default::default()
+            }),
+            TestAccount::new(rarity_info_key, nft_rarity::ID, rarity_info_data),
+            TestAccount::packed(Pubkey::new_unique(), token_account(signer)),
+            TestAccount::packed(prove_token_vault, token_account(Pubkey::new_unique())),
+            TestAccount::program_account(cnft_vault_key, &cnft_vault, CnftVaultAccount::LEN),
+            TestAccount::program_account(user_points_key, &user_points, UserPoints::LEN),
+            TestAccount::new(Pubkey::new_unique(), Pubkey::default(), vec![]),
+            TestAccount::new(merkle_tree, Pubkey::default(), vec![]),
+            TestAccount::executable(bubblegum::noop_program::ID),
+            TestAccount::executable(bubblegum::compression_program::ID),
+            TestAccount::executable(bubblegum::ID),
+            TestAccount::executable(system_program::ID),
+            TestAccount::executable(token_program),
+        ]
+    }
+
+    fn try_unstake_compressed(staker: Pubkey, signer: Pubkey, token_program: Pubkey) -> Result<()> {
+        let leaf = CompressedLeaf { root: [1u8; 32], data_hash: [2u8; 32], creator_hash: [3u8; 32], nonce: 7, index: 7 };
+        let mut accounts = unstake_compressed_accounts(staker, signer, token_program, &leaf);
+        let infos: Vec<AccountInfo> = accounts.iter_mut().map(|account| account.info()).collect();
+        let mut bumps = std::collections::BTreeMap::new();
+        UnstakeCompressed::try_accounts(&crate::ID, &mut &infos[..], &leaf.try_to_vec()?, &mut bumps)?;
+        Ok(())
+    }
+
+    #[test]
+    fn unstake_compressed_requires_the_staker() {
+        let staker = Pubkey::new_unique();
+        assert!(try_unstake_compressed(staker, staker, token::ID).is_ok());
+
+        let err = try_unstake_compressed(staker, Pubkey::new_unique(), token::ID).unwrap_err();
+        assert_eq!(error_code_number(err), ErrorCode::CnftVaultMismatch as u32 + anchor_lang::error::ERROR_CODE_OFFSET);
+    }
+
+    #[test]
+    fn unstake_compressed_requires_the_token_program() {
+        let staker = Pubkey::new_unique();
+        let err = try_unstake_compressed(staker, staker, Pubkey::new_unique()).unwrap_err();
+        assert_eq!(error_code_number(err), anchor_lang::error::ErrorCode::InvalidProgramId as u32);
+    }
+
+    #[test]
+    fn sft_points_weight_scales_and_rejects_overflow() {
+        let rare = nft_rarity::tofixlength("rare".to_string());
+        assert_eq!(sft_points_weight(1, &rare).unwrap(), 15_000);
+        assert_eq!(sft_points_weight(4, &rare).unwrap(), 60_000);
+        assert_eq!(sft_points_weight(u64::MAX / 15_000, &rare).unwrap(), u64::MAX / 15_000 * 15_000);
+
+        let err = sft_points_weight(u64::MAX / 15_000 + 1, &rare).unwrap_err();
+        assert_eq!(error_code_number(err), ErrorCode::MathOverflow as u32 + anchor_lang::error::ERROR_CODE_OFFSET);
+        assert!(sft_points_weight(u64::MAX, &[0u8; 16]).is_err());
+    }
+
+    fn try_force_unstake_compressed(admin: Pubkey, pauser: Pubkey, signer: Pubkey) -> Result<()> {
+        let leaf = CompressedLeaf { root: [1u8; 32], data_hash: [2u8; 32], creator_hash: [3u8; 32], nonce: 3, index: 3 };
+        let staker = Pubkey::new_unique();
+        let pool_info_key = Pubkey::new_unique();
+        let merkle_tree = Pubkey::new_unique();
+        let cnft_vault_key = Pubkey::find_program_address(
+            &[merkle_tree.as_ref(), &leaf.nonce.to_le_bytes(), pool_info_key.as_ref(), b"cnft_vault"],
+            &crate::ID,
+        ).0;
+        let user_points_key = Pubkey::find_program_address(
+            &[pool_info_key.as_ref(), staker.as_ref(), b"user_points"],
+            &crate::ID,
+        ).0;
+
+        let mut pool_info: PoolInfo = zeroed(PoolInfo::LEN);
+        pool_info.admin = admin;
+        pool_info.pauser = pauser;
+        let mut cnft_vault: CnftVaultAccount = zeroed(CnftVaultAccount::LEN);
+        cnft_vault.user = staker;
+        cnft_vault.nonce = leaf.nonce;
+        let user_points: UserPoints = zeroed(UserPoints::LEN);
+
+        let mut signer_account = TestAccount::new(signer, Pubkey::default(), vec![]);
+        signer_account.signer = true;
+        let mut accounts = vec![
+            signer_account,
+            TestAccount::program_account(pool_info_key, &pool_info, PoolInfo::LEN),
+            TestAccount::program_account(cnft_vault_key, &cnft_vault, CnftVaultAccount::LEN),
+            TestAccount::new(staker, Pubkey::default(), vec![]),
+            TestAccount::program_account(user_points_key, &user_points, UserPoints::LEN),
+            TestAccount::new(Pubkey::new_unique(), Pubkey::default(), vec![]),
+            TestAccount::new(merkle_tree, Pubkey::default(), vec![]),
+            TestAccount::executable(bubblegum::noop_program::ID),
+            TestAccount::executable(bubblegum::compression_program::ID),
+            TestAccount::executable(bubblegum::ID),
+            TestAccount::executable(system_program::ID),
+        ];
+        let infos: Vec<AccountInfo> = accounts.iter_mut().map(|account| account.info()).collect();
+        let mut bumps = std::collections::BTreeMap::new();
+        ForceUnstakeCompressed::try_accounts(&crate::ID, &mut &infos[..], &leaf.try_to_vec()?, &mut bumps)?;
+        Ok(())
+    }
+
+    #[test]
+    fn force_unstake_is_admin_only() {
+        let admin = Pubkey::new_unique();
+        let pauser = Pubkey::new_unique();
+        assert!(try_force_unstake_compressed(admin, pauser, admin).is_ok());
+
+        let err = try_force_unstake_compressed(admin, pauser, pauser).unwrap_err();
+        assert_eq!(error_code_number(err), anchor_lang::error::ErrorCode::ConstraintRaw as u32);
+    }
+
+    #[test]
+    fn snapshot_completes_with_sft_and_compressed_positions() {
+        let pool_info = Pubkey::new_unique();
+        let snapshot_key = Pubkey::new_unique();
+        let mut snapshot: Snapshot = zeroed(Snapshot::LEN);
+        snapshot.pool_info = pool_info;
+        snapshot.timestamp = 1_000;
+        snapshot.total_locked = 7;
+
+        let mut nft_vault: NftVaultAccount = zeroed(NftVaultAccount::LEN);
+        nft_vault.pool_info = pool_info;
+        nft_vault.stake_time = 900;
+        let mut sft_vault: SftVaultAccount = zeroed(SftVaultAccount::LEN);
+        sft_vault.pool_info = pool_info;
+        sft_vault.amount = 5;
+        sft_vault.stake_time = 1_000;
+        let mut cnft_vault: CnftVaultAccount = zeroed(CnftVaultAccount::LEN);
+        cnft_vault.pool_info = pool_info;
+        cnft_vault.asset_id = Pubkey::new_unique();
+        let mut late_sft_vault = sft_vault.clone();
+        late_sft_vault.stake_time = 1_001;
+        let mut other_pool_vault = nft_vault.clone();
+        other_pool_vault.pool_info = Pubkey::new_unique();
+
+        let mut accounts = [
+            TestAccount::program_account(Pubkey::new_unique(), &nft_vault, NftVaultAccount::LEN),
+            TestAccount::program_account(Pubkey::new_unique(), &sft_vault, SftVaultAccount::LEN),
+            TestAccount::program_account(Pubkey::new_unique(), &cnft_vault, CnftVaultAccount::LEN),
+            TestAccount::program_account(Pubkey::new_unique(), &late_sft_vault, SftVaultAccount::LEN),
+            TestAccount::program_account(Pubkey::new_unique(), &other_pool_vault, NftVaultAccount::LEN),
+            TestAccount::program_account(Pubkey::new_unique(), &snapshot, Snapshot::LEN),
+        ];
+        let infos: Vec<AccountInfo> = accounts.iter_mut().map(|account| account.info()).collect();
+
+        for info in infos[..3].iter() {
+            assert!(!snapshot.is_complete());
+            let entry = record_snapshot_position(info, &snapshot_key, &snapshot).unwrap();
+            snapshot.entry_count += 1;
+            snapshot.recorded_amount += entry.amount;
+        }
+        assert_eq!(snapshot.entry_count, 3);
+        assert!(snapshot.is_complete());
+
+        // Each position once, staked after begin, other pools and other accounts are rejected
+        for info in infos.iter() {
+            assert!(record_snapshot_position(info, &snapshot_key, &snapshot).is_err());
+        }
+        let sft_entry: SftVaultAccount = load_program_account(&infos[1]).unwrap();
+        assert_eq!(sft_entry.last_snapshot, snapshot_key);
+    }
+}
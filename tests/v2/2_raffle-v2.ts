@@ -0,0 +1,116 @@
+import * as anchor from "@project-serum/anchor";
+import NodeWallet from "@project-serum/anchor/dist/cjs/nodewallet";
+import { PublicKey } from "@solana/web3.js";
+import { assert } from "chai";
+import * as nftFinanceSDK from "../../ts/v2";
+import { AllInfo } from "../../ts/v2/poolInfos";
+import { IDL as nftStakingIDL } from "../../target/types/nft_staking";
+import { connection } from "../0_setting";
+import {
+  findNftVaultKey,
+  pickUnstakedNft,
+  sendIxs,
+  stakeNft,
+  unstakeNft,
+  waitForSlot,
+} from "./helpers";
+
+describe("nft staking v2: raffle", () => {
+  const wallet = NodeWallet.local();
+  const options = anchor.AnchorProvider.defaultOptions();
+  const provider = new anchor.AnchorProvider(connection, wallet, options);
+  anchor.setProvider(provider);
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    nftFinanceSDK.NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  let allInfo: AllInfo;
+  let nftMint: PublicKey;
+  let nftVault: PublicKey;
+  let raffle: PublicKey;
+  const index = Math.floor(Date.now() / 1000);
+
+  it("stake nft before commit", async () => {
+    ({ allInfo, nftMint } = await pickUnstakedNft(wallet, provider));
+    nftVault = await findNftVaultKey(allInfo.poolInfo.key, nftMint);
+    console.log("<Stake>", await stakeNft(allInfo, nftMint, wallet, provider));
+  });
+
+  it("commit raffle", async () => {
+    // stake_time has to be at or before commit time
+    await new Promise((resolve) => setTimeout(resolve, 1000));
+    const ix = await nftFinanceSDK.ix.commitRaffleIx(
+      wallet.publicKey,
+      index,
+      "stakeTime",
+      1,
+      10,
+      allInfo.poolInfo,
+      provider
+    );
+    console.log("<CommitRaffle>", await sendIxs([ix], wallet, provider));
+    raffle = await nftFinanceSDK.ix.findRaffleKey(allInfo.poolInfo.key, index);
+  });
+
+  it("enter raffle once", async () => {
+    const ix = await nftFinanceSDK.ix.enterRaffleIx(
+      wallet.publicKey,
+      raffle,
+      [nftVault],
+      provider
+    );
+    console.log("<EnterRaffle>", await sendIxs([ix], wallet, provider));
+
+    const { entries, totalWeight } =
+      await nftStakingProgram.account.raffle.fetch(raffle);
+    assert.equal(entries.length, 1);
+    assert.ok(entries[0].nftMint.equals(nftMint));
+    assert.ok(totalWeight.gtn(0));
+
+    let reentered = true;
+    try {
+      await sendIxs([ix], wallet, provider);
+    } catch (err) {
+      reentered = false;
+    }
+    assert.ok(!reentered, "the same NFT entered twice");
+  });
+
+  it("draw raffle after reveal slot", async () => {
+    const { revealSlot } = await nftStakingProgram.account.raffle.fetch(raffle);
+    await waitForSlot(revealSlot.toNumber(), provider);
+
+    const ix = await nftFinanceSDK.ix.drawRaffleIx(raffle, provider);
+    console.log("<DrawRaffle>", await sendIxs([ix], wallet, provider));
+
+    const { drawn, winners } = await nftStakingProgram.account.raffle.fetch(
+      raffle
+    );
+    assert.ok(drawn);
+    assert.equal(winners.length, 1);
+    assert.ok(winners[0].user.equals(wallet.publicKey));
+    assert.ok(winners[0].nftMint.equals(nftMint));
+  });
+
+  it("cancel rejects a drawn raffle", async () => {
+    const ix = await nftFinanceSDK.ix.cancelRaffleIx(
+      wallet.publicKey,
+      raffle,
+      allInfo.poolInfo,
+      provider
+    );
+    let cancelled = true;
+    try {
+      await sendIxs([ix], wallet, provider);
+    } catch (err) {
+      cancelled = false;
+    }
+    assert.ok(!cancelled, "drawn raffle was cancelled");
+  });
+
+  it("unstake nft", async () => {
+    console.log("<Unstake>", await unstakeNft(allInfo, nftMint, wallet, provider));
+  });
+});
@@ -0,0 +1,201 @@
+import * as anchor from "@project-serum/anchor";
+import NodeWallet from "@project-serum/anchor/dist/cjs/nodewallet";
+import { PublicKey } from "@solana/web3.js";
+import {
+  createMint,
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { assert } from "chai";
+import * as nftFinanceSDK from "../../ts/v2";
+import { AllInfo } from "../../ts/v2/poolInfos";
+import { findAssociatedTokenAddress } from "../../ts/v2/utils";
+import { IDL as nftStakingIDL } from "../../target/types/nft_staking";
+import { connection } from "../0_setting";
+import {
+  findNftVaultKey,
+  pickUnstakedNft,
+  sendIxs,
+  stakeNft,
+  unstakeNft,
+} from "./helpers";
+
+describe("nft staking v2: loan", () => {
+  const wallet = NodeWallet.local();
+  const options = anchor.AnchorProvider.defaultOptions();
+  const provider = new anchor.AnchorProvider(connection, wallet, options);
+  anchor.setProvider(provider);
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    nftFinanceSDK.NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const NFT_VALUE = 1_000;
+  const LTV_BPS = 5_000;
+  const INTEREST_BPS = 1_000;
+  const DURATION = 2; // seconds, short enough to seize within the test
+
+  let allInfo: AllInfo;
+  let nftMint: PublicKey;
+  let nftVault: PublicKey;
+  let loan: PublicKey;
+  let loanMint: PublicKey;
+  let userProveTokenAccount: PublicKey;
+
+  const tokenAmount = async (tokenAccount: PublicKey) =>
+    Number((await getAccount(connection, tokenAccount)).amount);
+
+  const borrow = async () => {
+    const ix = await nftFinanceSDK.ix.borrowIx(
+      wallet.publicKey,
+      nftVault,
+      (NFT_VALUE * LTV_BPS) / 10_000,
+      allInfo.poolInfo,
+      provider
+    );
+    return await sendIxs([ix], wallet, provider);
+  };
+
+  it("stake nft and configure loans", async () => {
+    ({ allInfo, nftMint } = await pickUnstakedNft(wallet, provider));
+    nftVault = await findNftVaultKey(allInfo.poolInfo.key, nftMint);
+    loan = await nftFinanceSDK.ix.findLoanKey(nftVault);
+    userProveTokenAccount = await findAssociatedTokenAddress(
+      wallet.publicKey,
+      allInfo.poolInfo.proveTokenMint
+    );
+    console.log("<Stake>", await stakeNft(allInfo, nftMint, wallet, provider));
+
+    const loanConfig = await nftFinanceSDK.ix.findLoanConfigKey(
+      allInfo.poolInfo.key
+    );
+    if ((await connection.getAccountInfo(loanConfig)) == null) {
+      loanMint = await createMint(
+        connection,
+        wallet.payer,
+        wallet.publicKey,
+        null,
+        0
+      );
+      const ix = await nftFinanceSDK.ix.initiateLoanConfigIx(
+        wallet.publicKey,
+        loanMint,
+        NFT_VALUE,
+        LTV_BPS,
+        INTEREST_BPS,
+        DURATION,
+        allInfo.poolInfo,
+        provider
+      );
+      console.log("<InitLoanConfig>", await sendIxs([ix], wallet, provider));
+    } else {
+      ({ loanMint } = await nftStakingProgram.account.loanConfig.fetch(
+        loanConfig
+      ));
+      const ix = await nftFinanceSDK.ix.updateLoanTermsIx(
+        wallet.publicKey,
+        NFT_VALUE,
+        LTV_BPS,
+        INTEREST_BPS,
+        DURATION,
+        allInfo.poolInfo,
+        provider
+      );
+      console.log("<UpdateLoanTerms>", await sendIxs([ix], wallet, provider));
+    }
+
+    // wallet lends and repays from the same ATA
+    const walletLoanAccount = await getOrCreateAssociatedTokenAccount(
+      connection,
+      wallet.payer,
+      loanMint,
+      wallet.publicKey
+    );
+    await mintTo(
+      connection,
+      wallet.payer,
+      loanMint,
+      walletLoanAccount.address,
+      wallet.publicKey,
+      NFT_VALUE * 2
+    );
+    const ix = await nftFinanceSDK.ix.fundLoanVaultIx(
+      wallet.publicKey,
+      NFT_VALUE,
+      allInfo.poolInfo,
+      provider
+    );
+    console.log("<FundLoanVault>", await sendIxs([ix], wallet, provider));
+  });
+
+  it("borrow escrows the prove token", async () => {
+    const before = await tokenAmount(userProveTokenAccount);
+    console.log("<Borrow>", await borrow());
+
+    const escrow = await findAssociatedTokenAddress(
+      loan,
+      allInfo.poolInfo.proveTokenMint
+    );
+    assert.equal(await tokenAmount(userProveTokenAccount), before - 1);
+    assert.equal(await tokenAmount(escrow), 1);
+
+    let unstaked = true;
+    try {
+      await unstakeNft(allInfo, nftMint, wallet, provider);
+    } catch (err) {
+      unstaked = false;
+    }
+    assert.ok(!unstaked, "unstaked with an open loan");
+  });
+
+  it("repay returns the prove token", async () => {
+    const before = await tokenAmount(userProveTokenAccount);
+    const ix = await nftFinanceSDK.ix.repayLoanIx(
+      wallet.publicKey,
+      nftVault,
+      provider
+    );
+    console.log("<RepayLoan>", await sendIxs([ix], wallet, provider));
+
+    assert.equal(await tokenAmount(userProveTokenAccount), before + 1);
+    assert.equal(await connection.getAccountInfo(loan), null);
+    const { loan: openLoan } =
+      await nftStakingProgram.account.nftVaultAccount.fetch(nftVault);
+    assert.ok(openLoan.equals(PublicKey.default));
+  });
+
+  it("seize sends the prove token back to the vault", async () => {
+    console.log("<Borrow>", await borrow());
+    await new Promise((resolve) => setTimeout(resolve, (DURATION + 2) * 1000));
+
+    const vaultBefore = await tokenAmount(allInfo.poolInfo.proveTokenVault);
+    const ix = await nftFinanceSDK.ix.seizeLoanIx(
+      wallet.publicKey,
+      nftVault,
+      allInfo.poolInfo,
+      provider
+    );
+    console.log("<SeizeLoan>", await sendIxs([ix], wallet, provider));
+
+    assert.equal(
+      await tokenAmount(allInfo.poolInfo.proveTokenVault),
+      vaultBefore + 1
+    );
+    assert.equal(await connection.getAccountInfo(nftVault), null);
+    const walletNftAccount = await findAssociatedTokenAddress(
+      wallet.publicKey,
+      nftMint
+    );
+    assert.equal(await tokenAmount(walletNftAccount), 1);
+
+    const auditIx = await nftFinanceSDK.ix.auditPoolInfoIx(
+      allInfo.poolInfo,
+      [],
+      [],
+      provider
+    );
+    console.log("<AuditPool>", await sendIxs([auditIx], wallet, provider));
+  });
+});
@@ -0,0 +1,215 @@
+import * as anchor from "@project-serum/anchor";
+import NodeWallet from "@project-serum/anchor/dist/cjs/nodewallet";
+import { Keypair, LAMPORTS_PER_SOL, PublicKey } from "@solana/web3.js";
+import {
+  getAccount,
+  getOrCreateAssociatedTokenAccount,
+  transfer,
+} from "@solana/spl-token";
+import { assert } from "chai";
+import * as nftFinanceSDK from "../../ts/v2";
+import { AllInfo } from "../../ts/v2/poolInfos";
+import { findAssociatedTokenAddress } from "../../ts/v2/utils";
+import { IDL as nftStakingIDL } from "../../target/types/nft_staking";
+import { connection } from "../0_setting";
+import {
+  findNftVaultKey,
+  pickUnstakedNft,
+  sendIxs,
+  stakeNft,
+  unstakeNft,
+} from "./helpers";
+
+describe("nft staking v2: stake and unstake", () => {
+  const wallet = NodeWallet.local();
+  const options = anchor.AnchorProvider.defaultOptions();
+  const provider = new anchor.AnchorProvider(connection, wallet, options);
+  anchor.setProvider(provider);
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    nftFinanceSDK.NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  let allInfo: AllInfo;
+  let nftMint: PublicKey;
+  let nftVault: PublicKey;
+  let userNftAccount: PublicKey;
+  let userProveTokenAccount: PublicKey;
+
+  const tokenAmount = async (tokenAccount: PublicKey) =>
+    Number((await getAccount(connection, tokenAccount)).amount);
+
+  it("stake nft", async () => {
+    ({ allInfo, nftMint } = await pickUnstakedNft(wallet, provider));
+    nftVault = await findNftVaultKey(allInfo.poolInfo.key, nftMint);
+    userNftAccount = await findAssociatedTokenAddress(wallet.publicKey, nftMint);
+    userProveTokenAccount = await findAssociatedTokenAddress(
+      wallet.publicKey,
+      allInfo.poolInfo.proveTokenMint
+    );
+    const { totalLocked } = await nftStakingProgram.account.poolInfo.fetch(
+      allInfo.poolInfo.key
+    );
+    const proveTokensBefore = await tokenAmount(userProveTokenAccount).catch(
+      () => 0
+    );
+
+    console.log("<Stake>", await stakeNft(allInfo, nftMint, wallet, provider));
+
+    assert.equal(await tokenAmount(userNftAccount), 0);
+    assert.equal(
+      await tokenAmount(userProveTokenAccount),
+      proveTokensBefore + 1
+    );
+    const nftVaultAccount = await nftStakingProgram.account.nftVaultAccount.fetch(
+      nftVault
+    );
+    assert.ok(nftVaultAccount.user.equals(wallet.publicKey));
+    assert.ok(nftVaultAccount.nftMint.equals(nftMint));
+    const poolInfo = await nftStakingProgram.account.poolInfo.fetch(
+      allInfo.poolInfo.key
+    );
+    assert.ok(poolInfo.totalLocked.eq(totalLocked.addn(1)));
+  });
+
+  it("unstake rejects a prove token holder who did not stake", async () => {
+    // other holds the prove token, only the staker recorded on the vault redeems it
+    const other = Keypair.generate();
+    await connection.confirmTransaction(
+      await connection.requestAirdrop(other.publicKey, LAMPORTS_PER_SOL)
+    );
+    const otherProveTokenAccount = await getOrCreateAssociatedTokenAccount(
+      connection,
+      wallet.payer,
+      allInfo.poolInfo.proveTokenMint,
+      other.publicKey
+    );
+    await transfer(
+      connection,
+      wallet.payer,
+      userProveTokenAccount,
+      otherProveTokenAccount.address,
+      wallet.publicKey,
+      1
+    );
+
+    const ix = await nftFinanceSDK.ix.unstakeIx(
+      allInfo.poolInfo,
+      other.publicKey,
+      nftMint,
+      provider
+    );
+    let unstaked = true;
+    try {
+      await sendIxs([ix], new NodeWallet(other), provider);
+    } catch (err) {
+      unstaked = false;
+    }
+    assert.ok(!unstaked, "non-owner unstaked the NFT");
+    assert.notEqual(await connection.getAccountInfo(nftVault), null);
+
+    await transfer(
+      connection,
+      wallet.payer,
+      otherProveTokenAccount.address,
+      userProveTokenAccount,
+      other,
+      1
+    );
+  });
+
+  it("unstake nft", async () => {
+    const proveTokensBefore = await tokenAmount(userProveTokenAccount);
+    console.log("<Unstake>", await unstakeNft(allInfo, nftMint, wallet, provider));
+
+    assert.equal(await tokenAmount(userNftAccount), 1);
+    assert.equal(
+      await tokenAmount(userProveTokenAccount),
+      proveTokensBefore - 1
+    );
+    assert.equal(await connection.getAccountInfo(nftVault), null);
+  });
+
+  it("force unstake rejects the pauser", async () => {
+    console.log("<Stake>", await stakeNft(allInfo, nftMint, wallet, provider));
+    const pauser = Keypair.generate();
+    await connection.confirmTransaction(
+      await connection.requestAirdrop(pauser.publicKey, LAMPORTS_PER_SOL)
+    );
+    const { pauser: pauserBefore } =
+      await nftStakingProgram.account.poolInfo.fetch(allInfo.poolInfo.key);
+    await sendIxs(
+      [
+        await nftFinanceSDK.ix.setPoolInfoRoleIx(
+          "pauser",
+          pauser.publicKey,
+          allInfo.poolInfo,
+          provider
+        ),
+      ],
+      wallet,
+      provider
+    );
+
+    const ix = await nftFinanceSDK.ix.forceUnstakeIx(
+      pauser.publicKey,
+      allInfo.poolInfo,
+      nftMint,
+      provider
+    );
+    let unstaked = true;
+    try {
+      await sendIxs([ix], new NodeWallet(pauser), provider);
+    } catch (err) {
+      unstaked = false;
+    }
+    assert.ok(!unstaked, "pauser force unstaked the NFT");
+    assert.notEqual(await connection.getAccountInfo(nftVault), null);
+
+    await sendIxs(
+      [
+        await nftFinanceSDK.ix.setPoolInfoRoleIx(
+          "pauser",
+          pauserBefore,
+          allInfo.poolInfo,
+          provider
+        ),
+      ],
+      wallet,
+      provider
+    );
+  });
+
+  it("force unstake returns the nft and orphans the prove token", async () => {
+    const proveTokensBefore = await tokenAmount(userProveTokenAccount);
+    const { totalLocked, orphanedProveTokens } =
+      await nftStakingProgram.account.poolInfo.fetch(allInfo.poolInfo.key);
+
+    const ix = await nftFinanceSDK.ix.forceUnstakeIx(
+      wallet.publicKey,
+      allInfo.poolInfo,
+      nftMint,
+      provider
+    );
+    console.log("<ForceUnstake>", await sendIxs([ix], wallet, provider));
+
+    assert.equal(await tokenAmount(userNftAccount), 1);
+    assert.equal(await tokenAmount(userProveTokenAccount), proveTokensBefore);
+    assert.equal(await connection.getAccountInfo(nftVault), null);
+    const poolInfo = await nftStakingProgram.account.poolInfo.fetch(
+      allInfo.poolInfo.key
+    );
+    assert.ok(poolInfo.totalLocked.eq(totalLocked.subn(1)));
+    assert.ok(poolInfo.orphanedProveTokens.eq(orphanedProveTokens.addn(1)));
+
+    // prove token supply still adds up with the orphaned one counted
+    const auditIx = await nftFinanceSDK.ix.auditPoolInfoIx(
+      allInfo.poolInfo,
+      [],
+      [],
+      provider
+    );
+    console.log("<AuditPool>", await sendIxs([auditIx], wallet, provider));
+  });
+});
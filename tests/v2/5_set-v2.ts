@@ -0,0 +1,108 @@
+import * as anchor from "@project-serum/anchor";
+import NodeWallet from "@project-serum/anchor/dist/cjs/nodewallet";
+import { Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import { assert } from "chai";
+import * as nftFinanceSDK from "../../ts/v2";
+import { AllInfo } from "../../ts/v2/poolInfos";
+import { IDL as nftStakingIDL } from "../../target/types/nft_staking";
+import { connection } from "../0_setting";
+import { sendIxs } from "./helpers";
+
+describe("nft staking v2: sets", () => {
+  const wallet = NodeWallet.local();
+  const options = anchor.AnchorProvider.defaultOptions();
+  const provider = new anchor.AnchorProvider(connection, wallet, options);
+  anchor.setProvider(provider);
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    nftFinanceSDK.NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  let allInfos: AllInfo[];
+  const nonce = Math.floor(Date.now() / 1000);
+
+  const rejected = async (
+    ixPromise: Promise<anchor.web3.TransactionInstruction>,
+    signer: NodeWallet
+  ) => {
+    try {
+      await sendIxs([await ixPromise], signer, provider);
+    } catch (err) {
+      return true;
+    }
+    return false;
+  };
+
+  it("pick two pools of the wallet", async () => {
+    allInfos = (await nftFinanceSDK.fetchAll(provider)).filter((allInfo) =>
+      allInfo.poolInfo.admin.equals(wallet.publicKey)
+    );
+    assert.isAtLeast(allInfos.length, 2, "a set needs two pools");
+  });
+
+  it("initialize set rejects a signer without the config manager role", async () => {
+    const other = Keypair.generate();
+    await connection.confirmTransaction(
+      await connection.requestAirdrop(other.publicKey, LAMPORTS_PER_SOL)
+    );
+
+    const ix = nftFinanceSDK.ix.initiateSetInfoIx(
+      other.publicKey,
+      allInfos[0].poolInfo,
+      nonce,
+      [allInfos[0].rarityInfo.key, allInfos[1].rarityInfo.key],
+      2_500,
+      provider
+    );
+    assert.ok(
+      await rejected(ix, new NodeWallet(other)),
+      "set created without the config manager role"
+    );
+  });
+
+  it("initialize set rejects a bonus above the cap", async () => {
+    const ix = nftFinanceSDK.ix.initiateSetInfoIx(
+      wallet.publicKey,
+      allInfos[0].poolInfo,
+      nonce,
+      [allInfos[0].rarityInfo.key, allInfos[1].rarityInfo.key],
+      10_001,
+      provider
+    );
+    assert.ok(await rejected(ix, wallet), "set bonus above 100% accepted");
+  });
+
+  it("initialize set rejects a pool that is not in the set", async () => {
+    if (allInfos.length < 3) {
+      return;
+    }
+    const ix = nftFinanceSDK.ix.initiateSetInfoIx(
+      wallet.publicKey,
+      allInfos[2].poolInfo,
+      nonce,
+      [allInfos[0].rarityInfo.key, allInfos[1].rarityInfo.key],
+      2_500,
+      provider
+    );
+    assert.ok(await rejected(ix, wallet), "set bound to a pool outside it");
+  });
+
+  it("initialize set", async () => {
+    const ix = await nftFinanceSDK.ix.initiateSetInfoIx(
+      wallet.publicKey,
+      allInfos[0].poolInfo,
+      nonce,
+      [allInfos[0].rarityInfo.key, allInfos[1].rarityInfo.key],
+      2_500,
+      provider
+    );
+    console.log("<InitializeSet>", await sendIxs([ix], wallet, provider));
+
+    const setInfo = await nftStakingProgram.account.setInfo.fetch(
+      await nftFinanceSDK.ix.findSetInfoKey(allInfos[0].poolInfo.key, nonce)
+    );
+    assert.ok(setInfo.poolInfo.equals(allInfos[0].poolInfo.key));
+    assert.equal(setInfo.bonusBps.toNumber(), 2_500);
+  });
+});
@@ -0,0 +1,93 @@
+import * as anchor from "@project-serum/anchor";
+import NodeWallet from "@project-serum/anchor/dist/cjs/nodewallet";
+import { PublicKey, Transaction } from "@solana/web3.js";
+import * as fs from "fs";
+import { findAssociatedTokenAddress } from "../../ts/v2/utils";
+import * as nftFinanceSDK from "../../ts/v2";
+import { AllInfo } from "../../ts/v2/poolInfos";
+import { MINT_LIST_PATH } from "../0_setting";
+
+const NFT_VAULT_SEED = "nft_vault";
+
+export function readNftMintList() {
+  const data: string[] = JSON.parse(fs.readFileSync(MINT_LIST_PATH, "utf-8"));
+  return data.map((element) => new PublicKey(element));
+}
+
+export async function findNftVaultKey(poolInfoKey: PublicKey, nftMint: PublicKey) {
+  return (
+    await PublicKey.findProgramAddress(
+      [nftMint.toBuffer(), poolInfoKey.toBuffer(), Buffer.from(NFT_VAULT_SEED)],
+      nftFinanceSDK.NFT_STAKING_PROGRAM_ID
+    )
+  )[0];
+}
+
+export async function sendIxs(
+  ixs: anchor.web3.TransactionInstruction[],
+  wallet: NodeWallet,
+  provider: anchor.AnchorProvider
+) {
+  const txn = new Transaction();
+  txn.add(...ixs);
+  return await provider.sendAndConfirm(txn, [wallet.payer]);
+}
+
+// first NFT of the mint list held by wallet, with the pool it stakes into
+export async function pickUnstakedNft(
+  wallet: NodeWallet,
+  provider: anchor.AnchorProvider
+) {
+  const allInfos = await nftFinanceSDK.fetchAll(provider);
+  const pairs = nftFinanceSDK.infoAndNftMatcher(allInfos, readNftMintList());
+  for (let pair of pairs) {
+    const nftVault = await findNftVaultKey(
+      pair.allInfo.poolInfo.key,
+      pair.nftMint
+    );
+    if ((await provider.connection.getAccountInfo(nftVault)) == null) {
+      return pair;
+    }
+  }
+  throw new Error("no unstaked NFT left in the mint list");
+}
+
+export async function stakeNft(
+  allInfo: AllInfo,
+  nftMint: PublicKey,
+  wallet: NodeWallet,
+  provider: anchor.AnchorProvider
+) {
+  const userNftAccount = await findAssociatedTokenAddress(
+    wallet.publicKey,
+    nftMint
+  );
+  const ixs = await nftFinanceSDK.ix.stakeIx(
+    allInfo.poolInfo,
+    wallet.publicKey,
+    userNftAccount,
+    provider
+  );
+  return await sendIxs(ixs, wallet, provider);
+}
+
+export async function unstakeNft(
+  allInfo: AllInfo,
+  nftMint: PublicKey,
+  wallet: NodeWallet,
+  provider: anchor.AnchorProvider
+) {
+  const ix = await nftFinanceSDK.ix.unstakeIx(
+    allInfo.poolInfo,
+    wallet.publicKey,
+    nftMint,
+    provider
+  );
+  return await sendIxs([ix], wallet, provider);
+}
+
+export async function waitForSlot(slot: number, provider: anchor.AnchorProvider) {
+  while ((await provider.connection.getSlot()) <= slot) {
+    await new Promise((resolve) => setTimeout(resolve, 400));
+  }
+}
@@ -6,6 +6,3 @@ export const NFT_STAKING_PROGRAM_ID = new PublicKey(
 export const NFT_RARITY_PROGRAM_ID = new PublicKey(
   "NFTRnyHzbhLx7XWc9PBKJyP68zFsjwNbUtmYTHc1ow3"
 );
-export const ATA_INIT_PROGRAM_ID = new PublicKey(
-  "9tiP8yZcekzfGzSBmp7n9LaDHRjxP2w7wJj8tpPJtfG"
-);
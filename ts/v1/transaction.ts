@@ -5,7 +5,7 @@ import { hex } from "@project-serum/anchor/dist/cjs/utils/bytes";
 import { hash } from "@project-serum/anchor/dist/cjs/utils/sha256";
 import { IDL as nftStakingIDL } from "../../target/types/nft_staking";
 import { IDL as rarityIDL } from "../../target/types/nft_rarity";
-import { findAssociatedTokenAddress, createATAWithoutCheckIx } from "./utils";
+import { findAssociatedTokenAddress, createATAIdempotentIx } from "./utils";
 import { NFT_STAKING_PROGRAM_ID, NFT_RARITY_PROGRAM_ID } from "./ids";
 
 const PROVE_TOKEN_VAULT_SEED = "prove_token_vault";
@@ -248,7 +248,7 @@ export async function nftStakingInit(
     proveTokenMint
   );
 
-  const createAtaIx = await createATAWithoutCheckIx(
+  const createAtaIx = await createATAIdempotentIx(
     proveTokenAuthority,
     proveTokenMint,
     admin
@@ -294,7 +294,8 @@ export async function updatePoolInfosAdmin(
 
   const allTx: Transaction[] = [];
   for (let poolInfo of poolInfos) {
-    const txUpdatePoolInfoAdmin = NftStakingProgram.transaction.updateAdmin({
+    // new admin has to send acceptAdmin to complete the handover
+    const txUpdatePoolInfoAdmin = NftStakingProgram.transaction.proposeAdmin({
       accounts: {
         admin,
         newAdmin,
@@ -4,7 +4,6 @@ import {
   TransactionInstruction,
   SystemProgram,
   Connection,
-  SYSVAR_RENT_PUBKEY,
   Keypair,
   sendAndConfirmTransaction,
 } from "@solana/web3.js";
@@ -12,7 +11,6 @@ import {
   TOKEN_PROGRAM_ID,
   ASSOCIATED_TOKEN_PROGRAM_ID,
 } from "@solana/spl-token";
-import { ATA_INIT_PROGRAM_ID } from "./ids";
 import * as fs from "fs";
 
 export class Category {
@@ -66,7 +64,8 @@ export async function findAssociatedTokenAddress(
   )[0];
 }
 
-export async function createATAWithoutCheckIx(
+// CreateIdempotent of the associated token account program, a no-op if the ATA exists
+export async function createATAIdempotentIx(
   wallet: PublicKey,
   mint: PublicKey,
   payer?: PublicKey
@@ -79,16 +78,15 @@ export async function createATAWithoutCheckIx(
   const keys = [
     { pubkey: payer, isSigner: true, isWritable: true },
     { pubkey: ATA, isSigner: false, isWritable: true },
-    { pubkey: wallet, isSigner: false, isWritable: true },
+    { pubkey: wallet, isSigner: false, isWritable: false },
     { pubkey: mint, isSigner: false, isWritable: false },
     { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
     { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
-    { pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false },
-    { pubkey: ASSOCIATED_TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
   ];
   return new TransactionInstruction({
     keys,
-    programId: ATA_INIT_PROGRAM_ID,
+    programId: ASSOCIATED_TOKEN_PROGRAM_ID,
+    data: Buffer.from([1]),
   });
 }
 
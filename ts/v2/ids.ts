@@ -6,3 +6,12 @@ export const NFT_STAKING_PROGRAM_ID = new PublicKey(
 export const NFT_RARITY_PROGRAM_ID = new PublicKey(
   "NFTRnyHzbhLx7XWc9PBKJyP68zFsjwNbUtmYTHc1ow3"
 );
+export const BUBBLEGUM_PROGRAM_ID = new PublicKey(
+  "BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY"
+);
+export const ACCOUNT_COMPRESSION_PROGRAM_ID = new PublicKey(
+  "cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK"
+);
+export const NOOP_PROGRAM_ID = new PublicKey(
+  "noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV"
+);
@@ -1,13 +1,59 @@
 import * as anchor from "@project-serum/anchor";
 import { PublicKey } from "@solana/web3.js";
-import { getAccount, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import {
+  getAccount,
+  createAssociatedTokenAccountInstruction,
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+} from "@solana/spl-token";
 import { IDL as nftStakingIDL } from "../../target/types/nft_staking";
 import { IDL as nftRarityIDL } from "../../target/types/nft_rarity";
-import { findAssociatedTokenAddress, createATAWithoutCheckIx } from "./utils";
-import { NFT_STAKING_PROGRAM_ID, NFT_RARITY_PROGRAM_ID } from "./ids";
+import { findAssociatedTokenAddress, createATAIdempotentIx } from "./utils";
+import {
+  NFT_STAKING_PROGRAM_ID,
+  NFT_RARITY_PROGRAM_ID,
+  BUBBLEGUM_PROGRAM_ID,
+  ACCOUNT_COMPRESSION_PROGRAM_ID,
+  NOOP_PROGRAM_ID,
+} from "./ids";
 import { PoolInfo, RarityInfo } from "./poolInfos";
 
 const NFT_VAULT_SEED = "nft_vault";
+const SET_INFO_SEED = "set_info";
+const SET_BONUS_SEED = "set_bonus";
+const USER_POINTS_SEED = "user_points";
+const LEADERBOARD_SEED = "leaderboard";
+const DISTRIBUTION_SEED = "distribution";
+const RAFFLE_SEED = "raffle";
+const LOAN_CONFIG_SEED = "loan_config";
+const LOAN_SEED = "loan";
+const CNFT_VAULT_SEED = "cnft_vault";
+const SFT_VAULT_SEED = "sft_vault";
+const SNAPSHOT_SEED = "snapshot";
+const SNAPSHOT_PAGE_SEED = "snapshot_page";
+const VESTING_ESCROW_SEED = "vesting_escrow";
+const BOOSTER_CONFIG_SEED = "booster_config";
+const BOOSTER_SEED = "booster";
+const GUILD_SEED = "guild";
+const GUILD_POSITION_SEED = "guild_position";
+
+export type PoolRole = "pauser" | "rewardManager" | "configManager";
+export type RaffleWeighting = "stakeTime" | "pointsWeight";
+export type BoosterKind = "token" | "nft";
+
+export interface GuildShare {
+  member: PublicKey;
+  shares: number;
+}
+
+// compressed NFT leaf as returned by the asset proof of a DAS API
+export interface CompressedLeaf {
+  root: Buffer;
+  dataHash: Buffer;
+  creatorHash: Buffer;
+  nonce: number;
+  index: number;
+}
 
 const MINT_LIST_LIMIT = 512; // stack has 4kb limit & heap has 32kb limit
 const MINT_LIST_PER_BATCH = 28; // 1232 bytes transaction limit
@@ -142,7 +188,7 @@ export async function initiatePoolInfoIx(
     poolInfo.proveTokenMint
   );
 
-  // const createAtaIx = await createATAWithoutCheckIx(
+  // const createAtaIx = await createATAIdempotentIx(
   //   poolInfo.proveTokenAuthority,
   //   poolInfo.proveTokenMint,
   //   poolInfo.admin
@@ -172,7 +218,7 @@ export async function initiatePoolInfoIx(
   return initIx;
 }
 
-export async function updatePoolInfoAdminIx(
+export async function proposePoolInfoAdminIx(
   newAdmin: PublicKey,
   poolInfo: PoolInfo,
   provider: anchor.AnchorProvider
@@ -183,8 +229,8 @@ export async function updatePoolInfoAdminIx(
     provider
   );
 
-  const updatePoolInfoAdminIx = await nftStakingProgram.methods
-    .updateAdmin()
+  const proposePoolInfoAdminIx = await nftStakingProgram.methods
+    .proposeAdmin()
     .accounts({
       admin: poolInfo.admin,
       newAdmin,
@@ -192,13 +238,12 @@ export async function updatePoolInfoAdminIx(
     })
     .instruction();
 
-  return updatePoolInfoAdminIx;
+  return proposePoolInfoAdminIx;
 }
 
-export async function stakeIx(
+export async function acceptPoolInfoAdminIx(
+  newAdmin: PublicKey,
   poolInfo: PoolInfo,
-  user: PublicKey,
-  userNftAccount: PublicKey,
   provider: anchor.AnchorProvider
 ) {
   const nftStakingProgram = new anchor.Program(
@@ -207,70 +252,250 @@ export async function stakeIx(
     provider
   );
 
-  // create user prove token ATA
-  const userProveTokenAccount = await findAssociatedTokenAddress(
-    user,
-    poolInfo.proveTokenMint
+  const acceptPoolInfoAdminIx = await nftStakingProgram.methods
+    .acceptAdmin()
+    .accounts({
+      newAdmin,
+      poolInfo: poolInfo.key,
+    })
+    .instruction();
+
+  return acceptPoolInfoAdminIx;
+}
+
+export async function cancelPoolInfoAdminProposalIx(
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
   );
-  const createProveTokenAtaIx = await createATAWithoutCheckIx(
-    user,
-    poolInfo.proveTokenMint
+
+  const cancelPoolInfoAdminProposalIx = await nftStakingProgram.methods
+    .cancelAdminProposal()
+    .accounts({
+      admin: poolInfo.admin,
+      poolInfo: poolInfo.key,
+    })
+    .instruction();
+
+  return cancelPoolInfoAdminProposalIx;
+}
+
+export async function setPoolInfoRoleIx(
+  role: PoolRole,
+  authority: PublicKey,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
   );
 
-  const IxArr: anchor.web3.TransactionInstruction[] = [];
-  IxArr.push(createProveTokenAtaIx);
+  const setPoolInfoRoleIx = await nftStakingProgram.methods
+    .setRole({ [role]: {} } as any, authority)
+    .accounts({
+      admin: poolInfo.admin,
+      poolInfo: poolInfo.key,
+    })
+    .instruction();
 
-  const nftAccount = await getAccount(provider.connection, userNftAccount);
-  const nftMint = nftAccount.mint;
+  return setPoolInfoRoleIx;
+}
 
-  const nftVaultAccount = (
+export async function setPoolInfoPausedIx(
+  authority: PublicKey, // admin or pauser
+  paused: boolean,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const setPoolInfoPausedIx = await nftStakingProgram.methods
+    .setPaused(paused)
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+    })
+    .instruction();
+
+  return setPoolInfoPausedIx;
+}
+
+export async function updatePoolInfoGateIx(
+  authority: PublicKey, // admin or config manager
+  gateMint: PublicKey,
+  gateMinBalance: number,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const updatePoolInfoGateIx = await nftStakingProgram.methods
+    .updateGate(gateMint, new anchor.BN(gateMinBalance))
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+    })
+    .instruction();
+
+  return updatePoolInfoGateIx;
+}
+
+export async function findUserPointsKey(poolInfoKey: PublicKey, user: PublicKey) {
+  return (
     await PublicKey.findProgramAddress(
-      [
-        nftMint.toBuffer(),
-        poolInfo.key.toBuffer(),
-        Buffer.from(NFT_VAULT_SEED),
-      ],
+      [poolInfoKey.toBuffer(), user.toBuffer(), Buffer.from(USER_POINTS_SEED)],
       NFT_STAKING_PROGRAM_ID
     )
   )[0];
+}
 
-  // create nft vault ATA
-  let nftVaultAta = await findAssociatedTokenAddress(nftVaultAccount, nftMint);
-  const createAtaIx = await createATAWithoutCheckIx(
-    nftVaultAccount,
-    nftMint,
-    user
+export async function updatePoolInfoPointsRateIx(
+  authority: PublicKey, // admin or config manager
+  pointsPerSecond: number,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
   );
 
-  IxArr.push(createAtaIx);
+  const updatePointsRateIx = await nftStakingProgram.methods
+    .updatePointsRate(new anchor.BN(pointsPerSecond))
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+    })
+    .instruction();
 
-  const stakeIx = await nftStakingProgram.methods
-    .stake()
+  return updatePointsRateIx;
+}
+
+export async function updatePoolInfoRateCurveIx(
+  authority: PublicKey, // admin or config manager
+  curveEnabled: boolean,
+  curveMinPointsPerSecond: number,
+  curveMaxPointsPerSecond: number,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const updateRateCurveIx = await nftStakingProgram.methods
+    .updateRateCurve(
+      curveEnabled,
+      new anchor.BN(curveMinPointsPerSecond),
+      new anchor.BN(curveMaxPointsPerSecond)
+    )
     .accounts({
-      user,
+      authority,
       poolInfo: poolInfo.key,
-      nftMint,
-      userNftAccount,
-      nftVaultAta,
-      userProveTokenAccount,
-      nftVaultAccount,
-      proveTokenMint: poolInfo.proveTokenMint,
       rarityInfo: poolInfo.rarityInfo,
-      proveTokenAuthority: poolInfo.proveTokenAuthority,
-      proveTokenVault: poolInfo.proveTokenVault,
+    })
+    .instruction();
+
+  return updateRateCurveIx;
+}
+
+export async function spendPointsIx(
+  authority: PublicKey, // admin or reward manager
+  user: PublicKey,
+  amount: number,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const spendPointsIx = await nftStakingProgram.methods
+    .spendPoints(new anchor.BN(amount))
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+      userPoints: await findUserPointsKey(poolInfo.key, user),
+    })
+    .instruction();
+
+  return spendPointsIx;
+}
+
+export async function updatePoolInfoReferralIx(
+  authority: PublicKey, // admin or config manager
+  referralShareBps: number,
+  referralCap: number,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const updateReferralIx = await nftStakingProgram.methods
+    .updateReferral(new anchor.BN(referralShareBps), new anchor.BN(referralCap))
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+    })
+    .instruction();
+
+  return updateReferralIx;
+}
+
+export async function claimReferralIx(
+  referrer: PublicKey,
+  user: PublicKey, // referred user
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const userPoints = await findUserPointsKey(poolInfo.key, user);
+
+  const claimReferralIx = await nftStakingProgram.methods
+    .claimReferral()
+    .accounts({
+      referrer,
+      poolInfo: poolInfo.key,
+      userPoints,
+      referrerPoints: await findUserPointsKey(poolInfo.key, referrer),
       systemProgram: anchor.web3.SystemProgram.programId,
-      tokenProgram: TOKEN_PROGRAM_ID,
     })
     .instruction();
-  IxArr.push(stakeIx);
 
-  return IxArr;
+  return claimReferralIx;
 }
 
-export async function unstakeIx(
+export async function migratePoolInfoIx(
   poolInfo: PoolInfo,
-  user: PublicKey,
-  nftMint: PublicKey,
+  nftVaultAccounts: PublicKey[], // NftVaultAccounts of the pool to migrate along, unstake migrates the rest
   provider: anchor.AnchorProvider
 ) {
   const nftStakingProgram = new anchor.Program(
@@ -279,10 +504,77 @@ export async function unstakeIx(
     provider
   );
 
-  // create user prove token ATA
-  const userProveTokenAccount = await findAssociatedTokenAddress(
-    user,
-    poolInfo.proveTokenMint
+  const migratePoolIx = await nftStakingProgram.methods
+    .migratePool()
+    .accounts({
+      admin: poolInfo.admin,
+      poolInfo: poolInfo.key,
+      rarityInfo: poolInfo.rarityInfo,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .remainingAccounts(
+      nftVaultAccounts.map((nftVaultAccount) => ({
+        pubkey: nftVaultAccount,
+        isSigner: false,
+        isWritable: true,
+      }))
+    )
+    .instruction();
+
+  return migratePoolIx;
+}
+
+export async function auditPoolInfoIx(
+  poolInfo: PoolInfo,
+  nftVaultAccounts: PublicKey[] = [], // optional NftVaultAccounts to audit along
+  nftMints: PublicKey[] = [],
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const remainingAccounts: anchor.web3.AccountMeta[] = [];
+  for (let [index, nftVaultAccount] of nftVaultAccounts.entries()) {
+    remainingAccounts.push({
+      pubkey: nftVaultAccount,
+      isSigner: false,
+      isWritable: false,
+    });
+    remainingAccounts.push({
+      pubkey: await findAssociatedTokenAddress(
+        nftVaultAccount,
+        nftMints[index]
+      ),
+      isSigner: false,
+      isWritable: false,
+    });
+  }
+
+  const auditPoolIx = await nftStakingProgram.methods
+    .auditPool()
+    .accounts({
+      poolInfo: poolInfo.key,
+      rarityInfo: poolInfo.rarityInfo,
+      proveTokenVault: poolInfo.proveTokenVault,
+    })
+    .remainingAccounts(remainingAccounts)
+    .instruction();
+
+  return auditPoolIx;
+}
+
+export async function getPositionIx(
+  poolInfo: PoolInfo,
+  nftMint: PublicKey,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
   );
 
   const nftVaultAccount = (
@@ -296,28 +588,2084 @@ export async function unstakeIx(
     )
   )[0];
 
-  let userNftAccount = await findAssociatedTokenAddress(user, nftMint);
-
-  // create nft vault ATA
-  let nftVaultAta = await findAssociatedTokenAddress(nftVaultAccount, nftMint);
-
-  const unstakeIx = nftStakingProgram.methods
-    .unstake()
+  // booster and guild position are only read when the NFT is boosted or guilded
+  const getPositionIx = await nftStakingProgram.methods
+    .getPosition()
     .accounts({
-      user,
       poolInfo: poolInfo.key,
       nftMint,
-      userNftAccount,
-      nftVaultAta,
-      userProveTokenAccount,
       nftVaultAccount,
-      proveTokenMint: poolInfo.proveTokenMint,
-      rarityInfo: poolInfo.rarityInfo,
-      proveTokenAuthority: poolInfo.proveTokenAuthority,
-      proveTokenVault: poolInfo.proveTokenVault,
-      tokenProgram: TOKEN_PROGRAM_ID,
+      booster: await findBoosterKey(nftVaultAccount),
+      guildPosition: await findGuildPositionKey(nftVaultAccount),
     })
     .instruction();
 
-  return unstakeIx;
+  return getPositionIx;
+}
+
+export async function getPoolStatsIx(
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const getPoolStatsIx = await nftStakingProgram.methods
+    .getPoolStats()
+    .accounts({
+      poolInfo: poolInfo.key,
+    })
+    .instruction();
+
+  return getPoolStatsIx;
+}
+
+export async function stakeIx(
+  poolInfo: PoolInfo,
+  user: PublicKey,
+  userNftAccount: PublicKey,
+  provider: anchor.AnchorProvider,
+  referrer: PublicKey | null = null // only recorded on user's first referral
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  // create user prove token ATA if missing
+  const userProveTokenAccount = await findAssociatedTokenAddress(
+    user,
+    poolInfo.proveTokenMint
+  );
+
+  const IxArr: anchor.web3.TransactionInstruction[] = [];
+  if (
+    (await provider.connection.getAccountInfo(userProveTokenAccount)) == null
+  ) {
+    IxArr.push(
+      createAssociatedTokenAccountInstruction(
+        user,
+        userProveTokenAccount,
+        user,
+        poolInfo.proveTokenMint
+      )
+    );
+  }
+
+  const nftAccount = await getAccount(provider.connection, userNftAccount);
+  const nftMint = nftAccount.mint;
+
+  const nftVaultAccount = (
+    await PublicKey.findProgramAddress(
+      [
+        nftMint.toBuffer(),
+        poolInfo.key.toBuffer(),
+        Buffer.from(NFT_VAULT_SEED),
+      ],
+      NFT_STAKING_PROGRAM_ID
+    )
+  )[0];
+
+  // nft vault ATA is created by stake instruction
+  let nftVaultAta = await findAssociatedTokenAddress(nftVaultAccount, nftMint);
+
+  // gated pool requires user's gate token account
+  const remainingAccounts: anchor.web3.AccountMeta[] = [];
+  if (poolInfo.gateMint && !poolInfo.gateMint.equals(PublicKey.default)) {
+    remainingAccounts.push({
+      pubkey: await findAssociatedTokenAddress(user, poolInfo.gateMint),
+      isSigner: false,
+      isWritable: false,
+    });
+  }
+
+  const stakeIx = await nftStakingProgram.methods
+    .stake(referrer)
+    .accounts({
+      user,
+      poolInfo: poolInfo.key,
+      nftMint,
+      userNftAccount,
+      nftVaultAta,
+      userProveTokenAccount,
+      nftVaultAccount,
+      userPoints: await findUserPointsKey(poolInfo.key, user),
+      proveTokenMint: poolInfo.proveTokenMint,
+      rarityInfo: poolInfo.rarityInfo,
+      proveTokenAuthority: poolInfo.proveTokenAuthority,
+      proveTokenVault: poolInfo.proveTokenVault,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+    })
+    .remainingAccounts(remainingAccounts)
+    .instruction();
+  IxArr.push(stakeIx);
+
+  return IxArr;
+}
+
+export async function unstakeIx(
+  poolInfo: PoolInfo,
+  user: PublicKey,
+  nftMint: PublicKey,
+  provider: anchor.AnchorProvider,
+  recipient: PublicKey | null = null // wallet receiving the NFT, user by default
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  // create user prove token ATA
+  const userProveTokenAccount = await findAssociatedTokenAddress(
+    user,
+    poolInfo.proveTokenMint
+  );
+
+  const nftVaultAccount = (
+    await PublicKey.findProgramAddress(
+      [
+        nftMint.toBuffer(),
+        poolInfo.key.toBuffer(),
+        Buffer.from(NFT_VAULT_SEED),
+      ],
+      NFT_STAKING_PROGRAM_ID
+    )
+  )[0];
+
+  // recipient NFT ATA is created by unstake instruction if missing
+  recipient = recipient ?? user;
+  let userNftAccount = await findAssociatedTokenAddress(recipient, nftMint);
+
+  // create nft vault ATA
+  let nftVaultAta = await findAssociatedTokenAddress(nftVaultAccount, nftMint);
+
+  // unstaking an NFT of a registered set dissolves its SetBonus, vaults staked
+  // under an older layout don't decode and predate sets, unstake migrates them
+  const remainingAccounts: anchor.web3.AccountMeta[] = [];
+  const nftVault = await nftStakingProgram.account.nftVaultAccount
+    .fetch(nftVaultAccount)
+    .catch(() => null);
+  if (nftVault && !nftVault.setBonus.equals(PublicKey.default)) {
+    remainingAccounts.push(
+      ...(await dissolveSetBonusAccounts(
+        nftVaultAccount,
+        nftVault.setBonus,
+        provider
+      ))
+    );
+  }
+
+  const unstakeIx = nftStakingProgram.methods
+    .unstake()
+    .accounts({
+      user,
+      poolInfo: poolInfo.key,
+      nftMint,
+      recipient,
+      userNftAccount,
+      nftVaultAta,
+      userProveTokenAccount,
+      nftVaultAccount,
+      // only the staker unstakes, points are settled on their ledger
+      userPoints: await findUserPointsKey(poolInfo.key, user),
+      proveTokenMint: poolInfo.proveTokenMint,
+      rarityInfo: poolInfo.rarityInfo,
+      proveTokenAuthority: poolInfo.proveTokenAuthority,
+      proveTokenVault: poolInfo.proveTokenVault,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+    })
+    .remainingAccounts(remainingAccounts)
+    .instruction();
+
+  return unstakeIx;
+}
+
+export async function forceUnstakeIx(
+  admin: PublicKey,
+  poolInfo: PoolInfo,
+  nftMint: PublicKey,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const nftVaultAccount = (
+    await PublicKey.findProgramAddress(
+      [
+        nftMint.toBuffer(),
+        poolInfo.key.toBuffer(),
+        Buffer.from(NFT_VAULT_SEED),
+      ],
+      NFT_STAKING_PROGRAM_ID
+    )
+  )[0];
+  const nftVault = await nftStakingProgram.account.nftVaultAccount.fetch(
+    nftVaultAccount
+  );
+
+  // force unstaking an NFT of a registered set dissolves its SetBonus
+  const remainingAccounts: anchor.web3.AccountMeta[] = [];
+  if (!nftVault.setBonus.equals(PublicKey.default)) {
+    remainingAccounts.push(
+      ...(await dissolveSetBonusAccounts(
+        nftVaultAccount,
+        nftVault.setBonus,
+        provider
+      ))
+    );
+  }
+
+  const forceUnstakeIx = await nftStakingProgram.methods
+    .forceUnstake()
+    .accounts({
+      admin,
+      poolInfo: poolInfo.key,
+      nftMint,
+      nftVaultAccount,
+      nftVaultAta: await findAssociatedTokenAddress(nftVaultAccount, nftMint),
+      owner: nftVault.user,
+      ownerNftAccount: await findAssociatedTokenAddress(nftVault.user, nftMint),
+      userPoints: await findUserPointsKey(poolInfo.key, nftVault.user),
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+    })
+    .remainingAccounts(remainingAccounts)
+    .instruction();
+
+  return forceUnstakeIx;
+}
+
+export async function migratePositionIx(
+  oldPoolInfo: PoolInfo,
+  newPoolInfo: PoolInfo,
+  user: PublicKey, // holder of the old prove token
+  nftMint: PublicKey,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  // create user new prove token ATA if missing
+  const userNewProveTokenAccount = await findAssociatedTokenAddress(
+    user,
+    newPoolInfo.proveTokenMint
+  );
+
+  const IxArr: anchor.web3.TransactionInstruction[] = [];
+  if (
+    (await provider.connection.getAccountInfo(userNewProveTokenAccount)) ==
+    null
+  ) {
+    IxArr.push(
+      createAssociatedTokenAccountInstruction(
+        user,
+        userNewProveTokenAccount,
+        user,
+        newPoolInfo.proveTokenMint
+      )
+    );
+  }
+
+  const findNftVaultKey = async (poolInfoKey: PublicKey) =>
+    (
+      await PublicKey.findProgramAddress(
+        [nftMint.toBuffer(), poolInfoKey.toBuffer(), Buffer.from(NFT_VAULT_SEED)],
+        NFT_STAKING_PROGRAM_ID
+      )
+    )[0];
+  const oldNftVaultAccount = await findNftVaultKey(oldPoolInfo.key);
+  const newNftVaultAccount = await findNftVaultKey(newPoolInfo.key);
+  const oldNftVault = await nftStakingProgram.account.nftVaultAccount.fetch(
+    oldNftVaultAccount
+  );
+
+  // gated new pool requires user's gate token account
+  const remainingAccounts: anchor.web3.AccountMeta[] = [];
+  if (newPoolInfo.gateMint && !newPoolInfo.gateMint.equals(PublicKey.default)) {
+    remainingAccounts.push({
+      pubkey: await findAssociatedTokenAddress(user, newPoolInfo.gateMint),
+      isSigner: false,
+      isWritable: false,
+    });
+  }
+
+  const migratePositionIx = await nftStakingProgram.methods
+    .migratePosition()
+    .accounts({
+      user,
+      oldPoolInfo: oldPoolInfo.key,
+      newPoolInfo: newPoolInfo.key,
+      oldRarityInfo: oldPoolInfo.rarityInfo,
+      newRarityInfo: newPoolInfo.rarityInfo,
+      nftMint,
+      oldNftVaultAccount,
+      oldNftVaultAta: await findAssociatedTokenAddress(
+        oldNftVaultAccount,
+        nftMint
+      ),
+      newNftVaultAccount,
+      newNftVaultAta: await findAssociatedTokenAddress(
+        newNftVaultAccount,
+        nftMint
+      ),
+      userOldProveTokenAccount: await findAssociatedTokenAddress(
+        user,
+        oldPoolInfo.proveTokenMint
+      ),
+      oldProveTokenVault: oldPoolInfo.proveTokenVault,
+      userNewProveTokenAccount,
+      newProveTokenAuthority: newPoolInfo.proveTokenAuthority,
+      newProveTokenVault: newPoolInfo.proveTokenVault,
+      // points are settled on the old staker's ledger
+      oldUserPoints: await findUserPointsKey(oldPoolInfo.key, oldNftVault.user),
+      newUserPoints: await findUserPointsKey(newPoolInfo.key, user),
+      systemProgram: anchor.web3.SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+    })
+    .remainingAccounts(remainingAccounts)
+    .instruction();
+  IxArr.push(migratePositionIx);
+
+  return IxArr;
+}
+
+export async function findSetInfoKey(poolInfoKey: PublicKey, nonce: number) {
+  return (
+    await PublicKey.findProgramAddress(
+      [
+        poolInfoKey.toBuffer(),
+        new anchor.BN(nonce).toArrayLike(Buffer, "le", 8),
+        Buffer.from(SET_INFO_SEED),
+      ],
+      NFT_STAKING_PROGRAM_ID
+    )
+  )[0];
+}
+
+export async function initiateSetInfoIx(
+  authority: PublicKey, // admin or config manager
+  poolInfo: PoolInfo, // one of the set's pools
+  nonce: number,
+  rarityInfos: PublicKey[],
+  bonusBps: number,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const setInfo = await findSetInfoKey(poolInfo.key, nonce);
+
+  const initSetIx = await nftStakingProgram.methods
+    .initializeSet(new anchor.BN(nonce), rarityInfos, new anchor.BN(bonusBps))
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+      setInfo,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .instruction();
+
+  return initSetIx;
+}
+
+// SetBonus followed by (poolInfo, nftVaultAccount, userPoints) of the other set members
+async function dissolveSetBonusAccounts(
+  nftVaultAccount: PublicKey,
+  setBonusKey: PublicKey,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const setBonus = await nftStakingProgram.account.setBonus.fetch(setBonusKey);
+  const accounts: anchor.web3.AccountMeta[] = [
+    { pubkey: setBonusKey, isSigner: false, isWritable: true },
+  ];
+  for (let otherNftVault of setBonus.nftVaults) {
+    if (otherNftVault.equals(nftVaultAccount)) {
+      continue;
+    }
+    const { poolInfo, user } =
+      await nftStakingProgram.account.nftVaultAccount.fetch(otherNftVault);
+    accounts.push({ pubkey: poolInfo, isSigner: false, isWritable: true });
+    accounts.push({ pubkey: otherNftVault, isSigner: false, isWritable: true });
+    accounts.push({
+      pubkey: await findUserPointsKey(poolInfo, user),
+      isSigner: false,
+      isWritable: true,
+    });
+  }
+
+  return accounts;
+}
+
+export async function registerSetIx(
+  setInfo: PublicKey,
+  user: PublicKey,
+  poolInfos: PoolInfo[], // one pool for each rarityInfo of the set, in the same order
+  nftMints: PublicKey[],
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const setBonus = (
+    await PublicKey.findProgramAddress(
+      [setInfo.toBuffer(), user.toBuffer(), Buffer.from(SET_BONUS_SEED)],
+      NFT_STAKING_PROGRAM_ID
+    )
+  )[0];
+
+  const remainingAccounts: anchor.web3.AccountMeta[] = [];
+  for (let [index, poolInfo] of poolInfos.entries()) {
+    const nftVaultAccount = (
+      await PublicKey.findProgramAddress(
+        [
+          nftMints[index].toBuffer(),
+          poolInfo.key.toBuffer(),
+          Buffer.from(NFT_VAULT_SEED),
+        ],
+        NFT_STAKING_PROGRAM_ID
+      )
+    )[0];
+    remainingAccounts.push({
+      pubkey: poolInfo.key,
+      isSigner: false,
+      isWritable: true,
+    });
+    remainingAccounts.push({
+      pubkey: nftVaultAccount,
+      isSigner: false,
+      isWritable: true,
+    });
+    // set bonus weight is added on the user's points ledger of each pool
+    remainingAccounts.push({
+      pubkey: await findUserPointsKey(poolInfo.key, user),
+      isSigner: false,
+      isWritable: true,
+    });
+  }
+
+  const registerIx = await nftStakingProgram.methods
+    .registerSet()
+    .accounts({
+      user,
+      setInfo,
+      setBonus,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .remainingAccounts(remainingAccounts)
+    .instruction();
+
+  return registerIx;
+}
+
+export async function findLeaderboardKey(poolInfoKey: PublicKey) {
+  return (
+    await PublicKey.findProgramAddress(
+      [poolInfoKey.toBuffer(), Buffer.from(LEADERBOARD_SEED)],
+      NFT_STAKING_PROGRAM_ID
+    )
+  )[0];
+}
+
+export async function initiateLeaderboardIx(
+  authority: PublicKey, // admin or config manager
+  metric: "stakedCount" | "points",
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const initLeaderboardIx = await nftStakingProgram.methods
+    .initializeLeaderboard({ [metric]: {} } as any)
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+      leaderboard: await findLeaderboardKey(poolInfo.key),
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .instruction();
+
+  return initLeaderboardIx;
+}
+
+export async function refreshLeaderboardIx(
+  users: PublicKey[],
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const remainingAccounts: anchor.web3.AccountMeta[] = [];
+  for (let user of users) {
+    remainingAccounts.push({
+      pubkey: await findUserPointsKey(poolInfo.key, user),
+      isSigner: false,
+      isWritable: false,
+    });
+  }
+
+  const refreshLeaderboardIx = await nftStakingProgram.methods
+    .refreshLeaderboard()
+    .accounts({
+      poolInfo: poolInfo.key,
+      leaderboard: await findLeaderboardKey(poolInfo.key),
+    })
+    .remainingAccounts(remainingAccounts)
+    .instruction();
+
+  return refreshLeaderboardIx;
+}
+
+export async function findDistributionKey(poolInfoKey: PublicKey, index: number) {
+  return (
+    await PublicKey.findProgramAddress(
+      [
+        poolInfoKey.toBuffer(),
+        new anchor.BN(index).toArrayLike(Buffer, "le", 8),
+        Buffer.from(DISTRIBUTION_SEED),
+      ],
+      NFT_STAKING_PROGRAM_ID
+    )
+  )[0];
+}
+
+export async function publishDistributionIx(
+  authority: PublicKey, // admin or reward manager
+  index: number,
+  rewardMint: PublicKey,
+  merkleRoot: Buffer,
+  totalAmount: number,
+  numLeaves: number,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const distribution = await findDistributionKey(poolInfo.key, index);
+
+  const publishDistributionIx = await nftStakingProgram.methods
+    .publishDistribution(
+      new anchor.BN(index),
+      [...merkleRoot],
+      new anchor.BN(totalAmount),
+      new anchor.BN(numLeaves)
+    )
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+      rewardMint,
+      authorityRewardAccount: await findAssociatedTokenAddress(
+        authority,
+        rewardMint
+      ),
+      distribution,
+      distributionVault: await findAssociatedTokenAddress(
+        distribution,
+        rewardMint
+      ),
+      systemProgram: anchor.web3.SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+    })
+    .instruction();
+
+  return publishDistributionIx;
+}
+
+export async function claimDistributionIx(
+  user: PublicKey,
+  distribution: PublicKey,
+  leafIndex: number,
+  amount: number,
+  proof: Buffer[],
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const { poolInfo, rewardMint, vault } =
+    await nftStakingProgram.account.distribution.fetch(distribution);
+
+  const claimDistributionIx = await nftStakingProgram.methods
+    .claimDistribution(
+      new anchor.BN(leafIndex),
+      new anchor.BN(amount),
+      proof.map((node) => [...node])
+    )
+    .accounts({
+      user,
+      poolInfo,
+      distribution,
+      distributionVault: vault,
+      userRewardAccount: await findAssociatedTokenAddress(user, rewardMint),
+      tokenProgram: TOKEN_PROGRAM_ID,
+    })
+    .instruction();
+
+  return claimDistributionIx;
+}
+
+export async function updatePoolInfoVestingIx(
+  authority: PublicKey, // admin or reward manager
+  vestingDuration: number, // seconds, 0 pays claims out directly
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const updateVestingIx = await nftStakingProgram.methods
+    .updateVesting(new anchor.BN(vestingDuration))
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+    })
+    .instruction();
+
+  return updateVestingIx;
+}
+
+export async function findVestingEscrowKey(
+  poolInfoKey: PublicKey,
+  user: PublicKey,
+  rewardMint: PublicKey
+) {
+  return (
+    await PublicKey.findProgramAddress(
+      [
+        poolInfoKey.toBuffer(),
+        user.toBuffer(),
+        rewardMint.toBuffer(),
+        Buffer.from(VESTING_ESCROW_SEED),
+      ],
+      NFT_STAKING_PROGRAM_ID
+    )
+  )[0];
+}
+
+export async function claimDistributionVestedIx(
+  user: PublicKey,
+  distribution: PublicKey,
+  leafIndex: number,
+  amount: number,
+  proof: Buffer[],
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const { poolInfo, rewardMint, vault } =
+    await nftStakingProgram.account.distribution.fetch(distribution);
+  const vestingEscrow = await findVestingEscrowKey(poolInfo, user, rewardMint);
+
+  const claimDistributionVestedIx = await nftStakingProgram.methods
+    .claimDistributionVested(
+      new anchor.BN(leafIndex),
+      new anchor.BN(amount),
+      proof.map((node) => [...node])
+    )
+    .accounts({
+      user,
+      poolInfo,
+      distribution,
+      distributionVault: vault,
+      rewardMint,
+      vestingEscrow,
+      vestingVault: await findAssociatedTokenAddress(vestingEscrow, rewardMint),
+      systemProgram: anchor.web3.SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+    })
+    .instruction();
+
+  return claimDistributionVestedIx;
+}
+
+export async function withdrawVestedIx(
+  user: PublicKey,
+  poolInfoKey: PublicKey,
+  rewardMint: PublicKey,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const vestingEscrow = await findVestingEscrowKey(poolInfoKey, user, rewardMint);
+
+  const withdrawVestedIx = await nftStakingProgram.methods
+    .withdrawVested()
+    .accounts({
+      user,
+      vestingEscrow,
+      vestingVault: await findAssociatedTokenAddress(vestingEscrow, rewardMint),
+      userRewardAccount: await findAssociatedTokenAddress(user, rewardMint),
+      tokenProgram: TOKEN_PROGRAM_ID,
+    })
+    .instruction();
+
+  return withdrawVestedIx;
+}
+
+export async function findRaffleKey(poolInfoKey: PublicKey, index: number) {
+  return (
+    await PublicKey.findProgramAddress(
+      [
+        poolInfoKey.toBuffer(),
+        new anchor.BN(index).toArrayLike(Buffer, "le", 8),
+        Buffer.from(RAFFLE_SEED),
+      ],
+      NFT_STAKING_PROGRAM_ID
+    )
+  )[0];
+}
+
+export async function commitRaffleIx(
+  authority: PublicKey, // admin or reward manager
+  index: number,
+  weighting: RaffleWeighting,
+  numWinners: number,
+  entrySlots: number,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const commitRaffleIx = await nftStakingProgram.methods
+    .commitRaffle(
+      new anchor.BN(index),
+      { [weighting]: {} },
+      numWinners,
+      new anchor.BN(entrySlots)
+    )
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+      raffle: await findRaffleKey(poolInfo.key, index),
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .instruction();
+
+  return commitRaffleIx;
+}
+
+export async function enterRaffleIx(
+  user: PublicKey, // owner of every entered NftVaultAccount
+  raffle: PublicKey,
+  nftVaultAccounts: PublicKey[], // staked no later than the raffle's commit
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const remainingAccounts: anchor.web3.AccountMeta[] = nftVaultAccounts.map(
+    (nftVaultAccount) => ({
+      pubkey: nftVaultAccount,
+      isSigner: false,
+      isWritable: true,
+    })
+  );
+
+  const enterRaffleIx = await nftStakingProgram.methods
+    .enterRaffle()
+    .accounts({
+      user,
+      raffle,
+    })
+    .remainingAccounts(remainingAccounts)
+    .instruction();
+
+  return enterRaffleIx;
+}
+
+export async function drawRaffleIx(
+  raffle: PublicKey,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const drawRaffleIx = await nftStakingProgram.methods
+    .drawRaffle()
+    .accounts({
+      raffle,
+      slotHashes: anchor.web3.SYSVAR_SLOT_HASHES_PUBKEY,
+    })
+    .instruction();
+
+  return drawRaffleIx;
+}
+
+// only once the reveal slot hash has left SlotHashes without a draw
+export async function cancelRaffleIx(
+  authority: PublicKey, // admin or reward manager
+  raffle: PublicKey,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const cancelRaffleIx = await nftStakingProgram.methods
+    .cancelRaffle()
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+      raffle,
+      slotHashes: anchor.web3.SYSVAR_SLOT_HASHES_PUBKEY,
+    })
+    .instruction();
+
+  return cancelRaffleIx;
+}
+
+export async function findLoanConfigKey(poolInfoKey: PublicKey) {
+  return (
+    await PublicKey.findProgramAddress(
+      [poolInfoKey.toBuffer(), Buffer.from(LOAN_CONFIG_SEED)],
+      NFT_STAKING_PROGRAM_ID
+    )
+  )[0];
+}
+
+export async function findLoanKey(nftVaultAccount: PublicKey) {
+  return (
+    await PublicKey.findProgramAddress(
+      [nftVaultAccount.toBuffer(), Buffer.from(LOAN_SEED)],
+      NFT_STAKING_PROGRAM_ID
+    )
+  )[0];
+}
+
+export async function initiateLoanConfigIx(
+  authority: PublicKey, // admin or config manager
+  loanMint: PublicKey,
+  nftValue: number,
+  ltvBps: number,
+  interestBps: number,
+  duration: number,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const loanConfig = await findLoanConfigKey(poolInfo.key);
+
+  const initiateLoanConfigIx = await nftStakingProgram.methods
+    .initializeLoanConfig(
+      new anchor.BN(nftValue),
+      new anchor.BN(ltvBps),
+      new anchor.BN(interestBps),
+      new anchor.BN(duration)
+    )
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+      loanMint,
+      loanConfig,
+      loanVault: await findAssociatedTokenAddress(loanConfig, loanMint),
+      systemProgram: anchor.web3.SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+    })
+    .instruction();
+
+  return initiateLoanConfigIx;
+}
+
+export async function updateLoanTermsIx(
+  authority: PublicKey, // admin or config manager
+  nftValue: number,
+  ltvBps: number,
+  interestBps: number,
+  duration: number,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const updateLoanTermsIx = await nftStakingProgram.methods
+    .updateLoanTerms(
+      new anchor.BN(nftValue),
+      new anchor.BN(ltvBps),
+      new anchor.BN(interestBps),
+      new anchor.BN(duration)
+    )
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+      loanConfig: await findLoanConfigKey(poolInfo.key),
+    })
+    .instruction();
+
+  return updateLoanTermsIx;
+}
+
+export async function fundLoanVaultIx(
+  authority: PublicKey, // admin or reward manager
+  amount: number,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const loanConfig = await findLoanConfigKey(poolInfo.key);
+  const { loanMint, loanVault } =
+    await nftStakingProgram.account.loanConfig.fetch(loanConfig);
+
+  const fundLoanVaultIx = await nftStakingProgram.methods
+    .fundLoanVault(new anchor.BN(amount))
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+      loanConfig,
+      loanVault,
+      authorityLoanAccount: await findAssociatedTokenAddress(
+        authority,
+        loanMint
+      ),
+      tokenProgram: TOKEN_PROGRAM_ID,
+    })
+    .instruction();
+
+  return fundLoanVaultIx;
+}
+
+export async function withdrawLoanVaultIx(
+  authority: PublicKey, // admin or reward manager
+  amount: number,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const loanConfig = await findLoanConfigKey(poolInfo.key);
+  const { loanMint, loanVault } =
+    await nftStakingProgram.account.loanConfig.fetch(loanConfig);
+
+  const withdrawLoanVaultIx = await nftStakingProgram.methods
+    .withdrawLoanVault(new anchor.BN(amount))
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+      loanConfig,
+      loanVault,
+      authorityLoanAccount: await findAssociatedTokenAddress(
+        authority,
+        loanMint
+      ),
+      tokenProgram: TOKEN_PROGRAM_ID,
+    })
+    .instruction();
+
+  return withdrawLoanVaultIx;
+}
+
+export async function borrowIx(
+  user: PublicKey,
+  nftVaultAccount: PublicKey,
+  amount: number,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const loanConfig = await findLoanConfigKey(poolInfo.key);
+  const { loanMint, loanVault } =
+    await nftStakingProgram.account.loanConfig.fetch(loanConfig);
+  const loan = await findLoanKey(nftVaultAccount);
+
+  const borrowIx = await nftStakingProgram.methods
+    .borrow(new anchor.BN(amount))
+    .accounts({
+      user,
+      poolInfo: poolInfo.key,
+      loanConfig,
+      nftVaultAccount,
+      loan,
+      loanVault,
+      userLoanAccount: await findAssociatedTokenAddress(user, loanMint),
+      // the position's prove token is escrowed until repaid or seized
+      proveTokenMint: poolInfo.proveTokenMint,
+      userProveTokenAccount: await findAssociatedTokenAddress(
+        user,
+        poolInfo.proveTokenMint
+      ),
+      loanProveTokenAccount: await findAssociatedTokenAddress(
+        loan,
+        poolInfo.proveTokenMint
+      ),
+      systemProgram: anchor.web3.SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+    })
+    .instruction();
+
+  return borrowIx;
+}
+
+export async function repayLoanIx(
+  payer: PublicKey,
+  nftVaultAccount: PublicKey,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const loan = await findLoanKey(nftVaultAccount);
+  const { borrower, poolInfo, loanMint } =
+    await nftStakingProgram.account.loan.fetch(loan);
+  const loanConfig = await findLoanConfigKey(poolInfo);
+  const { loanVault } = await nftStakingProgram.account.loanConfig.fetch(
+    loanConfig
+  );
+  const { proveTokenMint } = await nftStakingProgram.account.poolInfo.fetch(
+    poolInfo
+  );
+
+  // escrowed prove token goes back to the borrower's ATA, which has to exist
+  const repayLoanIx = await nftStakingProgram.methods
+    .repayLoan()
+    .accounts({
+      payer,
+      poolInfo,
+      loanConfig,
+      nftVaultAccount,
+      loan,
+      borrower,
+      loanVault,
+      payerLoanAccount: await findAssociatedTokenAddress(payer, loanMint),
+      loanProveTokenAccount: await findAssociatedTokenAddress(
+        loan,
+        proveTokenMint
+      ),
+      borrowerProveTokenAccount: await findAssociatedTokenAddress(
+        borrower,
+        proveTokenMint
+      ),
+      tokenProgram: TOKEN_PROGRAM_ID,
+    })
+    .instruction();
+
+  return repayLoanIx;
+}
+
+export async function seizeLoanIx(
+  authority: PublicKey, // admin or reward manager
+  nftVaultAccount: PublicKey,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const { user, nftMint } =
+    await nftStakingProgram.account.nftVaultAccount.fetch(nftVaultAccount);
+  const loan = await findLoanKey(nftVaultAccount);
+
+  // authority NFT account has to exist before seizing
+  const seizeLoanIx = await nftStakingProgram.methods
+    .seizeLoan()
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+      loanConfig: await findLoanConfigKey(poolInfo.key),
+      nftMint,
+      nftVaultAccount,
+      nftVaultAta: await findAssociatedTokenAddress(nftVaultAccount, nftMint),
+      loan,
+      borrower: user,
+      // escrowed prove token goes back to the prove token vault
+      loanProveTokenAccount: await findAssociatedTokenAddress(
+        loan,
+        poolInfo.proveTokenMint
+      ),
+      proveTokenVault: poolInfo.proveTokenVault,
+      authorityNftAccount: await findAssociatedTokenAddress(
+        authority,
+        nftMint
+      ),
+      userPoints: await findUserPointsKey(poolInfo.key, user),
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .instruction();
+
+  return seizeLoanIx;
+}
+
+export async function findCnftVaultKey(
+  poolInfoKey: PublicKey,
+  merkleTree: PublicKey,
+  nonce: number
+) {
+  return (
+    await PublicKey.findProgramAddress(
+      [
+        merkleTree.toBuffer(),
+        new anchor.BN(nonce).toArrayLike(Buffer, "le", 8),
+        poolInfoKey.toBuffer(),
+        Buffer.from(CNFT_VAULT_SEED),
+      ],
+      NFT_STAKING_PROGRAM_ID
+    )
+  )[0];
+}
+
+function compressedLeafArg(leaf: CompressedLeaf) {
+  return {
+    root: [...leaf.root],
+    dataHash: [...leaf.dataHash],
+    creatorHash: [...leaf.creatorHash],
+    nonce: new anchor.BN(leaf.nonce),
+    index: leaf.index,
+  };
+}
+
+async function compressedAccounts(merkleTree: PublicKey) {
+  return {
+    treeAuthority: (
+      await PublicKey.findProgramAddress(
+        [merkleTree.toBuffer()],
+        BUBBLEGUM_PROGRAM_ID
+      )
+    )[0],
+    merkleTree,
+    logWrapper: NOOP_PROGRAM_ID,
+    compressionProgram: ACCOUNT_COMPRESSION_PROGRAM_ID,
+    bubblegumProgram: BUBBLEGUM_PROGRAM_ID,
+  };
+}
+
+export async function stakeCompressedIx(
+  poolInfo: PoolInfo,
+  user: PublicKey,
+  merkleTree: PublicKey,
+  leaf: CompressedLeaf,
+  proof: PublicKey[], // proof nodes, canopy excluded
+  provider: anchor.AnchorProvider,
+  leafDelegate: PublicKey | null = null, // user if no delegate is set
+  referrer: PublicKey | null = null
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  // create user prove token ATA if missing
+  const userProveTokenAccount = await findAssociatedTokenAddress(
+    user,
+    poolInfo.proveTokenMint
+  );
+
+  const IxArr: anchor.web3.TransactionInstruction[] = [];
+  if (
+    (await provider.connection.getAccountInfo(userProveTokenAccount)) == null
+  ) {
+    IxArr.push(
+      createAssociatedTokenAccountInstruction(
+        user,
+        userProveTokenAccount,
+        user,
+        poolInfo.proveTokenMint
+      )
+    );
+  }
+
+  // gated pool requires user's gate token account ahead of proof nodes
+  const remainingAccounts: anchor.web3.AccountMeta[] = [];
+  if (poolInfo.gateMint && !poolInfo.gateMint.equals(PublicKey.default)) {
+    remainingAccounts.push({
+      pubkey: await findAssociatedTokenAddress(user, poolInfo.gateMint),
+      isSigner: false,
+      isWritable: false,
+    });
+  }
+  for (let node of proof) {
+    remainingAccounts.push({ pubkey: node, isSigner: false, isWritable: false });
+  }
+
+  const stakeCompressedIx = await nftStakingProgram.methods
+    .stakeCompressed(compressedLeafArg(leaf), referrer)
+    .accounts({
+      user,
+      poolInfo: poolInfo.key,
+      proveTokenMint: poolInfo.proveTokenMint,
+      rarityInfo: poolInfo.rarityInfo,
+      userProveTokenAccount,
+      proveTokenAuthority: poolInfo.proveTokenAuthority,
+      proveTokenVault: poolInfo.proveTokenVault,
+      cnftVaultAccount: await findCnftVaultKey(
+        poolInfo.key,
+        merkleTree,
+        leaf.nonce
+      ),
+      userPoints: await findUserPointsKey(poolInfo.key, user),
+      leafDelegate: leafDelegate ?? user,
+      ...(await compressedAccounts(merkleTree)),
+      systemProgram: anchor.web3.SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    })
+    .remainingAccounts(remainingAccounts)
+    .instruction();
+  IxArr.push(stakeCompressedIx);
+
+  return IxArr;
+}
+
+export async function unstakeCompressedIx(
+  poolInfo: PoolInfo,
+  user: PublicKey,
+  merkleTree: PublicKey,
+  leaf: CompressedLeaf,
+  proof: PublicKey[], // proof nodes, canopy excluded
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const cnftVaultAccount = await findCnftVaultKey(
+    poolInfo.key,
+    merkleTree,
+    leaf.nonce
+  );
+  const cnftVault = await nftStakingProgram.account.cnftVaultAccount.fetch(
+    cnftVaultAccount
+  );
+
+  const unstakeCompressedIx = await nftStakingProgram.methods
+    .unstakeCompressed(compressedLeafArg(leaf))
+    .accounts({
+      user,
+      poolInfo: poolInfo.key,
+      proveTokenMint: poolInfo.proveTokenMint,
+      rarityInfo: poolInfo.rarityInfo,
+      userProveTokenAccount: await findAssociatedTokenAddress(
+        user,
+        poolInfo.proveTokenMint
+      ),
+      proveTokenVault: poolInfo.proveTokenVault,
+      cnftVaultAccount,
+      userPoints: await findUserPointsKey(poolInfo.key, cnftVault.user),
+      ...(await compressedAccounts(merkleTree)),
+      systemProgram: anchor.web3.SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    })
+    .remainingAccounts(
+      proof.map((node) => ({ pubkey: node, isSigner: false, isWritable: false }))
+    )
+    .instruction();
+
+  return unstakeCompressedIx;
+}
+
+export async function forceUnstakeCompressedIx(
+  admin: PublicKey,
+  poolInfo: PoolInfo,
+  merkleTree: PublicKey,
+  leaf: CompressedLeaf,
+  proof: PublicKey[], // proof nodes, canopy excluded
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const cnftVaultAccount = await findCnftVaultKey(
+    poolInfo.key,
+    merkleTree,
+    leaf.nonce
+  );
+  const cnftVault = await nftStakingProgram.account.cnftVaultAccount.fetch(
+    cnftVaultAccount
+  );
+
+  const forceUnstakeCompressedIx = await nftStakingProgram.methods
+    .forceUnstakeCompressed(compressedLeafArg(leaf))
+    .accounts({
+      admin,
+      poolInfo: poolInfo.key,
+      cnftVaultAccount,
+      owner: cnftVault.user,
+      userPoints: await findUserPointsKey(poolInfo.key, cnftVault.user),
+      ...(await compressedAccounts(merkleTree)),
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .remainingAccounts(
+      proof.map((node) => ({ pubkey: node, isSigner: false, isWritable: false }))
+    )
+    .instruction();
+
+  return forceUnstakeCompressedIx;
+}
+
+export async function findSftVaultKey(
+  poolInfoKey: PublicKey,
+  nftMint: PublicKey,
+  user: PublicKey
+) {
+  return (
+    await PublicKey.findProgramAddress(
+      [
+        nftMint.toBuffer(),
+        poolInfoKey.toBuffer(),
+        user.toBuffer(),
+        Buffer.from(SFT_VAULT_SEED),
+      ],
+      NFT_STAKING_PROGRAM_ID
+    )
+  )[0];
+}
+
+export async function updatePoolInfoSftIx(
+  authority: PublicKey, // admin or config manager
+  sftEnabled: boolean,
+  fundAmount: number, // extra prove token for SFT stakes, from authority's ATA
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const updateSftIx = await nftStakingProgram.methods
+    .updateSft(sftEnabled, new anchor.BN(fundAmount))
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+      authorityProveTokenAccount: await findAssociatedTokenAddress(
+        authority,
+        poolInfo.proveTokenMint
+      ),
+      proveTokenVault: poolInfo.proveTokenVault,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    })
+    .instruction();
+
+  return updateSftIx;
+}
+
+export async function stakeSftIx(
+  poolInfo: PoolInfo,
+  user: PublicKey,
+  userNftAccount: PublicKey,
+  amount: number,
+  provider: anchor.AnchorProvider,
+  referrer: PublicKey | null = null
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  // create user prove token ATA if missing
+  const userProveTokenAccount = await findAssociatedTokenAddress(
+    user,
+    poolInfo.proveTokenMint
+  );
+
+  const IxArr: anchor.web3.TransactionInstruction[] = [];
+  if (
+    (await provider.connection.getAccountInfo(userProveTokenAccount)) == null
+  ) {
+    IxArr.push(
+      createAssociatedTokenAccountInstruction(
+        user,
+        userProveTokenAccount,
+        user,
+        poolInfo.proveTokenMint
+      )
+    );
+  }
+
+  const nftAccount = await getAccount(provider.connection, userNftAccount);
+  const nftMint = nftAccount.mint;
+  const sftVaultAccount = await findSftVaultKey(poolInfo.key, nftMint, user);
+
+  // gated pool requires user's gate token account
+  const remainingAccounts: anchor.web3.AccountMeta[] = [];
+  if (poolInfo.gateMint && !poolInfo.gateMint.equals(PublicKey.default)) {
+    remainingAccounts.push({
+      pubkey: await findAssociatedTokenAddress(user, poolInfo.gateMint),
+      isSigner: false,
+      isWritable: false,
+    });
+  }
+
+  const stakeSftIx = await nftStakingProgram.methods
+    .stakeSft(new anchor.BN(amount), referrer)
+    .accounts({
+      user,
+      poolInfo: poolInfo.key,
+      proveTokenMint: poolInfo.proveTokenMint,
+      nftMint,
+      rarityInfo: poolInfo.rarityInfo,
+      userNftAccount,
+      userProveTokenAccount,
+      proveTokenAuthority: poolInfo.proveTokenAuthority,
+      proveTokenVault: poolInfo.proveTokenVault,
+      sftVaultAccount,
+      sftVaultAta: await findAssociatedTokenAddress(sftVaultAccount, nftMint),
+      userPoints: await findUserPointsKey(poolInfo.key, user),
+      systemProgram: anchor.web3.SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+    })
+    .remainingAccounts(remainingAccounts)
+    .instruction();
+  IxArr.push(stakeSftIx);
+
+  return IxArr;
+}
+
+export async function unstakeSftIx(
+  poolInfo: PoolInfo,
+  user: PublicKey,
+  nftMint: PublicKey,
+  amount: number,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const sftVaultAccount = await findSftVaultKey(poolInfo.key, nftMint, user);
+
+  const unstakeSftIx = await nftStakingProgram.methods
+    .unstakeSft(new anchor.BN(amount))
+    .accounts({
+      user,
+      poolInfo: poolInfo.key,
+      proveTokenMint: poolInfo.proveTokenMint,
+      nftMint,
+      rarityInfo: poolInfo.rarityInfo,
+      userNftAccount: await findAssociatedTokenAddress(user, nftMint),
+      userProveTokenAccount: await findAssociatedTokenAddress(
+        user,
+        poolInfo.proveTokenMint
+      ),
+      proveTokenVault: poolInfo.proveTokenVault,
+      sftVaultAccount,
+      sftVaultAta: await findAssociatedTokenAddress(sftVaultAccount, nftMint),
+      userPoints: await findUserPointsKey(poolInfo.key, user),
+      tokenProgram: TOKEN_PROGRAM_ID,
+    })
+    .instruction();
+
+  return unstakeSftIx;
+}
+
+export async function forceUnstakeSftIx(
+  admin: PublicKey,
+  poolInfo: PoolInfo,
+  owner: PublicKey, // staker of the position
+  nftMint: PublicKey,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const sftVaultAccount = await findSftVaultKey(poolInfo.key, nftMint, owner);
+
+  const forceUnstakeSftIx = await nftStakingProgram.methods
+    .forceUnstakeSft()
+    .accounts({
+      admin,
+      poolInfo: poolInfo.key,
+      nftMint,
+      sftVaultAccount,
+      sftVaultAta: await findAssociatedTokenAddress(sftVaultAccount, nftMint),
+      owner,
+      ownerNftAccount: await findAssociatedTokenAddress(owner, nftMint),
+      userPoints: await findUserPointsKey(poolInfo.key, owner),
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+    })
+    .instruction();
+
+  return forceUnstakeSftIx;
+}
+
+export async function findSnapshotKey(poolInfoKey: PublicKey, index: number) {
+  return (
+    await PublicKey.findProgramAddress(
+      [
+        poolInfoKey.toBuffer(),
+        new anchor.BN(index).toArrayLike(Buffer, "le", 8),
+        Buffer.from(SNAPSHOT_SEED),
+      ],
+      NFT_STAKING_PROGRAM_ID
+    )
+  )[0];
+}
+
+export async function findSnapshotPageKey(
+  snapshot: PublicKey,
+  pageIndex: number
+) {
+  return (
+    await PublicKey.findProgramAddress(
+      [
+        snapshot.toBuffer(),
+        new anchor.BN(pageIndex).toArrayLike(Buffer, "le", 4),
+        Buffer.from(SNAPSHOT_PAGE_SEED),
+      ],
+      NFT_STAKING_PROGRAM_ID
+    )
+  )[0];
+}
+
+export async function beginSnapshotIx(
+  authority: PublicKey, // admin or reward manager
+  index: number,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const beginSnapshotIx = await nftStakingProgram.methods
+    .beginSnapshot(new anchor.BN(index))
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+      snapshot: await findSnapshotKey(poolInfo.key, index),
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .instruction();
+
+  return beginSnapshotIx;
+}
+
+export async function addSnapshotPageIx(
+  authority: PublicKey,
+  snapshot: PublicKey,
+  pageIndex: number,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const addSnapshotPageIx = await nftStakingProgram.methods
+    .addSnapshotPage(pageIndex)
+    .accounts({
+      authority,
+      snapshot,
+      snapshotPage: await findSnapshotPageKey(snapshot, pageIndex),
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .instruction();
+
+  return addSnapshotPageIx;
+}
+
+export async function recordSnapshotIx(
+  snapshot: PublicKey,
+  pageIndex: number,
+  positions: PublicKey[], // NftVaultAccounts, SftVaultAccounts or CnftVaultAccounts
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const remainingAccounts: anchor.web3.AccountMeta[] = positions.map(
+    (position) => ({
+      pubkey: position,
+      isSigner: false,
+      isWritable: true,
+    })
+  );
+
+  const recordSnapshotIx = await nftStakingProgram.methods
+    .recordSnapshot()
+    .accounts({
+      snapshot,
+      snapshotPage: await findSnapshotPageKey(snapshot, pageIndex),
+    })
+    .remainingAccounts(remainingAccounts)
+    .instruction();
+
+  return recordSnapshotIx;
+}
+
+export async function finalizeSnapshotIx(
+  authority: PublicKey,
+  snapshot: PublicKey,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  // finalizing frees the pool for its next snapshot
+  const { poolInfo } = await nftStakingProgram.account.snapshot.fetch(snapshot);
+  const finalizeSnapshotIx = await nftStakingProgram.methods
+    .finalizeSnapshot()
+    .accounts({
+      authority,
+      poolInfo,
+      snapshot,
+    })
+    .instruction();
+
+  return finalizeSnapshotIx;
+}
+
+export async function cancelSnapshotIx(
+  authority: PublicKey, // admin or reward manager
+  snapshot: PublicKey,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const cancelSnapshotIx = await nftStakingProgram.methods
+    .cancelSnapshot()
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+      snapshot,
+    })
+    .instruction();
+
+  return cancelSnapshotIx;
+}
+
+export async function findBoosterConfigKey(
+  poolInfoKey: PublicKey,
+  source: PublicKey // booster mint, or RarityInfo of booster NFTs
+) {
+  return (
+    await PublicKey.findProgramAddress(
+      [poolInfoKey.toBuffer(), source.toBuffer(), Buffer.from(BOOSTER_CONFIG_SEED)],
+      NFT_STAKING_PROGRAM_ID
+    )
+  )[0];
+}
+
+export async function findBoosterKey(nftVaultAccount: PublicKey) {
+  return (
+    await PublicKey.findProgramAddress(
+      [nftVaultAccount.toBuffer(), Buffer.from(BOOSTER_SEED)],
+      NFT_STAKING_PROGRAM_ID
+    )
+  )[0];
+}
+
+export async function configureBoosterIx(
+  authority: PublicKey, // admin or config manager
+  source: PublicKey, // booster mint, or RarityInfo of booster NFTs
+  kind: BoosterKind,
+  amount: number, // 1 for NFTs
+  multiplierBps: number, // 10000 is 1x
+  active: boolean,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const configureBoosterIx = await nftStakingProgram.methods
+    .configureBooster(
+      { [kind]: {} },
+      new anchor.BN(amount),
+      new anchor.BN(multiplierBps),
+      active
+    )
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+      source,
+      boosterConfig: await findBoosterConfigKey(poolInfo.key, source),
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .instruction();
+
+  return configureBoosterIx;
+}
+
+export async function attachBoosterIx(
+  user: PublicKey,
+  nftVaultAccount: PublicKey,
+  boosterConfig: PublicKey,
+  boosterMint: PublicKey,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const { source } = await nftStakingProgram.account.boosterConfig.fetch(
+    boosterConfig
+  );
+  const booster = await findBoosterKey(nftVaultAccount);
+
+  const attachBoosterIx = await nftStakingProgram.methods
+    .attachBooster()
+    .accounts({
+      user,
+      poolInfo: poolInfo.key,
+      nftVaultAccount,
+      boosterConfig,
+      boosterSource: source,
+      boosterMint,
+      booster,
+      boosterVault: await findAssociatedTokenAddress(booster, boosterMint),
+      userBoosterAccount: await findAssociatedTokenAddress(user, boosterMint),
+      userPoints: await findUserPointsKey(poolInfo.key, user),
+      systemProgram: anchor.web3.SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+    })
+    .instruction();
+
+  return attachBoosterIx;
+}
+
+export async function detachBoosterIx(
+  authority: PublicKey, // staker or admin
+  nftVaultAccount: PublicKey,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const booster = await findBoosterKey(nftVaultAccount);
+  const { user, boosterConfig, boosterMint, vault } =
+    await nftStakingProgram.account.booster.fetch(booster);
+
+  const detachBoosterIx = await nftStakingProgram.methods
+    .detachBooster()
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+      nftVaultAccount,
+      boosterConfig,
+      booster,
+      owner: user,
+      boosterMint,
+      boosterVault: vault,
+      ownerBoosterAccount: await findAssociatedTokenAddress(user, boosterMint),
+      userPoints: await findUserPointsKey(poolInfo.key, user),
+      systemProgram: anchor.web3.SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+    })
+    .instruction();
+
+  return detachBoosterIx;
+}
+
+export async function findGuildKey(poolInfoKey: PublicKey, authority: PublicKey) {
+  return (
+    await PublicKey.findProgramAddress(
+      [poolInfoKey.toBuffer(), authority.toBuffer(), Buffer.from(GUILD_SEED)],
+      NFT_STAKING_PROGRAM_ID
+    )
+  )[0];
+}
+
+export async function findGuildPositionKey(nftVaultAccount: PublicKey) {
+  return (
+    await PublicKey.findProgramAddress(
+      [nftVaultAccount.toBuffer(), Buffer.from(GUILD_POSITION_SEED)],
+      NFT_STAKING_PROGRAM_ID
+    )
+  )[0];
+}
+
+export async function createGuildIx(
+  authority: PublicKey,
+  members: GuildShare[],
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const createGuildIx = await nftStakingProgram.methods
+    .createGuild(
+      members.map(({ member, shares }) => ({
+        member,
+        shares: new anchor.BN(shares),
+      }))
+    )
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+      guild: await findGuildKey(poolInfo.key, authority),
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .instruction();
+
+  return createGuildIx;
+}
+
+export async function updateGuildMembersIx(
+  authority: PublicKey,
+  members: GuildShare[],
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const guild = await findGuildKey(poolInfo.key, authority);
+
+  const updateGuildMembersIx = await nftStakingProgram.methods
+    .updateGuildMembers(
+      members.map(({ member, shares }) => ({
+        member,
+        shares: new anchor.BN(shares),
+      }))
+    )
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+      guild,
+      guildPoints: await findUserPointsKey(poolInfo.key, guild),
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .instruction();
+
+  return updateGuildMembersIx;
+}
+
+export async function depositToGuildIx(
+  member: PublicKey,
+  guild: PublicKey,
+  nftVaultAccount: PublicKey,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const depositToGuildIx = await nftStakingProgram.methods
+    .depositToGuild()
+    .accounts({
+      member,
+      poolInfo: poolInfo.key,
+      guild,
+      nftVaultAccount,
+      guildPosition: await findGuildPositionKey(nftVaultAccount),
+      userPoints: await findUserPointsKey(poolInfo.key, member),
+      guildPoints: await findUserPointsKey(poolInfo.key, guild),
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .instruction();
+
+  return depositToGuildIx;
+}
+
+export async function withdrawFromGuildIx(
+  authority: PublicKey, // depositing member or admin
+  nftVaultAccount: PublicKey,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const guildPosition = await findGuildPositionKey(nftVaultAccount);
+  const { guild, member } =
+    await nftStakingProgram.account.guildPosition.fetch(guildPosition);
+
+  const withdrawFromGuildIx = await nftStakingProgram.methods
+    .withdrawFromGuild()
+    .accounts({
+      authority,
+      poolInfo: poolInfo.key,
+      guild,
+      nftVaultAccount,
+      guildPosition,
+      member,
+      userPoints: await findUserPointsKey(poolInfo.key, member),
+      guildPoints: await findUserPointsKey(poolInfo.key, guild),
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .instruction();
+
+  return withdrawFromGuildIx;
+}
+
+export async function claimGuildShareIx(
+  member: PublicKey,
+  guild: PublicKey,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const nftStakingProgram = new anchor.Program(
+    nftStakingIDL,
+    NFT_STAKING_PROGRAM_ID,
+    provider
+  );
+
+  const claimGuildShareIx = await nftStakingProgram.methods
+    .claimGuildShare()
+    .accounts({
+      member,
+      poolInfo: poolInfo.key,
+      guild,
+      userPoints: await findUserPointsKey(poolInfo.key, member),
+      guildPoints: await findUserPointsKey(poolInfo.key, guild),
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .instruction();
+
+  return claimGuildShareIx;
 }
@@ -20,6 +20,7 @@ const PROVE_TOKEN_VAULT_SEED = "prove_token_vault";
 const FARM_INFO_SEED = "farm-info";
 const MINING_VAULT_SEED = "vault";
 const MINING_TOKEN_SEED = "mining-token";
+export const POOL_INFO_LEN = 512; // PoolInfo::LEN, includes reserved space
 
 export class RarityInfo {
   constructor(
@@ -87,7 +88,9 @@ export class PoolInfo {
     public rarityInfo: PublicKey,
     public proveTokenAuthority?: PublicKey,
     public proveTokenVault?: PublicKey,
-    public totalStakedAmount?: number
+    public totalStakedAmount?: number,
+    public gateMint?: PublicKey,
+    public gateMinBalance?: number
   ) {}
 
   async findKeyAndAuthorityAndVault() {
@@ -152,7 +155,7 @@ export async function fetchAll(
   }
 
   const poolInfoSizeFilter: DataSizeFilter = {
-    dataSize: nftStakingProgram.account.poolInfo.size,
+    dataSize: POOL_INFO_LEN,
   };
   let filters: (anchor.web3.MemcmpFilter | anchor.web3.DataSizeFilter)[] = [
     poolInfoSizeFilter,
@@ -193,7 +196,9 @@ export async function fetchAll(
           rarityInfo.key,
           currentPoolInfo.account.proveTokenAuthority,
           currentPoolInfo.account.proveTokenVault,
-          Number(currentPoolInfo.account.totalLocked)
+          Number(currentPoolInfo.account.totalLocked),
+          currentPoolInfo.account.gateMint,
+          Number(currentPoolInfo.account.gateMinBalance)
         );
 
         allInfos.push(new AllInfo(rarityInfo, poolInfo));
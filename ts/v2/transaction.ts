@@ -1,6 +1,6 @@
 import * as anchor from "@project-serum/anchor";
 import { PublicKey, SystemProgram, Transaction } from "@solana/web3.js";
-import { createATAWithoutCheckIx } from "./utils";
+import { createATAIdempotentIx } from "./utils";
 import { NFT_RARITY_PROGRAM_ID } from "./ids";
 import { PoolInfo, RarityInfo } from "./poolInfos";
 import * as ix from "./instruction";
@@ -90,7 +90,7 @@ export async function initiatePoolInfoTxn(
   }
   await poolInfo.findKeyAndAuthorityAndVault();
 
-  const createAtaIx = await createATAWithoutCheckIx(
+  const createAtaIx = await createATAIdempotentIx(
     poolInfo.proveTokenAuthority,
     poolInfo.proveTokenMint,
     poolInfo.admin
@@ -110,22 +110,54 @@ export async function initiatePoolInfoTxn(
   return txn;
 }
 
-export async function updatePoolInfoAdminTxn(
+export async function proposePoolInfoAdminTxn(
   newAdmin: PublicKey,
   poolInfo: PoolInfo,
   provider: anchor.AnchorProvider
 ) {
-  const updatePoolInfoAdminIx = await ix.updatePoolInfoAdminIx(
+  const proposePoolInfoAdminIx = await ix.proposePoolInfoAdminIx(
     newAdmin,
     poolInfo,
     provider
   );
   const txn = new Transaction();
-  txn.add(updatePoolInfoAdminIx);
+  txn.add(proposePoolInfoAdminIx);
 
   return txn;
 }
 
+export async function acceptPoolInfoAdminTxn(
+  newAdmin: PublicKey,
+  poolInfo: PoolInfo,
+  provider: anchor.AnchorProvider
+) {
+  const acceptPoolInfoAdminIx = await ix.acceptPoolInfoAdminIx(
+    newAdmin,
+    poolInfo,
+    provider
+  );
+  const txn = new Transaction();
+  txn.add(acceptPoolInfoAdminIx);
+
+  return txn;
+}
+
+export async function forceUnstakeTxn(
+  admin: PublicKey,
+  poolInfo: PoolInfo,
+  nftMintList: PublicKey[],
+  provider: anchor.AnchorProvider
+) {
+  const allTxn: Transaction[] = [];
+  for (let nftMint of nftMintList) {
+    const txn = new Transaction();
+    txn.add(await ix.forceUnstakeIx(admin, poolInfo, nftMint, provider));
+    allTxn.push(txn);
+  }
+
+  return allTxn;
+}
+
 export async function stakeTxn(
   poolInfo: PoolInfo,
   user: PublicKey,
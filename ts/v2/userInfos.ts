@@ -4,7 +4,6 @@ import {
   PublicKey,
   MemcmpFilter,
   GetProgramAccountsConfig,
-  DataSizeFilter,
 } from "@solana/web3.js";
 import { NFT_RARITY_PROGRAM_ID, NFT_STAKING_PROGRAM_ID } from "./ids";
 import { find, min } from "lodash";
@@ -12,6 +11,40 @@ import { IDL as nftStakingIDL } from "../../target/types/nft_staking";
 import { IDL as nftRarityIDL } from "../../target/types/nft_rarity";
 import { AllInfo } from "./poolInfos";
 
+export const NFT_VAULT_LEN = 512; // NftVaultAccount::LEN, includes reserved space
+
+// Vaults staked under older layouts are shorter than NFT_VAULT_LEN until unstake migrates them,
+// so match on the discriminator and read the user, poolInfo and nftMint every layout starts with
+async function fetchNFTVaults(
+  filters: MemcmpFilter[],
+  provider: anchor.Provider
+) {
+  const discriminatorMemcmp: MemcmpFilter = {
+    memcmp: {
+      offset: 0,
+      bytes: anchor.utils.bytes.bs58.encode(
+        anchor.BorshAccountsCoder.accountDiscriminator("NftVaultAccount")
+      ),
+    },
+  };
+  const config: GetProgramAccountsConfig = {
+    filters: [discriminatorMemcmp, ...filters],
+  };
+  const accounts = await provider.connection.getProgramAccounts(
+    NFT_STAKING_PROGRAM_ID,
+    config
+  );
+  return accounts.map(
+    ({ pubkey, account }) =>
+      new NFTVault(
+        pubkey,
+        new PublicKey(account.data.slice(8, 40)),
+        new PublicKey(account.data.slice(40, 72)),
+        new PublicKey(account.data.slice(72, 104))
+      )
+  );
+}
+
 export class NFTVault {
   constructor(
     public key: PublicKey,
@@ -26,38 +59,15 @@ export class UserInfo {
 }
 
 export async function fetchUser(wallet: PublicKey, provider: anchor.Provider) {
-  const nftStakingProgram = new anchor.Program(
-    nftStakingIDL,
-    NFT_STAKING_PROGRAM_ID,
-    provider
-  );
-
   const ownerIdMemcmp: MemcmpFilter = {
     memcmp: {
       offset: 8,
       bytes: wallet.toString(),
     },
   };
-  const nftVaultSizeFilter: DataSizeFilter = {
-    dataSize: nftStakingProgram.account.nftVaultAccount.size,
-  };
-  let filters = [ownerIdMemcmp, nftVaultSizeFilter];
   // console.log("fetch NFT vault");
-  const allNftVaults = await nftStakingProgram.account.nftVaultAccount.all(
-    filters
-  );
-
   const userInfo = new UserInfo(wallet);
-  for (let nftVault of allNftVaults) {
-    userInfo.staked.push(
-      new NFTVault(
-        nftVault.publicKey,
-        nftVault.account.user,
-        nftVault.account.poolInfo,
-        nftVault.account.nftMint
-      )
-    );
-  }
+  userInfo.staked = await fetchNFTVaults([ownerIdMemcmp], provider);
 
   return userInfo;
 }
@@ -66,34 +76,11 @@ export async function fetchAllNFTVault(
   provider: anchor.Provider,
   poolInfoKey: PublicKey
 ) {
-  const nftStakingProgram = new anchor.Program(
-    nftStakingIDL,
-    NFT_STAKING_PROGRAM_ID,
-    provider
-  );
   const ownerIdMemcmp: MemcmpFilter = {
     memcmp: {
       offset: 40,
       bytes: poolInfoKey.toString(),
     },
   };
-  const nftVaultSizeFilter: DataSizeFilter = {
-    dataSize: nftStakingProgram.account.nftVaultAccount.size,
-  };
-  let filters = [ownerIdMemcmp, nftVaultSizeFilter];
-  const allNftVaults = await nftStakingProgram.account.nftVaultAccount.all(
-    filters
-  );
-  let staked: NFTVault[] = [];
-  for (let nftVault of allNftVaults) {
-    staked.push(
-      new NFTVault(
-        nftVault.publicKey,
-        nftVault.account.user,
-        nftVault.account.poolInfo,
-        nftVault.account.nftMint
-      )
-    );
-  }
-  return staked;
+  return await fetchNFTVaults([ownerIdMemcmp], provider);
 }
@@ -3,7 +3,6 @@ import {
   Transaction,
   TransactionInstruction,
   SystemProgram,
-  SYSVAR_RENT_PUBKEY,
 } from "@solana/web3.js";
 import NodeWallet from "@project-serum/anchor/dist/cjs/nodewallet";
 import {
@@ -11,10 +10,9 @@ import {
   ASSOCIATED_TOKEN_PROGRAM_ID,
 } from "@solana/spl-token";
 import * as fs from "fs";
+import { createHash } from "crypto";
+import BN from "bn.js";
 import { AnchorProvider } from "@project-serum/anchor";
-const ATA_INIT_PROGRAM_ID = new PublicKey(
-  "9tiP8yZcekzfGzSBmp7n9LaDHRjxP2w7wJj8tpPJtfG"
-);
 
 export class RawRarityInfo {
   constructor(
@@ -51,7 +49,8 @@ export async function findAssociatedTokenAddress(
   )[0];
 }
 
-export async function createATAWithoutCheckIx(
+// CreateIdempotent of the associated token account program, a no-op if the ATA exists
+export async function createATAIdempotentIx(
   wallet: PublicKey,
   mint: PublicKey,
   payer?: PublicKey
@@ -64,16 +63,15 @@ export async function createATAWithoutCheckIx(
   const keys = [
     { pubkey: payer, isSigner: true, isWritable: true },
     { pubkey: ATA, isSigner: false, isWritable: true },
-    { pubkey: wallet, isSigner: false, isWritable: true },
+    { pubkey: wallet, isSigner: false, isWritable: false },
     { pubkey: mint, isSigner: false, isWritable: false },
     { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
     { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
-    { pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false },
-    { pubkey: ASSOCIATED_TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
   ];
   return new TransactionInstruction({
     keys,
-    programId: ATA_INIT_PROGRAM_ID,
+    programId: ASSOCIATED_TOKEN_PROGRAM_ID,
+    data: Buffer.from([1]),
   });
 }
 
@@ -129,3 +127,54 @@ export async function sendAndLog(
     }
   }
 }
+
+export interface DistributionEntry {
+  user: PublicKey;
+  amount: number;
+}
+
+function sha256(...buffers: Buffer[]) {
+  return createHash("sha256").update(Buffer.concat(buffers)).digest();
+}
+
+// leaf is (leafIndex, user, amount) and pairs are hashed in sorted order, same as the program
+export function buildDistributionTree(entries: DistributionEntry[]) {
+  const leaves = entries.map((entry, index) =>
+    sha256(
+      new BN(index).toArrayLike(Buffer, "le", 8),
+      entry.user.toBuffer(),
+      new BN(entry.amount).toArrayLike(Buffer, "le", 8)
+    )
+  );
+
+  const layers: Buffer[][] = [leaves];
+  while (layers[layers.length - 1].length > 1) {
+    const layer = layers[layers.length - 1];
+    const next: Buffer[] = [];
+    for (let i = 0; i < layer.length; i += 2) {
+      if (i + 1 == layer.length) {
+        next.push(layer[i]);
+      } else if (Buffer.compare(layer[i], layer[i + 1]) <= 0) {
+        next.push(sha256(layer[i], layer[i + 1]));
+      } else {
+        next.push(sha256(layer[i + 1], layer[i]));
+      }
+    }
+    layers.push(next);
+  }
+
+  const proofs = leaves.map((_, leafIndex) => {
+    const proof: Buffer[] = [];
+    let index = leafIndex;
+    for (let layer of layers.slice(0, -1)) {
+      const sibling = index % 2 == 0 ? index + 1 : index - 1;
+      if (sibling < layer.length) {
+        proof.push(layer[sibling]);
+      }
+      index = Math.floor(index / 2);
+    }
+    return proof;
+  });
+
+  return { root: layers[layers.length - 1][0], proofs };
+}